@@ -1,5 +1,8 @@
+use alloy::primitives::{utils::parse_units, Address, U256};
+use alloy_chains::NamedChain;
 use clap::Parser;
-use std::str::FromStr;
+use shared::inventory::{BalanceSource, InventoryToken};
+use std::{net::SocketAddr, str::FromStr};
 
 #[derive(Debug, Clone)]
 pub enum StrategyType {
@@ -19,13 +22,87 @@ impl FromStr for StrategyType {
     }
 }
 
+/// A token added to the inventory of the strategy on one of the chains.
+#[derive(Debug, Clone)]
+pub struct InventoryTokenArg {
+    pub chain: NamedChain,
+    pub token: InventoryToken,
+}
+
+impl FromStr for InventoryTokenArg {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split(':').collect();
+        let [chain_id, address, decimals, min_amount_in, max_amount_in, source @ ..] =
+            parts.as_slice()
+        else {
+            return Err(format!(
+                "Expected CHAIN_ID:ADDRESS:DECIMALS:MIN:MAX[:SOURCE], got {}",
+                s
+            ));
+        };
+        let chain_id = chain_id.parse::<u64>().map_err(|e| e.to_string())?;
+        let chain = NamedChain::try_from(chain_id)
+            .map_err(|_| format!("Unknown chain ID: {}", chain_id))?;
+        let address = Address::from_str(address).map_err(|e| e.to_string())?;
+        let decimals = decimals.parse::<u8>().map_err(|e| e.to_string())?;
+        let parse_amount = |amount: &str| -> Result<U256, String> {
+            parse_units(amount, decimals)
+                .map(Into::into)
+                .map_err(|e| e.to_string())
+        };
+        let source = match source {
+            [] => BalanceSource::default(),
+            [source] => source.parse().map_err(|e: eyre::Report| e.to_string())?,
+            _ => return Err(format!("Unexpected inventory token fields in {}", s)),
+        };
+        let token = InventoryToken::new(address, decimals)
+            .with_source(source)
+            .with_min_amount_in(parse_amount(min_amount_in)?)
+            .with_max_amount_in(parse_amount(max_amount_in)?);
+        Ok(Self { chain, token })
+    }
+}
+
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 pub struct Args {
-    #[arg(short, long)]
-    pub chain_id: u64,
+    /// The chains to run the strategy on, in a single engine (e.g. 1,8453)
+    #[arg(short, long = "chain-id", required = true, value_delimiter = ',')]
+    pub chain_ids: Vec<u64>,
+    /// Save the last block processed by the strategy to this file, and backfill
+    /// the logs from there on startup
     #[arg(long)]
     pub checkpoint_path: Option<String>,
     #[arg(short, long, default_value = "unitriarb")]
     pub strategy: StrategyType,
+    /// Resync strategy state every N blocks
+    #[arg(long)]
+    pub sync_every_blocks: Option<u64>,
+    /// Resync strategy state every N seconds
+    #[arg(long)]
+    pub sync_every_secs: Option<u64>,
+    /// Serve Prometheus metrics on this address (e.g. 0.0.0.0:9000)
+    #[arg(long)]
+    pub metrics_addr: Option<SocketAddr>,
+    /// Simulate actions instead of sending transactions, and journal the results
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Journal dry run results to this file (JSON lines) instead of the database
+    #[arg(long, requires = "dry_run")]
+    pub journal_path: Option<String>,
+    /// Connect over HTTP and poll for new logs every N milliseconds, for
+    /// endpoints without websocket support
+    #[arg(long)]
+    pub poll_interval_ms: Option<u64>,
+    /// Largest amount of ETH traded through a single cycle (e.g. 0.5)
+    #[arg(long)]
+    pub max_amount_in: Option<String>,
+    /// Token cycles can also start and end with on one of the chains, as
+    /// CHAIN_ID:ADDRESS:DECIMALS:MIN:MAX[:SOURCE], with amounts in whole tokens
+    /// and SOURCE either executor (default) or wallet. Can be repeated
+    /// (e.g. 8453:0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913:6:10:5000)
+    #[arg(long = "inventory-token")]
+    pub inventory_tokens: Vec<InventoryTokenArg>,
 }
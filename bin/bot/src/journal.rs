@@ -0,0 +1,59 @@
+use alloy_chains::Chain;
+use async_trait::async_trait;
+use db::{
+    establish_connection, models::NewDbDryRunResult,
+    queries::dry_run_result::insert_dry_run_result, run_migrations,
+};
+use diesel::pg::PgConnection;
+use engine::executors::dry_run_executor::{DryRunResult, Journal};
+use eyre::{eyre, Result};
+use std::sync::{Arc, Mutex};
+
+/// Journal inserting dry run results into the `dry_run_results` table.
+pub struct DbJournal {
+    conn: Arc<Mutex<PgConnection>>,
+    chain: String,
+}
+
+impl DbJournal {
+    /// Connects to the database and runs pending migrations, so that the
+    /// results table exists.
+    pub fn new(database_url: &str, chain: Chain) -> Result<Self> {
+        let mut conn = establish_connection(database_url);
+        run_migrations(&mut conn).map_err(|e| eyre!("failed to run migrations: {}", e))?;
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+            chain: chain.to_string(),
+        })
+    }
+}
+
+#[async_trait]
+impl Journal for DbJournal {
+    async fn record(&self, result: &DryRunResult) -> Result<()> {
+        let new_result = NewDbDryRunResult {
+            chain: self.chain.clone(),
+            executor: result.action.clone(),
+            block_number: result.block_number as i64,
+            from_address: result.from.map(|address| address.to_string()),
+            to_address: result.to.map(|address| address.to_string()),
+            value: result.value.to_string(),
+            success: result.success,
+            gas_used: result.gas_used.map(|gas| gas as i64),
+            gas_price: result.gas_price.map(|price| price.to_string()),
+            gas_cost: result.gas_cost.map(|cost| cost.to_string()),
+            expected_profit: result.expected_profit.map(|profit| profit.to_string()),
+            net_profit: result.net_profit.map(|profit| profit.to_string()),
+            revert_reason: result.revert_reason.clone(),
+            drop_reason: result.drop_reason.clone(),
+        };
+        // diesel blocks on the database, off the async runtime
+        let conn = self.conn.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut conn = conn.lock().unwrap();
+            insert_dry_run_result(&mut conn, &new_result)
+        })
+        .await??;
+        Ok(())
+    }
+}
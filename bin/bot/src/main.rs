@@ -1,11 +1,24 @@
 mod cli;
+mod journal;
 mod strategies;
-use alloy_chains::Chain;
+use alloy::primitives::utils::parse_ether;
+use alloy_chains::{Chain, NamedChain};
 use clap::Parser;
 use cli::{Args, StrategyType};
 use dotenv::dotenv;
+use engine::{
+    checkpoint::CheckpointStore,
+    executors::dry_run_executor::{FileJournal, Journal},
+    supervisor::cancel_on_shutdown_signal,
+    sync::SyncPolicy,
+};
 use eyre::Result;
-use provider::get_default_signer_provider_arc;
+use journal::DbJournal;
+use metrics_exporter_prometheus::PrometheusBuilder;
+use provider::{get_default_http_signer_provider_arc, get_default_signer_provider_arc};
+use shared::inventory::InventoryToken;
+use std::{collections::HashMap, sync::Arc, time::Duration};
+use strategies::{BotOptions, ChainSetup};
 use tracing::info;
 use tracing_subscriber::{fmt, prelude::*, EnvFilter};
 
@@ -26,31 +39,111 @@ async fn main() -> Result<()> {
         .init();
 
     let args = Args::parse();
+
+    if let Some(addr) = args.metrics_addr {
+        PrometheusBuilder::new()
+            .with_http_listener(addr)
+            .install()?;
+        engine::metrics::describe();
+        info!("Serving metrics on http://{}/metrics", addr);
+    }
+
     let db_url = std::env::var("DATABASE_URL").expect("DATABASE_URL not set");
-    let chain = Chain::try_from(args.chain_id).expect("Invalid chain ID");
-    let provider = get_default_signer_provider_arc(chain).await;
+    let poll_interval = args.poll_interval_ms.map(Duration::from_millis);
+    if let Some(interval) = poll_interval {
+        info!("Polling for new logs every {:?} over HTTP", interval);
+    }
+
+    // results of every chain go to the same file, each tagged with its chain id
+    let file_journal: Option<Arc<dyn Journal>> = match &args.journal_path {
+        Some(path) => {
+            info!("Dry run: journaling results to {}", path);
+            Some(Arc::new(FileJournal::open(path).await?))
+        }
+        None => None,
+    };
+
+    let mut chains = vec![];
+    for chain_id in &args.chain_ids {
+        let named_chain = NamedChain::try_from(*chain_id).expect("Invalid chain ID");
+        let chain = Chain::from(named_chain);
+        let provider = match poll_interval {
+            Some(_) => get_default_http_signer_provider_arc(chain).await,
+            None => get_default_signer_provider_arc(chain).await,
+        };
+        let dry_run: Option<Arc<dyn Journal>> = match (args.dry_run, &file_journal) {
+            (false, _) => None,
+            (true, Some(journal)) => Some(journal.clone()),
+            (true, None) => {
+                info!(
+                    "Dry run: journaling {} results to the database",
+                    named_chain
+                );
+                Some(Arc::new(DbJournal::new(&db_url, chain)?))
+            }
+        };
+        info!("Running on {}", named_chain);
+        chains.push(ChainSetup {
+            chain: named_chain,
+            provider,
+            dry_run,
+        });
+    }
+
+    // checkpoints are keyed by strategy and chain
+    let checkpoints = match &args.checkpoint_path {
+        Some(path) => {
+            info!("Checkpointing processed blocks to {}", path);
+            Some(Arc::new(CheckpointStore::open(path, "bot")?))
+        }
+        None => None,
+    };
+
+    let max_amount_in = args
+        .max_amount_in
+        .map(|amount| parse_ether(&amount))
+        .transpose()?;
+    let mut inventory_tokens: HashMap<NamedChain, Vec<InventoryToken>> = HashMap::new();
+    for arg in args.inventory_tokens {
+        info!("Trading {:?} on {}", arg.token, arg.chain);
+        inventory_tokens
+            .entry(arg.chain)
+            .or_default()
+            .push(arg.token);
+    }
+    let options = BotOptions {
+        poll_interval,
+        checkpoints,
+        max_amount_in,
+        inventory_tokens,
+    };
+
+    let mut sync_policy = SyncPolicy::default();
+    if let Some(blocks) = args.sync_every_blocks {
+        sync_policy = sync_policy.with_block_interval(blocks);
+    }
+    if let Some(secs) = args.sync_every_secs {
+        sync_policy = sync_policy.with_time_interval(Duration::from_secs(secs));
+    }
 
     match args.strategy {
         StrategyType::GeneralizedArb => {
             info!("Initializing GeneralizedArb strategy...");
-            let engine =
-                strategies::init_generalized_arbitrage_bot(chain, provider.clone(), db_url);
+            let engine = strategies::init_generalized_arbitrage_bot(chains, db_url, options)
+                .with_sync_policy(sync_policy);
+            cancel_on_shutdown_signal(engine.shutdown_token());
 
-            if let Ok(mut set) = engine.run().await {
-                while let Some(res) = set.join_next().await {
-                    info!("res: {:?}", res);
-                }
-            }
+            let report = engine.run().await;
+            info!("{}", report);
         }
         StrategyType::BaseArb => {
             info!("Initializing BaseArb strategy...");
-            let engine = strategies::init_base_arbitrage_bot(chain, provider.clone(), db_url);
+            let engine = strategies::init_base_arbitrage_bot(chains, db_url, options)
+                .with_sync_policy(sync_policy);
+            cancel_on_shutdown_signal(engine.shutdown_token());
 
-            if let Ok(mut set) = engine.run().await {
-                while let Some(res) = set.join_next().await {
-                    info!("res: {:?}", res);
-                }
-            }
+            let report = engine.run().await;
+            info!("{}", report);
         }
     }
 
@@ -1,10 +1,11 @@
 use alloy::{
     eips::BlockNumberOrTag,
+    primitives::U256,
     providers::Provider,
     rpc::types::{Filter, Log},
     sol_types::SolEvent,
 };
-use alloy_chains::Chain;
+use alloy_chains::{Chain, NamedChain};
 use amms::bindings::{
     iaerodromepool::IAerodromePool, iuniswapv2pool::IUniswapV2Pool, iuniswapv3pool::IUniswapV3Pool,
 };
@@ -13,24 +14,99 @@ use base_arb_strategy::{
     types::{Action as BaseArbAction, Event as BaseArbEvent},
 };
 use engine::{
+    chain::{ChainCollector, ChainStrategy, Chained},
+    checkpoint::{checkpoint_key, CheckpointStore},
     collectors::multi_log_collector::MultiLogCollector,
+    delivery::{DeliveryPolicy, SubscriptionConfig},
     engine::Engine,
-    executors::{encoded_tx_executor::EncodedTxExecutor, mempool_executor::MempoolExecutor},
-    types::{CollectorMap, ExecutorMap},
+    executors::{
+        dry_run_executor::{DryRunExecutor, Journal},
+        encoded_tx_executor::{EncodedTxExecutor, SubmitEncodedTx},
+        gas_bidder::GasBidder,
+        mempool_executor::{MempoolExecutor, SubmitTxToMempool},
+        tx_manager::TxManager,
+    },
+    types::{CollectorEvent, CollectorMap, Executor, ExecutorMap, Strategy},
 };
 use generalized_arb_strategy::{
     strategy::GeneralizedArb,
     types::{Action as GeneralizedArbAction, Event as GeneralizedArbEvent},
 };
-use std::sync::Arc;
+use provider::get_default_wallet;
+use shared::inventory::InventoryToken;
+use std::{collections::HashMap, sync::Arc, time::Duration};
+use tracing::info;
+
+/// One of the chains a bot runs on.
+pub struct ChainSetup<P> {
+    pub chain: NamedChain,
+    pub provider: Arc<P>,
+    /// Journal to record simulated actions to, instead of sending transactions
+    pub dry_run: Option<Arc<dyn Journal>>,
+}
+
+/// Options shared by every bot.
+#[derive(Default)]
+pub struct BotOptions {
+    /// Poll for new logs at this interval instead of subscribing
+    pub poll_interval: Option<Duration>,
+    /// Store of the last block processed by each strategy, from which logs
+    /// are backfilled on startup
+    pub checkpoints: Option<Arc<CheckpointStore>>,
+    /// Largest amount of WETH traded through a single cycle
+    pub max_amount_in: Option<U256>,
+    /// Tokens cycles can also start and end with, on each chain
+    pub inventory_tokens: HashMap<NamedChain, Vec<InventoryToken>>,
+}
+
+/// Returns the subscription of a strategy to the pool logs of `chain`.
+///
+/// The collected logs each carry the latest state of their pool: the reserves
+/// in `Sync` logs of UniswapV2 and Aerodrome pools, the price, liquidity and
+/// tick in `Swap` logs of UniswapV3 pools. So only the latest log of each pool
+/// is kept when the strategy falls behind.
+fn pool_state_subscription(chain: NamedChain) -> SubscriptionConfig {
+    SubscriptionConfig::new(DeliveryPolicy::Coalesce).with_chains([chain])
+}
+
+/// Returns the block the logs of `strategy` on `chain` should be backfilled
+/// from, right after its last checkpoint.
+fn start_block(options: &BotOptions, strategy: &str, chain: NamedChain) -> Option<u64> {
+    let key = checkpoint_key(strategy, Some(chain));
+    let checkpoint = options.checkpoints.as_ref()?.get(&key);
+    info!("Last checkpoint of {}: {:?}", key, checkpoint);
+    checkpoint.map(|block| block + 1)
+}
 
 pub fn init_generalized_arbitrage_bot<P: Provider + Clone + 'static>(
-    chain: Chain,
-    provider: Arc<P>,
+    chains: Vec<ChainSetup<P>>,
     db_url: String,
-) -> Engine<GeneralizedArbEvent, GeneralizedArbAction> {
-    let mut engine: Engine<GeneralizedArbEvent, GeneralizedArbAction> = Engine::default();
+    options: BotOptions,
+) -> Engine<Chained<GeneralizedArbEvent>, Chained<GeneralizedArbAction>> {
+    let mut engine: Engine<Chained<GeneralizedArbEvent>, Chained<GeneralizedArbAction>> =
+        Engine::default();
+    if let Some(store) = &options.checkpoints {
+        engine = engine.with_checkpoints(store.clone());
+    }
+    for setup in chains {
+        add_generalized_arbitrage_chain(&mut engine, setup, db_url.clone(), &options);
+    }
+    engine
+}
 
+/// Adds a GeneralizedArb strategy, along with its collector and executor, for
+/// a single chain.
+fn add_generalized_arbitrage_chain<P: Provider + Clone + 'static>(
+    engine: &mut Engine<Chained<GeneralizedArbEvent>, Chained<GeneralizedArbAction>>,
+    ChainSetup {
+        chain: named_chain,
+        provider,
+        dry_run,
+    }: ChainSetup<P>,
+    db_url: String,
+    options: &BotOptions,
+) {
+    let chain = Chain::from(named_chain);
     let uniswap_v2_filter = Filter::new()
         .from_block(BlockNumberOrTag::Latest)
         .event(IUniswapV2Pool::Sync::SIGNATURE);
@@ -40,48 +116,150 @@ pub fn init_generalized_arbitrage_bot<P: Provider + Clone + 'static>(
 
     let filters = vec![uniswap_v2_filter, uniswap_v3_filter];
 
-    let strategy = GeneralizedArb::new(chain, provider.clone(), db_url);
-    engine.add_strategy(Box::new(strategy));
+    let mut strategy = GeneralizedArb::new(chain, provider.clone(), db_url);
+    if let Some(max_amount_in) = options.max_amount_in {
+        strategy = strategy.with_max_amount_in(max_amount_in);
+    }
+    let inventory_tokens = options.inventory_tokens.get(&named_chain);
+    for token in inventory_tokens.into_iter().flatten() {
+        strategy = strategy.with_inventory_token(token.clone());
+    }
+    let pool_set = strategy.pool_set();
+    // checkpoints are saved under the chain-qualified name of the strategy
+    let strategy = ChainStrategy::new(named_chain, Box::new(strategy));
+    let start_block = start_block(options, strategy.name(), named_chain);
+    engine.add_strategy_with(Box::new(strategy), pool_state_subscription(named_chain));
 
-    let mempool_executor = Box::new(MempoolExecutor::new(provider.clone()));
-    let mempool_executor =
-        ExecutorMap::new(
-            mempool_executor,
-            |action: GeneralizedArbAction| match action {
-                GeneralizedArbAction::SubmitTx(tx) => Some(tx),
-            },
-        );
+    let gas_bidder = GasBidder::new(provider.clone()).with_l1_data_fee(chain.is_optimism());
+    let mempool_executor: Box<dyn Executor<SubmitTxToMempool>> = match dry_run {
+        Some(journal) => {
+            Box::new(DryRunExecutor::new(provider.clone(), journal).with_gas_bidder(gas_bidder))
+        }
+        None => {
+            // transactions are tracked by the manager, so the executor never waits for receipts
+            let tx_manager = Arc::new(TxManager::new(provider.clone(), get_default_wallet()));
+            tx_manager.spawn();
+            Box::new(
+                MempoolExecutor::new(provider.clone())
+                    .with_tx_manager(tx_manager)
+                    .with_gas_bidder(gas_bidder),
+            )
+        }
+    };
+    // every executor receives the actions of every chain
+    let mempool_executor = ExecutorMap::new(
+        mempool_executor,
+        move |action: Chained<GeneralizedArbAction>| match action.on(named_chain)? {
+            GeneralizedArbAction::SubmitTx(tx) => Some(tx),
+        },
+    );
     engine.add_executor(Box::new(mempool_executor));
 
-    let collector = Box::new(MultiLogCollector::new(provider, filters));
-    let collector = CollectorMap::new(collector, |event: Log| GeneralizedArbEvent::Log(event));
+    // only the logs of the pools tracked by the strategy are collected
+    let mut collector = MultiLogCollector::new(provider, filters).with_pool_set(pool_set);
+    if let Some(interval) = options.poll_interval {
+        collector = collector.with_polling(interval);
+    }
+    if let Some(block) = start_block {
+        collector = collector.with_start_block(block);
+    }
+    let collector = Box::new(collector);
+    let collector = CollectorMap::new(collector, |event: CollectorEvent<Log>| match event {
+        CollectorEvent::Event(log) => GeneralizedArbEvent::Log(log),
+        CollectorEvent::Restarted(restarted) => GeneralizedArbEvent::CollectorRestarted(restarted),
+        CollectorEvent::Reorg(reorg) => GeneralizedArbEvent::Reorg(reorg),
+        CollectorEvent::BlockEnd(block) => GeneralizedArbEvent::BlockEnd(block),
+    });
+    let collector = ChainCollector::new(named_chain, Box::new(collector));
     engine.add_collector(Box::new(collector));
-    engine
 }
 
 pub fn init_base_arbitrage_bot<P: Provider + Clone + 'static>(
-    chain: Chain,
-    provider: Arc<P>,
+    chains: Vec<ChainSetup<P>>,
     db_url: String,
-) -> Engine<BaseArbEvent, BaseArbAction> {
-    let mut engine: Engine<BaseArbEvent, BaseArbAction> = Engine::default();
+    options: BotOptions,
+) -> Engine<Chained<BaseArbEvent>, Chained<BaseArbAction>> {
+    let mut engine: Engine<Chained<BaseArbEvent>, Chained<BaseArbAction>> = Engine::default();
+    if let Some(store) = &options.checkpoints {
+        engine = engine.with_checkpoints(store.clone());
+    }
+    for setup in chains {
+        add_base_arbitrage_chain(&mut engine, setup, db_url.clone(), &options);
+    }
+    engine
+}
 
-    let strategy = BaseArb::new(chain, provider.clone(), db_url);
-    engine.add_strategy(Box::new(strategy));
+/// Adds a BaseArb strategy, along with its collector and executor, for a
+/// single chain.
+fn add_base_arbitrage_chain<P: Provider + Clone + 'static>(
+    engine: &mut Engine<Chained<BaseArbEvent>, Chained<BaseArbAction>>,
+    ChainSetup {
+        chain: named_chain,
+        provider,
+        dry_run,
+    }: ChainSetup<P>,
+    db_url: String,
+    options: &BotOptions,
+) {
+    let chain = Chain::from(named_chain);
+    let mut strategy = BaseArb::new(chain, provider.clone(), db_url);
+    if let Some(max_amount_in) = options.max_amount_in {
+        strategy = strategy.with_max_amount_in(max_amount_in);
+    }
+    let inventory_tokens = options.inventory_tokens.get(&named_chain);
+    for token in inventory_tokens.into_iter().flatten() {
+        strategy = strategy.with_inventory_token(token.clone());
+    }
+    let pool_set = strategy.pool_set();
+    // checkpoints are saved under the chain-qualified name of the strategy
+    let strategy = ChainStrategy::new(named_chain, Box::new(strategy));
+    let start_block = start_block(options, strategy.name(), named_chain);
+    engine.add_strategy_with(Box::new(strategy), pool_state_subscription(named_chain));
 
-    let mempool_executor = Box::new(EncodedTxExecutor::new(provider.clone()));
-    let mempool_executor =
-        ExecutorMap::new(mempool_executor, |action: BaseArbAction| match action {
-            BaseArbAction::SubmitEncodedTx(tx) => Some(tx),
-        });
+    let gas_bidder = GasBidder::new(provider.clone()).with_l1_data_fee(chain.is_optimism());
+    let mempool_executor: Box<dyn Executor<SubmitEncodedTx>> = match dry_run {
+        Some(journal) => {
+            Box::new(DryRunExecutor::new(provider.clone(), journal).with_gas_bidder(gas_bidder))
+        }
+        None => {
+            // transactions are tracked by the manager, so the executor never waits for receipts
+            let tx_manager = Arc::new(TxManager::new(provider.clone(), get_default_wallet()));
+            tx_manager.spawn();
+            Box::new(
+                EncodedTxExecutor::new(provider.clone())
+                    .with_tx_manager(tx_manager)
+                    .with_gas_bidder(gas_bidder),
+            )
+        }
+    };
+    // every executor receives the actions of every chain
+    let mempool_executor = ExecutorMap::new(
+        mempool_executor,
+        move |action: Chained<BaseArbAction>| match action.on(named_chain)? {
+            BaseArbAction::ExecuteCycle(arb) => Some(arb.tx),
+        },
+    );
     engine.add_executor(Box::new(mempool_executor));
 
     let aerodrome_filter = Filter::new()
         .from_block(BlockNumberOrTag::Latest)
         .event(IAerodromePool::Sync::SIGNATURE);
     let filters = vec![aerodrome_filter];
-    let collector = Box::new(MultiLogCollector::new(provider, filters));
-    let collector = CollectorMap::new(collector, |event: Log| BaseArbEvent::Log(event));
+    // only the logs of the pools tracked by the strategy are collected
+    let mut collector = MultiLogCollector::new(provider, filters).with_pool_set(pool_set);
+    if let Some(interval) = options.poll_interval {
+        collector = collector.with_polling(interval);
+    }
+    if let Some(block) = start_block {
+        collector = collector.with_start_block(block);
+    }
+    let collector = Box::new(collector);
+    let collector = CollectorMap::new(collector, |event: CollectorEvent<Log>| match event {
+        CollectorEvent::Event(log) => BaseArbEvent::Log(log),
+        CollectorEvent::Restarted(restarted) => BaseArbEvent::CollectorRestarted(restarted),
+        CollectorEvent::Reorg(reorg) => BaseArbEvent::Reorg(reorg),
+        CollectorEvent::BlockEnd(block) => BaseArbEvent::BlockEnd(block),
+    });
+    let collector = ChainCollector::new(named_chain, Box::new(collector));
     engine.add_collector(Box::new(collector));
-    engine
 }
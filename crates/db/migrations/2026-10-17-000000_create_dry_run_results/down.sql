@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_dry_run_results_chain_executor;
+DROP TABLE IF EXISTS dry_run_results;
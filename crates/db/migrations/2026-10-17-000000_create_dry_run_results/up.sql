@@ -0,0 +1,20 @@
+-- Results of actions simulated by the dry-run executor
+CREATE TABLE dry_run_results (
+    id SERIAL PRIMARY KEY,
+    chain VARCHAR NOT NULL,
+    executor VARCHAR NOT NULL,
+    block_number BIGINT NOT NULL,
+    from_address VARCHAR,
+    to_address VARCHAR,
+    value VARCHAR NOT NULL,
+    success BOOLEAN NOT NULL,
+    gas_used BIGINT,
+    gas_price VARCHAR,
+    gas_cost VARCHAR,
+    expected_profit VARCHAR,
+    net_profit VARCHAR,
+    revert_reason VARCHAR,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_dry_run_results_chain_executor ON dry_run_results (chain, executor);
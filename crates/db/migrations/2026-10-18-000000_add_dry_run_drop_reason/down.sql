@@ -0,0 +1 @@
+ALTER TABLE dry_run_results DROP COLUMN IF EXISTS drop_reason;
@@ -0,0 +1,2 @@
+-- Why a successful simulation would not have been submitted
+ALTER TABLE dry_run_results ADD COLUMN drop_reason VARCHAR;
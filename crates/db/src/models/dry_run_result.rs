@@ -0,0 +1,43 @@
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Queryable, Selectable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = crate::schema::dry_run_results)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct DbDryRunResult {
+    pub id: i32,
+    pub chain: String,
+    pub executor: String,
+    pub block_number: i64,
+    pub from_address: Option<String>,
+    pub to_address: Option<String>,
+    pub value: String,
+    pub success: bool,
+    pub gas_used: Option<i64>,
+    pub gas_price: Option<String>,
+    pub gas_cost: Option<String>,
+    pub expected_profit: Option<String>,
+    pub net_profit: Option<String>,
+    pub revert_reason: Option<String>,
+    pub created_at: chrono::NaiveDateTime,
+    pub drop_reason: Option<String>,
+}
+
+#[derive(Insertable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = crate::schema::dry_run_results)]
+pub struct NewDbDryRunResult {
+    pub chain: String,
+    pub executor: String,
+    pub block_number: i64,
+    pub from_address: Option<String>,
+    pub to_address: Option<String>,
+    pub value: String,
+    pub success: bool,
+    pub gas_used: Option<i64>,
+    pub gas_price: Option<String>,
+    pub gas_cost: Option<String>,
+    pub expected_profit: Option<String>,
+    pub net_profit: Option<String>,
+    pub revert_reason: Option<String>,
+    pub drop_reason: Option<String>,
+}
@@ -1,5 +1,6 @@
 pub mod curve_pool;
 pub mod db_pool;
+pub mod dry_run_result;
 pub mod erc4626_vault;
 pub mod exchange;
 pub mod tag;
@@ -8,6 +9,7 @@ pub mod uni_v3_pool;
 
 pub use curve_pool::{DbCurvePool, NewDbCurvePool};
 pub use db_pool::NewDbPool;
+pub use dry_run_result::{DbDryRunResult, NewDbDryRunResult};
 pub use erc4626_vault::{DbErc4626Vault, NewDbErc4626Vault};
 pub use exchange::{DbExchange, NewDbExchange};
 pub use tag::{DbTag, NewDbTag};
@@ -0,0 +1,26 @@
+use crate::models::dry_run_result::{DbDryRunResult, NewDbDryRunResult};
+use crate::schema::dry_run_results;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use diesel::result::Error;
+
+pub fn insert_dry_run_result(
+    conn: &mut PgConnection,
+    new_result: &NewDbDryRunResult,
+) -> Result<DbDryRunResult, Error> {
+    diesel::insert_into(dry_run_results::table)
+        .values(new_result)
+        .get_result(conn)
+}
+
+pub fn get_dry_run_results(
+    conn: &mut PgConnection,
+    chain: &str,
+    executor: &str,
+) -> Result<Vec<DbDryRunResult>, Error> {
+    dry_run_results::table
+        .filter(dry_run_results::chain.eq(chain))
+        .filter(dry_run_results::executor.eq(executor))
+        .order(dry_run_results::id.asc())
+        .load::<DbDryRunResult>(conn)
+}
@@ -1,4 +1,5 @@
 pub mod curve_pool;
+pub mod dry_run_result;
 pub mod erc4626_vault;
 pub mod exchange;
 pub mod tag;
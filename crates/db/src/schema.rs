@@ -29,6 +29,27 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    dry_run_results (id) {
+        id -> Int4,
+        chain -> Varchar,
+        executor -> Varchar,
+        block_number -> Int8,
+        from_address -> Nullable<Varchar>,
+        to_address -> Nullable<Varchar>,
+        value -> Varchar,
+        success -> Bool,
+        gas_used -> Nullable<Int8>,
+        gas_price -> Nullable<Varchar>,
+        gas_cost -> Nullable<Varchar>,
+        expected_profit -> Nullable<Varchar>,
+        net_profit -> Nullable<Varchar>,
+        revert_reason -> Nullable<Varchar>,
+        created_at -> Timestamp,
+        drop_reason -> Nullable<Varchar>,
+    }
+}
+
 diesel::table! {
     erc4626_vaults (id) {
         id -> Int4,
@@ -125,6 +146,7 @@ diesel::joinable!(uni_v3_pools -> tags (tag));
 
 diesel::allow_tables_to_appear_in_same_query!(
     curve_pools,
+    dry_run_results,
     erc4626_vaults,
     exchanges,
     tags,
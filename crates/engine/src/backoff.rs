@@ -0,0 +1,71 @@
+use std::time::Duration;
+
+/// Exponential backoff used when re-establishing subscriptions or restarting
+/// failed components.
+///
+/// The delay starts at `initial_delay` and doubles after every failed attempt,
+/// capped at `max_delay`. When `max_retries` is `None`, retries never stop.
+///
+/// # Example
+/// ```rust,no_run
+/// use std::time::Duration;
+/// use engine::backoff::Backoff;
+///
+/// let backoff = Backoff::default()
+///     .with_initial_delay(Duration::from_millis(250))
+///     .with_max_retries(Some(10));
+/// assert_eq!(backoff.delay(0), Duration::from_millis(250));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    /// The delay before the first retry
+    pub initial_delay: Duration,
+    /// The upper bound for the delay between two retries
+    pub max_delay: Duration,
+    /// The maximum number of consecutive retries, or `None` to retry forever
+    pub max_retries: Option<usize>,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            max_retries: None,
+        }
+    }
+}
+
+impl Backoff {
+    /// Sets the delay before the first retry.
+    pub fn with_initial_delay(mut self, delay: Duration) -> Self {
+        self.initial_delay = delay;
+        self
+    }
+
+    /// Sets the upper bound for the delay between two retries.
+    pub fn with_max_delay(mut self, delay: Duration) -> Self {
+        self.max_delay = delay;
+        self
+    }
+
+    /// Sets the maximum number of consecutive retries.
+    pub fn with_max_retries(mut self, max_retries: Option<usize>) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Returns the delay to wait before the given (zero-based) retry attempt.
+    pub fn delay(&self, attempt: usize) -> Duration {
+        let factor = 1u32.checked_shl(attempt.min(31) as u32).unwrap_or(u32::MAX);
+        self.initial_delay
+            .checked_mul(factor)
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay)
+    }
+
+    /// Returns true if another retry is allowed after `attempt` failed attempts.
+    pub fn can_retry(&self, attempt: usize) -> bool {
+        self.max_retries.is_none_or(|max| attempt < max)
+    }
+}
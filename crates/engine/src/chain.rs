@@ -0,0 +1,209 @@
+use alloy::primitives::Address;
+use alloy_chains::NamedChain;
+use async_trait::async_trait;
+use eyre::Result;
+use tokio_stream::StreamExt;
+
+use crate::outcome::ActionOutcome;
+use crate::types::{Collector, CollectorStream, EngineEvent, Strategy};
+
+/// An event or action tagged with the chain it belongs to, so that a single
+/// [Engine](crate::engine::Engine) can host components for several chains.
+///
+/// Strategies subscribe to a subset of chains through
+/// [SubscriptionConfig::with_chains](crate::delivery::SubscriptionConfig::with_chains).
+#[derive(Debug, Clone)]
+pub struct Chained<T> {
+    /// The chain the event was collected on, or the action is meant for
+    pub chain: NamedChain,
+    /// The event or action itself
+    pub inner: T,
+}
+
+impl<T> Chained<T> {
+    pub fn new(chain: NamedChain, inner: T) -> Self {
+        Self { chain, inner }
+    }
+
+    /// Returns the inner value if it belongs to `chain`.
+    pub fn on(self, chain: NamedChain) -> Option<T> {
+        (self.chain == chain).then_some(self.inner)
+    }
+}
+
+impl<T: EngineEvent> EngineEvent for Chained<T> {
+    fn chain(&self) -> Option<NamedChain> {
+        Some(self.chain)
+    }
+
+    fn block_number(&self) -> Option<u64> {
+        self.inner.block_number()
+    }
+
+    fn is_collector_restart(&self) -> bool {
+        self.inner.is_collector_restart()
+    }
+
+    fn coalesce_key(&self) -> Option<Address> {
+        self.inner.coalesce_key()
+    }
+
+    fn is_control(&self) -> bool {
+        self.inner.is_control()
+    }
+}
+
+/// Returns the name of a component running on `chain`, for example
+/// `base/BaseArb`. Names are built once per component and live as long as the
+/// program, like the static names of the components they wrap.
+fn chain_name(chain: NamedChain, name: &str) -> &'static str {
+    Box::leak(format!("{}/{}", chain, name).into_boxed_str())
+}
+
+/// ChainCollector wraps a [Collector] connected to a single chain and tags
+/// every event it emits with that chain.
+pub struct ChainCollector<E> {
+    chain: NamedChain,
+    collector: Box<dyn Collector<E>>,
+    name: &'static str,
+}
+
+impl<E> ChainCollector<E> {
+    pub fn new(chain: NamedChain, collector: Box<dyn Collector<E>>) -> Self {
+        let name = chain_name(chain, collector.name());
+        Self {
+            chain,
+            collector,
+            name,
+        }
+    }
+}
+
+#[async_trait]
+impl<E: Send + Sync + 'static> Collector<Chained<E>> for ChainCollector<E> {
+    async fn get_event_stream(&self) -> Result<CollectorStream<'_, Chained<E>>> {
+        let chain = self.chain;
+        let stream = self.collector.get_event_stream().await?;
+        let stream = stream.map(move |event| Chained::new(chain, event));
+        Ok(Box::pin(stream))
+    }
+
+    fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+/// ChainStrategy runs a single-chain [Strategy] in a multi-chain engine: it
+/// only processes the events of its chain, and tags the actions it emits with
+/// that chain so that they can be routed to the executors of the same chain.
+///
+/// Its name is qualified with the chain, so that the strategies of different
+/// chains are told apart in logs, metrics and checkpoints.
+pub struct ChainStrategy<E, A> {
+    chain: NamedChain,
+    strategy: Box<dyn Strategy<E, A>>,
+    name: &'static str,
+}
+
+impl<E, A> ChainStrategy<E, A> {
+    pub fn new(chain: NamedChain, strategy: Box<dyn Strategy<E, A>>) -> Self {
+        let name = chain_name(chain, strategy.name());
+        Self {
+            chain,
+            strategy,
+            name,
+        }
+    }
+}
+
+#[async_trait]
+impl<E, A> Strategy<Chained<E>, Chained<A>> for ChainStrategy<E, A>
+where
+    E: Send + Sync + 'static,
+    A: Send + Sync + 'static,
+{
+    async fn init_state(&mut self) -> Result<()> {
+        self.strategy.init_state().await
+    }
+
+    async fn sync_state(&mut self) -> Result<()> {
+        self.strategy.sync_state().await
+    }
+
+    async fn process_event(&mut self, event: Chained<E>) -> Vec<Chained<A>> {
+        let chain = self.chain;
+        // the subscription should already filter out the events of other chains
+        let Some(event) = event.on(chain) else {
+            return vec![];
+        };
+        let actions = self.strategy.process_event(event).await;
+        actions
+            .into_iter()
+            .map(|action| Chained::new(chain, action))
+            .collect()
+    }
+
+    fn on_lagged(&mut self, missed: u64) {
+        self.strategy.on_lagged(missed)
+    }
+
+    fn on_action_outcome(&mut self, outcome: ActionOutcome<Chained<A>>) {
+        self.strategy.on_action_outcome(ActionOutcome {
+            id: outcome.id,
+            action: outcome.action.inner,
+            executor: outcome.executor,
+            status: outcome.status,
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::stream;
+
+    struct Named;
+
+    #[async_trait]
+    impl Collector<u64> for Named {
+        async fn get_event_stream(&self) -> Result<CollectorStream<'_, u64>> {
+            Ok(Box::pin(stream::empty()))
+        }
+
+        fn name(&self) -> &'static str {
+            "Named"
+        }
+    }
+
+    #[async_trait]
+    impl Strategy<u64, u64> for Named {
+        async fn init_state(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        async fn sync_state(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        async fn process_event(&mut self, _event: u64) -> Vec<u64> {
+            vec![]
+        }
+
+        fn name(&self) -> &'static str {
+            "Named"
+        }
+    }
+
+    #[test]
+    fn test_names_are_qualified_with_the_chain() {
+        let collector = ChainCollector::new(NamedChain::Base, Box::new(Named));
+        assert_eq!(collector.name(), "base/Named");
+        let strategy: ChainStrategy<u64, u64> =
+            ChainStrategy::new(NamedChain::Mainnet, Box::new(Named));
+        assert_eq!(strategy.name(), "mainnet/Named");
+    }
+}
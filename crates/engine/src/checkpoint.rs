@@ -0,0 +1,239 @@
+use alloy_chains::NamedChain;
+use eyre::Result;
+use std::collections::{BTreeMap, HashMap};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+use crate::types::EngineEvent;
+
+/// Persists the last block fully processed by each strategy, so that a
+/// restarted bot can backfill the events it missed while it was down.
+///
+/// Checkpoints are stored in a JSON file mapping `<namespace>/<strategy>` keys,
+/// for example `bot/base/BaseArb@8453`, to block numbers. Strategies receiving
+/// [chain-tagged](crate::chain::Chained) events are checkpointed once per chain,
+/// under the keys returned by [checkpoint_key]. The file is rewritten
+/// atomically on every update, off the async runtime.
+#[derive(Debug)]
+pub struct CheckpointStore {
+    path: PathBuf,
+    namespace: String,
+    checkpoints: Mutex<BTreeMap<String, u64>>,
+    /// Held while the file is written, so that writes never interleave
+    write_lock: Mutex<()>,
+    /// Whether a background write is queued, which saves every later update
+    write_pending: AtomicBool,
+}
+
+impl CheckpointStore {
+    /// Opens the checkpoints stored at `path`, starting from none if the file
+    /// does not exist. Checkpoints are scoped to `namespace`, typically the chain id.
+    pub fn open(path: impl AsRef<Path>, namespace: impl Into<String>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let checkpoints = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content)?,
+            Err(e) if e.kind() == ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self {
+            path,
+            namespace: namespace.into(),
+            checkpoints: Mutex::new(checkpoints),
+            write_lock: Mutex::new(()),
+            write_pending: AtomicBool::new(false),
+        })
+    }
+
+    fn key(&self, strategy: &str) -> String {
+        format!("{}/{}", self.namespace, strategy)
+    }
+
+    /// Returns the last block fully processed by `strategy`, if any.
+    pub fn get(&self, strategy: &str) -> Option<u64> {
+        let checkpoints = self.checkpoints.lock().unwrap();
+        checkpoints.get(&self.key(strategy)).copied()
+    }
+
+    /// Records that `strategy` processed every event up to `block`.
+    ///
+    /// Within a tokio runtime, the file is written on a blocking task and
+    /// failures are logged. Updates made while a write is queued are saved by
+    /// that write, so a strategy moving through blocks quickly does not queue
+    /// a write per block. Outside of a runtime, the file is written in place.
+    pub fn set(self: &Arc<Self>, strategy: &str, block: u64) -> Result<()> {
+        self.checkpoints
+            .lock()
+            .unwrap()
+            .insert(self.key(strategy), block);
+
+        if tokio::runtime::Handle::try_current().is_err() {
+            return self.flush();
+        }
+        if !self.write_pending.swap(true, Ordering::SeqCst) {
+            let store = self.clone();
+            tokio::task::spawn_blocking(move || {
+                if let Err(e) = store.flush() {
+                    warn!("failed to save checkpoints to {:?}: {}", store.path, e);
+                }
+            });
+        }
+        Ok(())
+    }
+
+    /// Writes every checkpoint to the file, blocking until it is written.
+    pub fn flush(&self) -> Result<()> {
+        let _write = self.write_lock.lock().unwrap();
+        // updates from now on need another write
+        self.write_pending.store(false, Ordering::SeqCst);
+        let content = serde_json::to_string_pretty(&*self.checkpoints.lock().unwrap())?;
+
+        // a crash while writing must not leave a truncated file behind
+        let tmp = self.path.with_extension("tmp");
+        std::fs::write(&tmp, content)?;
+        std::fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+/// Returns the name under which the checkpoint of `strategy` is saved for the
+/// events of `chain`, for example `BaseArb@8453`. Events without a chain are
+/// checkpointed under the name of the strategy.
+pub fn checkpoint_key(strategy: &str, chain: Option<NamedChain>) -> String {
+    match chain {
+        Some(chain) => format!("{}@{}", strategy, chain as u64),
+        None => strategy.to_string(),
+    }
+}
+
+/// Saves the checkpoint of a strategy each time it moves on to a new block.
+/// Events of each chain are expected in block order, so once the strategy
+/// receives an event of a block, every earlier block of the same chain has
+/// been fully processed.
+pub(crate) struct Checkpointer {
+    store: Arc<CheckpointStore>,
+    strategy: &'static str,
+    /// The highest block of the events received so far, per chain
+    current_blocks: HashMap<Option<NamedChain>, u64>,
+}
+
+impl Checkpointer {
+    pub(crate) fn new(store: Arc<CheckpointStore>, strategy: &'static str) -> Self {
+        Self {
+            store,
+            strategy,
+            current_blocks: HashMap::new(),
+        }
+    }
+
+    /// Records an event about to be processed by the strategy.
+    pub(crate) fn observe<E: EngineEvent>(&mut self, event: &E) {
+        // restart notifications carry the chain head, ahead of the backfilled events
+        if event.is_collector_restart() {
+            return;
+        }
+        let Some(block) = event.block_number() else {
+            return;
+        };
+
+        let chain = event.chain();
+        let current = self.current_blocks.get(&chain).copied();
+        if current.is_some_and(|current| block > current) {
+            let key = checkpoint_key(self.strategy, chain);
+            if let Err(e) = self.store.set(&key, block - 1) {
+                warn!("failed to save checkpoint of {}: {}", key, e);
+            }
+        }
+        self.current_blocks
+            .insert(chain, current.map_or(block, |c| c.max(block)));
+    }
+
+    /// Writes the checkpoints saved so far to the file, waiting for the write.
+    /// Called when the strategy stops, so that no update is lost.
+    pub(crate) async fn flush(&self) {
+        let store = self.store.clone();
+        match tokio::task::spawn_blocking(move || store.flush()).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => warn!("failed to save checkpoints of {}: {}", self.strategy, e),
+            Err(e) => warn!("failed to save checkpoints of {}: {}", self.strategy, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::Chained;
+
+    struct Block(u64);
+
+    impl EngineEvent for Block {
+        fn block_number(&self) -> Option<u64> {
+            Some(self.0)
+        }
+    }
+
+    #[test]
+    fn test_checkpoints_are_saved_once_blocks_are_complete() {
+        let path = std::env::temp_dir().join("checkpoint-test.json");
+        let _ = std::fs::remove_file(&path);
+
+        let store = Arc::new(CheckpointStore::open(&path, "1").unwrap());
+        let mut checkpointer = Checkpointer::new(store.clone(), "Strategy");
+        checkpointer.observe(&Block(10));
+        checkpointer.observe(&Block(10));
+        assert_eq!(store.get("Strategy"), None);
+        checkpointer.observe(&Block(12));
+        assert_eq!(store.get("Strategy"), Some(11));
+        // late events never move the checkpoint back
+        checkpointer.observe(&Block(11));
+        checkpointer.observe(&Block(13));
+        assert_eq!(store.get("Strategy"), Some(12));
+
+        // checkpoints are scoped to their namespace
+        let reopened = CheckpointStore::open(&path, "1").unwrap();
+        assert_eq!(reopened.get("Strategy"), Some(12));
+        let other_chain = CheckpointStore::open(&path, "2").unwrap();
+        assert_eq!(other_chain.get("Strategy"), None);
+    }
+
+    #[test]
+    fn test_chain_tagged_events_are_checkpointed_per_chain() {
+        let path = std::env::temp_dir().join("checkpoint-chains-test.json");
+        let _ = std::fs::remove_file(&path);
+
+        let store = Arc::new(CheckpointStore::open(&path, "bot").unwrap());
+        let mut checkpointer = Checkpointer::new(store.clone(), "Strategy");
+        checkpointer.observe(&Chained::new(NamedChain::Mainnet, Block(100)));
+        // blocks of other chains never complete the blocks of this one
+        checkpointer.observe(&Chained::new(NamedChain::Base, Block(5000)));
+        checkpointer.observe(&Chained::new(NamedChain::Mainnet, Block(101)));
+
+        let mainnet = checkpoint_key("Strategy", Some(NamedChain::Mainnet));
+        let base = checkpoint_key("Strategy", Some(NamedChain::Base));
+        assert_eq!(mainnet, "Strategy@1");
+        assert_eq!(store.get(&mainnet), Some(100));
+        assert_eq!(store.get(&base), None);
+        checkpointer.observe(&Chained::new(NamedChain::Base, Block(5001)));
+        assert_eq!(store.get(&base), Some(5000));
+    }
+
+    #[tokio::test]
+    async fn test_checkpoints_are_written_off_the_runtime() {
+        let path = std::env::temp_dir().join("checkpoint-async-test.json");
+        let _ = std::fs::remove_file(&path);
+
+        let store = Arc::new(CheckpointStore::open(&path, "1").unwrap());
+        let mut checkpointer = Checkpointer::new(store.clone(), "Strategy");
+        for block in 1..=100 {
+            checkpointer.observe(&Block(block));
+        }
+        assert_eq!(store.get("Strategy"), Some(99));
+
+        checkpointer.flush().await;
+        let reopened = CheckpointStore::open(&path, "1").unwrap();
+        assert_eq!(reopened.get("Strategy"), Some(99));
+    }
+}
@@ -1,16 +1,29 @@
-use crate::types::{Collector, CollectorStream};
+use crate::backoff::Backoff;
+use crate::collectors::resubscribe::{polling_stream, resubscribing_stream, Resubscribe};
+use crate::types::{Collector, CollectorEvent, CollectorStream};
 use alloy::{
-    primitives::{U256, U64},
+    eips::BlockNumberOrTag,
+    primitives::{Address, TxHash, B256, U256, U64},
     providers::Provider,
+    rpc::types::{BlockTransactionsKind, Header},
 };
 use async_trait::async_trait;
-use eyre::Result;
-use std::sync::Arc;
+use eyre::{eyre, Result};
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
 use tokio_stream::StreamExt;
+use tracing::warn;
+
+/// Number of recent block hashes kept to detect reorgs.
+pub(crate) const REORG_TRACKING_DEPTH: u64 = 128;
 
 /// BlockCollector is responsible for monitoring new blocks on the blockchain.
-/// It provides a stream of [NewBlock] events that contain information about
-/// each new block as it is mined.
+/// It provides a stream of [NewBlock] events that contain the header of each
+/// new block as it is mined. When the subscription drops, the collector
+/// resubscribes with [Backoff] and backfills the blocks it missed. On endpoints
+/// without subscriptions, [BlockCollector::with_polling] polls for new blocks instead.
+///
+/// Each block is checked against the blocks emitted before it, and flagged
+/// with [NewBlock::reorg] when it does not build on them.
 ///
 /// Type Parameters:
 /// - P: The provider type that implements the Provider trait
@@ -18,12 +31,13 @@ use tokio_stream::StreamExt;
 /// # Example
 /// ```rust,no_run
 /// use std::sync::Arc;
-/// use engine::collectors::BlockCollector;
+/// use engine::collectors::block_collector::BlockCollector;
+/// use engine::types::CollectorEvent;
 ///
 /// async fn example(provider: Arc<impl Provider>) {
 ///     let collector = BlockCollector::new(provider);
 ///     let mut stream = collector.get_event_stream().await.unwrap();
-///     while let Some(block) = stream.next().await {
+///     while let Some(CollectorEvent::Event(block)) = stream.next().await {
 ///         println!("New block: {} ({})", block.number, block.hash);
 ///     }
 /// }
@@ -31,6 +45,12 @@ use tokio_stream::StreamExt;
 pub struct BlockCollector<P: Provider> {
     /// The blockchain provider used to subscribe to new blocks
     provider: Arc<P>,
+    /// The backoff used when resubscribing
+    backoff: Backoff,
+    /// Whether to fetch the transaction hashes of each block
+    with_transactions: bool,
+    /// Poll for new blocks at this interval instead of subscribing
+    poll_interval: Option<Duration>,
 }
 
 /// Represents a newly mined block on the blockchain.
@@ -41,6 +61,72 @@ pub struct NewBlock {
     pub hash: U256,
     /// The block number
     pub number: U64,
+    /// The hash of the parent block
+    pub parent_hash: B256,
+    /// The block timestamp, in seconds since the Unix epoch
+    pub timestamp: u64,
+    /// The base fee per gas, if the chain supports EIP-1559
+    pub base_fee_per_gas: Option<u64>,
+    pub gas_used: u64,
+    pub gas_limit: u64,
+    /// The blob gas used, if the chain supports EIP-4844
+    pub blob_gas_used: Option<u64>,
+    /// The excess blob gas, which prices the blobs of the next block
+    pub excess_blob_gas: Option<u64>,
+    /// The coinbase of the block, receiving its priority fees
+    pub miner: Address,
+    /// The hashes of the block transactions, if the collector was created
+    /// with [BlockCollector::with_transactions]
+    pub transactions: Option<Vec<TxHash>>,
+    /// True if the block does not build on the blocks emitted before it,
+    /// meaning that some of them were reorged out. State derived from blocks
+    /// at or above `number - 1` should be considered stale.
+    pub reorg: bool,
+}
+
+impl From<&Header> for NewBlock {
+    fn from(header: &Header) -> Self {
+        Self {
+            hash: header.hash.into(),
+            number: U64::from(header.number),
+            parent_hash: header.parent_hash,
+            timestamp: header.timestamp,
+            base_fee_per_gas: header.base_fee_per_gas,
+            gas_used: header.gas_used,
+            gas_limit: header.gas_limit,
+            blob_gas_used: header.blob_gas_used,
+            excess_blob_gas: header.excess_blob_gas,
+            miner: header.beneficiary,
+            transactions: None,
+            reorg: false,
+        }
+    }
+}
+
+/// Remembers the hashes of recent blocks to detect reorgs.
+#[derive(Debug, Default)]
+struct ReorgTracker {
+    hashes: BTreeMap<u64, B256>,
+}
+
+impl ReorgTracker {
+    /// Records a block, and returns true if it conflicts with the blocks
+    /// recorded before it.
+    fn observe(&mut self, number: u64, hash: B256, parent_hash: B256) -> bool {
+        let parent_replaced = number
+            .checked_sub(1)
+            .and_then(|parent| self.hashes.get(&parent))
+            .is_some_and(|known| *known != parent_hash);
+        let block_replaced = self.hashes.get(&number).is_some_and(|known| *known != hash);
+
+        // blocks above this one belong to the abandoned branch
+        self.hashes.split_off(&number);
+        self.hashes.insert(number, hash);
+        if let Some(oldest) = number.checked_sub(REORG_TRACKING_DEPTH) {
+            self.hashes = self.hashes.split_off(&oldest);
+        }
+        parent_replaced || block_replaced
+    }
 }
 
 impl<P: Provider> BlockCollector<P> {
@@ -49,7 +135,96 @@ impl<P: Provider> BlockCollector<P> {
     /// # Arguments
     /// * `provider` - An Arc-wrapped provider that implements the Provider trait
     pub fn new(provider: Arc<P>) -> Self {
-        Self { provider }
+        Self {
+            provider,
+            backoff: Backoff::default(),
+            with_transactions: false,
+            poll_interval: None,
+        }
+    }
+
+    /// Sets whether the transaction hashes of each block are fetched and
+    /// included in [NewBlock::transactions]. This costs one extra request per block.
+    pub fn with_transactions(mut self, with_transactions: bool) -> Self {
+        self.with_transactions = with_transactions;
+        self
+    }
+
+    /// Polls the chain head every `interval` instead of subscribing, fetching
+    /// new blocks with `eth_getBlockByNumber`. Use this with HTTP endpoints, which do not
+    /// support subscriptions.
+    pub fn with_polling(mut self, interval: Duration) -> Self {
+        self.poll_interval = Some(interval);
+        self
+    }
+
+    /// Sets the backoff used when resubscribing after the stream ends.
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+}
+
+#[async_trait]
+impl<P: Provider> Resubscribe<NewBlock> for BlockCollector<P> {
+    fn name(&self) -> &'static str {
+        "BlockCollector"
+    }
+
+    async fn subscribe(&self) -> Result<CollectorStream<'_, NewBlock>> {
+        if let Some(interval) = self.poll_interval {
+            return polling_stream(self, interval).await;
+        }
+        let sub = self.provider.subscribe_blocks().await?;
+        let stream = sub.into_stream().then(move |header| async move {
+            let mut block = NewBlock::from(&header);
+            if self.with_transactions {
+                match self
+                    .provider
+                    .get_block_by_hash(header.hash, BlockTransactionsKind::Hashes)
+                    .await
+                {
+                    Ok(Some(full)) => {
+                        block.transactions = Some(full.transactions.hashes().collect())
+                    }
+                    Ok(None) => warn!("Block {} not found", header.hash),
+                    Err(e) => warn!(
+                        "Failed to fetch transactions of block {}: {}",
+                        header.hash, e
+                    ),
+                }
+            }
+            block
+        });
+        Ok(Box::pin(stream))
+    }
+
+    async fn head(&self) -> Result<Option<u64>> {
+        Ok(Some(self.provider.get_block_number().await?))
+    }
+
+    async fn backfill(&self, from_block: u64, to_block: u64) -> Result<Vec<NewBlock>> {
+        let mut blocks = vec![];
+        for number in from_block..=to_block {
+            let block = self
+                .provider
+                .get_block_by_number(
+                    BlockNumberOrTag::Number(number),
+                    BlockTransactionsKind::Hashes,
+                )
+                .await?
+                .ok_or_else(|| eyre!("Block {} not found", number))?;
+            let mut new_block = NewBlock::from(&block.header);
+            if self.with_transactions {
+                new_block.transactions = Some(block.transactions.hashes().collect());
+            }
+            blocks.push(new_block);
+        }
+        Ok(blocks)
+    }
+
+    fn block_number(&self, event: &NewBlock) -> Option<u64> {
+        Some(event.number.to::<u64>())
     }
 }
 
@@ -57,31 +232,60 @@ impl<P: Provider> BlockCollector<P> {
 /// This implementation:
 /// 1. Subscribes to new blocks using the provider
 /// 2. Converts block headers into NewBlock events
-/// 3. Provides a stream of these events
+/// 3. Resubscribes and backfills missed blocks when the subscription drops
+/// 4. Flags blocks that do not build on the previously emitted blocks as reorgs
+/// 5. Provides a stream of these events
 #[async_trait]
-impl<P> Collector<NewBlock> for BlockCollector<P>
+impl<P> Collector<CollectorEvent<NewBlock>> for BlockCollector<P>
 where
     P: Provider,
 {
     /// Returns a stream of NewBlock events.
-    /// The stream will emit a new event each time a block is mined.
+    /// The stream will emit a new event each time a block is mined, and a
+    /// [CollectorRestarted](crate::types::CollectorRestarted) event each time
+    /// the subscription had to be re-established.
     ///
     /// # Returns
-    /// * `Result<CollectorStream<'_, NewBlock>>` - A stream of NewBlock events
+    /// * `Result<CollectorStream<'_, CollectorEvent<NewBlock>>>` - A stream of NewBlock events
     ///
     /// # Errors
-    /// Returns an error if:
-    /// * The subscription to new blocks fails
-    /// * The provider connection is lost
-    async fn get_event_stream(&self) -> Result<CollectorStream<'_, NewBlock>> {
-        let sub = self.provider.subscribe_blocks().await?;
-        let stream = sub.into_stream().take(256);
-        let stream = stream.filter_map(|header| {
-            Some(NewBlock {
-                hash: header.hash.into(),
-                number: U64::from(header.number),
-            })
+    /// Returns an error if the initial subscription to new blocks fails
+    async fn get_event_stream(&self) -> Result<CollectorStream<'_, CollectorEvent<NewBlock>>> {
+        let stream = self.subscribe().await?;
+        let mut tracker = ReorgTracker::default();
+        let stream = resubscribing_stream(self, self.backoff.clone(), stream).map(move |event| {
+            let CollectorEvent::Event(mut block) = event else {
+                return event;
+            };
+            let number = block.number.to::<u64>();
+            block.reorg = tracker.observe(number, block.hash.into(), block.parent_hash);
+            if block.reorg {
+                warn!("Reorg detected at block {} ({})", number, block.hash);
+            }
+            CollectorEvent::Event(block)
         });
         Ok(Box::pin(stream))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reorg_tracker() {
+        let hash = B256::with_last_byte;
+        let mut tracker = ReorgTracker::default();
+        assert!(!tracker.observe(1, hash(1), hash(0)));
+        assert!(!tracker.observe(2, hash(2), hash(1)));
+        assert!(!tracker.observe(3, hash(3), hash(2)));
+
+        // block 3 is replaced by a block building on block 2
+        assert!(tracker.observe(3, hash(0x33), hash(2)));
+        // a late block building on the abandoned block 3
+        assert!(tracker.observe(4, hash(4), hash(3)));
+        // block 4 of the new branch replaces it
+        assert!(tracker.observe(4, hash(0x44), hash(0x33)));
+        assert!(!tracker.observe(5, hash(5), hash(0x44)));
+    }
+}
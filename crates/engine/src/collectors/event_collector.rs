@@ -1,4 +1,6 @@
-use crate::types::{Collector, CollectorStream};
+use crate::backoff::Backoff;
+use crate::collectors::log_collector::LogCollector;
+use crate::types::{Collector, CollectorEvent, CollectorStream};
 use alloy::{primitives::Log, providers::Provider, rpc::types::Filter, sol_types::SolEvent};
 use async_trait::async_trait;
 use eyre::Result;
@@ -6,8 +8,7 @@ use std::sync::Arc;
 use tokio_stream::StreamExt;
 
 pub struct EventCollector<M, E> {
-    provider: Arc<M>,
-    filter: Filter,
+    inner: LogCollector<M>,
     _phantom: std::marker::PhantomData<E>,
 }
 
@@ -18,11 +19,17 @@ where
     pub fn new(provider: Arc<M>, filter: Filter) -> Self {
         // let event = IUniswapV2Pair::Swap;
         Self {
-            provider,
-            filter,
+            inner: LogCollector::new(provider, filter),
             _phantom: std::marker::PhantomData,
         }
     }
+
+    /// Sets the backoff used when resubscribing after the stream ends.
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.inner = self.inner.with_backoff(backoff);
+        self
+    }
+
     fn parse_event_log(log: &Log) -> Option<E> {
         let log = E::decode_log(&log, true).ok();
         let data = log.map(|log| log.data);
@@ -31,15 +38,21 @@ where
 }
 
 #[async_trait]
-impl<M, E> Collector<E> for EventCollector<M, E>
+impl<M, E> Collector<CollectorEvent<E>> for EventCollector<M, E>
 where
     M: Provider,
     E: SolEvent + Send + Sync + 'static,
 {
-    async fn get_event_stream(&self) -> Result<CollectorStream<'_, E>> {
-        let sub = self.provider.subscribe_logs(&self.filter).await?;
-        let stream = sub.into_stream().take(256);
-        let stream = stream.filter_map(|log| Self::parse_event_log(&log.inner));
+    async fn get_event_stream(&self) -> Result<CollectorStream<'_, CollectorEvent<E>>> {
+        let stream = self.inner.get_event_stream().await?;
+        let stream = stream.filter_map(|event| match event {
+            CollectorEvent::Event(log) => {
+                Self::parse_event_log(&log.inner).map(CollectorEvent::Event)
+            }
+            CollectorEvent::Restarted(restarted) => Some(CollectorEvent::Restarted(restarted)),
+            CollectorEvent::Reorg(reorg) => Some(CollectorEvent::Reorg(reorg)),
+            CollectorEvent::BlockEnd(block) => Some(CollectorEvent::BlockEnd(block)),
+        });
         Ok(Box::pin(stream))
     }
 }
@@ -1,35 +1,106 @@
-use crate::types::{Collector, CollectorStream};
+use crate::backoff::Backoff;
+use crate::collectors::reorg::with_reorgs;
+use crate::collectors::resubscribe::{
+    backfilling_stream, get_logs_in_range, polling_stream, resubscribing_stream, Resubscribe,
+};
+use crate::types::{Collector, CollectorEvent, CollectorStream};
 use alloy::rpc::types::Log;
 use alloy::{providers::Provider, rpc::types::Filter};
 use async_trait::async_trait;
 use eyre::Result;
-use std::sync::Arc;
-use tokio_stream::StreamExt;
+use std::{sync::Arc, time::Duration};
 
 /// A collector that listens for new blockchain event logs based on a [Filter](Filter),
 /// and generates a stream of [events](Log).
+///
+/// When the subscription drops, the collector resubscribes with [Backoff] and
+/// backfills the logs it missed with `eth_getLogs`. On endpoints without
+/// subscriptions, [LogCollector::with_polling] polls for new logs instead.
+///
+/// Logs removed by a chain reorganization are not forwarded: a
+/// [Reorg](crate::types::Reorg) event is emitted instead, followed by the logs
+/// of the canonical branch.
 pub struct LogCollector<M> {
     provider: Arc<M>,
     filter: Filter,
+    backoff: Backoff,
+    poll_interval: Option<Duration>,
+    /// The first block to backfill logs from before streaming new ones
+    start_block: Option<u64>,
 }
 
 impl<M> LogCollector<M> {
     pub fn new(provider: Arc<M>, filter: Filter) -> Self {
-        Self { provider, filter }
+        Self {
+            provider,
+            filter,
+            backoff: Backoff::default(),
+            poll_interval: None,
+            start_block: None,
+        }
+    }
+
+    /// Sets the backoff used when resubscribing after the stream ends.
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Polls the chain head every `interval` instead of subscribing, fetching
+    /// new logs with `eth_getLogs`. Use this with HTTP endpoints, which do not
+    /// support subscriptions.
+    pub fn with_polling(mut self, interval: Duration) -> Self {
+        self.poll_interval = Some(interval);
+        self
+    }
+
+    /// Backfills the logs from `block` to the chain head with `eth_getLogs`
+    /// before streaming new logs, for example to catch up from a checkpoint.
+    pub fn with_start_block(mut self, block: u64) -> Self {
+        self.start_block = Some(block);
+        self
+    }
+}
+
+#[async_trait]
+impl<M: Provider> Resubscribe<Log> for LogCollector<M> {
+    fn name(&self) -> &'static str {
+        "LogCollector"
+    }
+
+    async fn subscribe(&self) -> Result<CollectorStream<'_, Log>> {
+        if let Some(interval) = self.poll_interval {
+            return polling_stream(self, interval).await;
+        }
+        let sub = self.provider.subscribe_logs(&self.filter).await?;
+        Ok(Box::pin(sub.into_stream()))
+    }
+
+    async fn head(&self) -> Result<Option<u64>> {
+        Ok(Some(self.provider.get_block_number().await?))
+    }
+
+    async fn backfill(&self, from_block: u64, to_block: u64) -> Result<Vec<Log>> {
+        get_logs_in_range(self.provider.as_ref(), &self.filter, from_block, to_block).await
+    }
+
+    fn block_number(&self, log: &Log) -> Option<u64> {
+        log.block_number
     }
 }
 
 /// Implementation of the [Collector](Collector) trait for the [LogCollector](LogCollector).
 /// This implementation uses the [PubsubClient](PubsubClient) to subscribe to new logs.
 #[async_trait]
-impl<M> Collector<Log> for LogCollector<M>
+impl<M> Collector<CollectorEvent<Log>> for LogCollector<M>
 where
     M: Provider,
 {
-    async fn get_event_stream(&self) -> Result<CollectorStream<'_, Log>> {
-        let stream = self.provider.subscribe_logs(&self.filter).await?;
-        let stream = stream.into_stream();
-        let stream = stream.filter_map(|log| Some(log));
-        Ok(Box::pin(stream))
+    async fn get_event_stream(&self) -> Result<CollectorStream<'_, CollectorEvent<Log>>> {
+        let stream = match self.start_block {
+            Some(from_block) => backfilling_stream(self, self.backoff.clone(), from_block).await?,
+            None => resubscribing_stream(self, self.backoff.clone(), self.subscribe().await?),
+        };
+        Ok(with_reorgs(Resubscribe::name(self), stream))
     }
 }
@@ -0,0 +1,286 @@
+use crate::collectors::block_collector::REORG_TRACKING_DEPTH;
+use crate::types::{CollectorEvent, CollectorStream};
+use alloy::rpc::types::Log;
+use futures::{stream, StreamExt};
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::time::Duration;
+use tracing::warn;
+
+/// How long the logs of a block are buffered after the last log received,
+/// before the block is considered complete.
+pub(crate) const BLOCK_FLUSH_DELAY: Duration = Duration::from_millis(250);
+
+/// The position of a log within its block: its transaction index and log index.
+type Position = (u64, u64);
+
+/// Buffers the logs of each block and emits them sorted by
+/// `(block_number, transaction_index, log_index)`, without duplicates,
+/// followed by a [BlockEnd](CollectorEvent::BlockEnd) marker.
+///
+/// A block is complete once a log of a later block is received, or when the
+/// stream is flushed. Logs of a block that was already complete are emitted
+/// as soon as they are received, unless they are duplicates: they follow the
+/// end-of-block marker of their block, so consumers evaluating each block once
+/// apply them with the next one. Such late logs are counted and reported.
+///
+/// Logs without a block number, transaction index or log index cannot be
+/// ordered nor told apart, and are passed through as they are received.
+#[derive(Debug, Default)]
+pub(crate) struct LogOrderer {
+    /// The block whose logs are being buffered
+    current: Option<u64>,
+    /// The buffered logs of the current block
+    buffer: BTreeMap<Position, Log>,
+    /// The last complete block
+    last_complete: Option<u64>,
+    /// The positions of the logs emitted for recent blocks
+    emitted: BTreeMap<u64, HashSet<Position>>,
+    /// The number of logs received after their block was complete
+    late_logs: u64,
+}
+
+impl LogOrderer {
+    /// Returns true if logs are waiting for their block to complete.
+    pub(crate) fn is_buffering(&self) -> bool {
+        self.current.is_some()
+    }
+
+    /// Returns the events to emit in place of `event`.
+    pub(crate) fn apply(&mut self, event: CollectorEvent<Log>) -> Vec<CollectorEvent<Log>> {
+        match event {
+            CollectorEvent::Event(log) => self.apply_log(log),
+            CollectorEvent::Reorg(reorg) => {
+                // the buffered logs of the abandoned branch were never emitted
+                if self.current.is_some_and(|block| block >= reorg.from_block) {
+                    self.current = None;
+                    self.buffer.clear();
+                }
+                self.emitted.split_off(&reorg.from_block);
+                self.last_complete = self
+                    .last_complete
+                    .map(|block| block.min(reorg.from_block.saturating_sub(1)));
+                vec![CollectorEvent::Reorg(reorg)]
+            }
+            event => {
+                let mut events = self.flush();
+                events.push(event);
+                events
+            }
+        }
+    }
+
+    fn apply_log(&mut self, log: Log) -> Vec<CollectorEvent<Log>> {
+        let (Some(block), Some(transaction_index), Some(log_index)) =
+            (log.block_number, log.transaction_index, log.log_index)
+        else {
+            return vec![CollectorEvent::Event(log)];
+        };
+        let position = (transaction_index, log_index);
+
+        let late = self.last_complete.is_some_and(|last| block <= last)
+            || self.current.is_some_and(|current| block < current);
+        if late {
+            if !self.emitted.entry(block).or_default().insert(position) {
+                return vec![];
+            }
+            self.late_logs += 1;
+            warn!(
+                "Log of block {} received after the block was complete ({} late logs)",
+                block, self.late_logs
+            );
+            return vec![CollectorEvent::Event(log)];
+        }
+
+        let mut events = vec![];
+        if self.current != Some(block) {
+            events = self.flush();
+            self.current = Some(block);
+        }
+        self.buffer.entry(position).or_insert(log);
+        events
+    }
+
+    /// Completes the current block: returns its logs in order, followed by the
+    /// end-of-block marker.
+    pub(crate) fn flush(&mut self) -> Vec<CollectorEvent<Log>> {
+        let Some(block) = self.current.take() else {
+            return vec![];
+        };
+        let buffer = std::mem::take(&mut self.buffer);
+        self.emitted
+            .entry(block)
+            .or_default()
+            .extend(buffer.keys().copied());
+        if let Some(oldest) = block.checked_sub(REORG_TRACKING_DEPTH) {
+            self.emitted = self.emitted.split_off(&oldest);
+        }
+        self.last_complete = Some(self.last_complete.map_or(block, |last| last.max(block)));
+
+        let mut events: Vec<_> = buffer.into_values().map(CollectorEvent::Event).collect();
+        events.push(CollectorEvent::BlockEnd(block));
+        events
+    }
+}
+
+/// Wraps a stream of logs so that the logs of each block are emitted in order
+/// and without duplicates, followed by a [BlockEnd](CollectorEvent::BlockEnd)
+/// marker. A block is also completed once no log was received for `flush_delay`,
+/// so that the logs of the latest block are not held back until the next one.
+pub(crate) fn with_block_ordering<'a>(
+    stream: CollectorStream<'a, CollectorEvent<Log>>,
+    flush_delay: Duration,
+) -> CollectorStream<'a, CollectorEvent<Log>> {
+    let state = (Some(stream), LogOrderer::default(), VecDeque::new());
+    Box::pin(stream::unfold(
+        state,
+        move |(mut stream, mut orderer, mut pending)| async move {
+            loop {
+                if let Some(event) = pending.pop_front() {
+                    return Some((event, (stream, orderer, pending)));
+                }
+                let inner = stream.as_mut()?;
+
+                let next = if orderer.is_buffering() {
+                    match tokio::time::timeout(flush_delay, inner.next()).await {
+                        Ok(next) => next,
+                        Err(_) => {
+                            pending.extend(orderer.flush());
+                            continue;
+                        }
+                    }
+                } else {
+                    inner.next().await
+                };
+                match next {
+                    Some(event) => pending.extend(orderer.apply(event)),
+                    None => {
+                        pending.extend(orderer.flush());
+                        stream = None;
+                    }
+                }
+            }
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Reorg;
+
+    fn log(block: u64, tx: u64, index: u64) -> CollectorEvent<Log> {
+        CollectorEvent::Event(Log {
+            block_number: Some(block),
+            transaction_index: Some(tx),
+            log_index: Some(index),
+            ..Default::default()
+        })
+    }
+
+    fn describe(events: &[CollectorEvent<Log>]) -> Vec<String> {
+        events
+            .iter()
+            .map(|event| match event {
+                CollectorEvent::Event(log) => {
+                    format!("{}:{}", log.block_number.unwrap(), log.log_index.unwrap())
+                }
+                CollectorEvent::BlockEnd(block) => format!("end {}", block),
+                CollectorEvent::Reorg(reorg) => format!("reorg {}", reorg.from_block),
+                CollectorEvent::Restarted(_) => "restarted".to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_logs_are_sorted_and_deduplicated_per_block() {
+        let mut orderer = LogOrderer::default();
+        assert!(orderer.apply(log(1, 1, 3)).is_empty());
+        assert!(orderer.apply(log(1, 0, 1)).is_empty());
+        assert!(orderer.apply(log(1, 1, 3)).is_empty());
+        assert!(orderer.apply(log(1, 0, 0)).is_empty());
+
+        let events = orderer.apply(log(2, 0, 0));
+        assert_eq!(describe(&events), ["1:0", "1:1", "1:3", "end 1"]);
+
+        // late logs of a complete block are forwarded once
+        assert_eq!(describe(&orderer.apply(log(1, 2, 5))), ["1:5"]);
+        assert!(orderer.apply(log(1, 2, 5)).is_empty());
+        assert!(orderer.apply(log(1, 0, 1)).is_empty());
+        assert_eq!(orderer.late_logs, 1);
+
+        assert_eq!(describe(&orderer.flush()), ["2:0", "end 2"]);
+        assert!(orderer.flush().is_empty());
+    }
+
+    #[test]
+    fn test_logs_without_position_are_passed_through() {
+        let mut orderer = LogOrderer::default();
+        assert!(orderer.apply(log(1, 0, 0)).is_empty());
+
+        // neither buffered nor deduplicated
+        let unpositioned = || {
+            CollectorEvent::Event(Log {
+                block_number: Some(1),
+                ..Default::default()
+            })
+        };
+        for _ in 0..2 {
+            let events = orderer.apply(unpositioned());
+            assert!(matches!(
+                events.as_slice(),
+                [CollectorEvent::Event(log)] if log.log_index.is_none()
+            ));
+        }
+        let pending = CollectorEvent::Event(Log::default());
+        assert_eq!(orderer.apply(pending).len(), 1);
+
+        assert_eq!(describe(&orderer.flush()), ["1:0", "end 1"]);
+    }
+
+    #[test]
+    fn test_reorg_drops_buffered_logs() {
+        let mut orderer = LogOrderer::default();
+        orderer.apply(log(1, 0, 0));
+        orderer.apply(log(2, 0, 0));
+        orderer.apply(log(3, 0, 0));
+
+        let events = orderer.apply(CollectorEvent::Reorg(Reorg {
+            collector: "test",
+            from_block: 2,
+            to_block: 3,
+        }));
+        assert_eq!(describe(&events), ["reorg 2"]);
+
+        // the canonical logs of the replaced blocks are buffered again
+        assert!(orderer.apply(log(2, 0, 0)).is_empty());
+        assert_eq!(describe(&orderer.apply(log(3, 0, 0))), ["2:0", "end 2"]);
+    }
+
+    #[tokio::test]
+    async fn test_latest_block_is_flushed_after_delay() {
+        let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+        let stream = tokio_stream::wrappers::UnboundedReceiverStream::new(receiver);
+        let mut stream = with_block_ordering(Box::pin(stream), Duration::from_millis(10));
+
+        sender.send(log(1, 0, 1)).unwrap();
+        sender.send(log(1, 0, 0)).unwrap();
+        let mut events = vec![];
+        for _ in 0..3 {
+            events.push(stream.next().await.unwrap());
+        }
+        assert_eq!(describe(&events), ["1:0", "1:1", "end 1"]);
+
+        // a log of the flushed block arriving after the delay follows its end
+        // marker, ahead of the logs of the next block
+        sender.send(log(1, 0, 2)).unwrap();
+        sender.send(log(2, 0, 0)).unwrap();
+        let mut events = vec![];
+        for _ in 0..3 {
+            events.push(stream.next().await.unwrap());
+        }
+        assert_eq!(describe(&events), ["1:2", "2:0", "end 2"]);
+
+        drop(sender);
+        assert!(stream.next().await.is_none());
+    }
+}
@@ -4,32 +4,59 @@ use async_trait::async_trait;
 use futures::StreamExt;
 use std::sync::Arc;
 
-use crate::types::{Collector, CollectorStream};
+use crate::backoff::Backoff;
+use crate::collectors::resubscribe::{resubscribing_stream, Resubscribe};
+use crate::types::{Collector, CollectorEvent, CollectorStream};
 use eyre::Result;
 
 /// A collector that listens for new transactions in the mempool, and generates a stream of
 /// [events](Transaction) which contain the transaction.
+///
+/// When the subscription drops, the collector resubscribes with [Backoff]. Pending
+/// transactions seen by the node while the collector was down cannot be backfilled.
 pub struct MempoolCollector<P> {
     provider: Arc<P>,
+    backoff: Backoff,
 }
 
 impl<P> MempoolCollector<P> {
     pub fn new(provider: Arc<P>) -> Self {
-        Self { provider }
+        Self {
+            provider,
+            backoff: Backoff::default(),
+        }
+    }
+
+    /// Sets the backoff used when resubscribing after the stream ends.
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+}
+
+#[async_trait]
+impl<P: Provider> Resubscribe<B256> for MempoolCollector<P> {
+    fn name(&self) -> &'static str {
+        "MempoolCollector"
+    }
+
+    async fn subscribe(&self) -> Result<CollectorStream<'_, B256>> {
+        let sub = self.provider.subscribe_full_pending_transactions().await?;
+        let stream = sub.into_stream();
+        let stream = stream.filter_map(|res| async move { Some(res.tx_hash()) });
+        Ok(Box::pin(stream))
     }
 }
 
 /// Implementation of the [Collector](Collector) trait for the [MempoolCollector](MempoolCollector).
 /// This implementation uses the [PubsubClient](PubsubClient) to subscribe to new transactions.
 #[async_trait]
-impl<P> Collector<B256> for MempoolCollector<P>
+impl<P> Collector<CollectorEvent<B256>> for MempoolCollector<P>
 where
     P: Provider,
 {
-    async fn get_event_stream(&self) -> Result<CollectorStream<'_, B256>> {
-        let sub = self.provider.subscribe_full_pending_transactions().await?;
-        let stream = sub.into_stream();
-        let stream = stream.filter_map(|res| async move { Some(res.tx_hash()) });
-        Ok(Box::pin(stream))
+    async fn get_event_stream(&self) -> Result<CollectorStream<'_, CollectorEvent<B256>>> {
+        let stream = self.subscribe().await?;
+        Ok(resubscribing_stream(self, self.backoff.clone(), stream))
     }
 }
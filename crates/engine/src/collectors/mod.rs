@@ -11,6 +11,28 @@ pub mod log_collector;
 /// This collector listens to a stream of new pending transactions.
 pub mod mempool_collector;
 
+/// These collectors emit ticks at fixed intervals or on a cron schedule.
+pub mod timer_collector;
+
+/// This collector listens to pending transactions, and decodes the swaps of
+/// known router calls.
+pub mod pending_swap_collector;
+
 pub mod event_collector;
 
 pub mod multi_log_collector;
+
+/// This collector decodes logs of several event types into a single enum.
+pub mod multi_event_collector;
+
+/// The set of pools tracked by a strategy, used to scope log subscriptions.
+pub mod pool_set;
+
+/// Ordering and deduplication of the logs of each block.
+pub(crate) mod log_order;
+
+/// Detection of chain reorganizations in log streams.
+pub(crate) mod reorg;
+
+/// Shared machinery for collectors that resubscribe when their stream drops.
+pub(crate) mod resubscribe;
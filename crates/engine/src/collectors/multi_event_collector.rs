@@ -0,0 +1,378 @@
+use crate::types::{Collector, CollectorEvent, CollectorStream, EngineEvent};
+use alloy::{
+    primitives::{Address, TxHash, B256},
+    rpc::types::{Filter, Log},
+    sol_types::SolEvent,
+};
+use async_trait::async_trait;
+use eyre::Result;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio_stream::StreamExt;
+use tracing::warn;
+
+/// The metadata of the log an event was decoded from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LogMeta {
+    /// The address of the contract that emitted the log
+    pub address: Address,
+    /// The block the log was emitted in
+    pub block_number: Option<u64>,
+    /// The hash of the block the log was emitted in
+    pub block_hash: Option<B256>,
+    /// The hash of the transaction that emitted the log
+    pub transaction_hash: Option<TxHash>,
+    /// The index of the transaction in its block
+    pub transaction_index: Option<u64>,
+    /// The index of the log in its block
+    pub log_index: Option<u64>,
+}
+
+impl From<&Log> for LogMeta {
+    fn from(log: &Log) -> Self {
+        Self {
+            address: log.address(),
+            block_number: log.block_number,
+            block_hash: log.block_hash,
+            transaction_hash: log.transaction_hash,
+            transaction_index: log.transaction_index,
+            log_index: log.log_index,
+        }
+    }
+}
+
+/// An event decoded by a [MultiEventCollector], along with the metadata of its log.
+#[derive(Debug, Clone)]
+pub struct DecodedEvent<T> {
+    /// The metadata of the log the event was decoded from
+    pub meta: LogMeta,
+    /// The decoded event
+    pub event: T,
+    /// True if the event carries the full state of the contract that emitted
+    /// it, see [EventRegistry::register_coalesced]
+    pub coalesce: bool,
+}
+
+impl<T> EngineEvent for DecodedEvent<T> {
+    fn block_number(&self) -> Option<u64> {
+        self.meta.block_number
+    }
+
+    fn coalesce_key(&self) -> Option<Address> {
+        self.coalesce.then_some(self.meta.address)
+    }
+}
+
+type Decoder<T> = Arc<dyn Fn(&Log) -> Option<T> + Send + Sync>;
+
+/// The decoder of a registered event, and whether its events are coalesced.
+type Registered<T> = (Decoder<T>, bool);
+
+/// A registry of [SolEvent] types, each decoded into a variant of the
+/// user-defined enum `T`.
+///
+/// # Example
+/// ```rust,no_run
+/// use alloy::sol;
+/// use engine::collectors::multi_event_collector::EventRegistry;
+///
+/// sol! {
+///     event Sync(uint112 reserve0, uint112 reserve1);
+///     event Swap(address indexed sender, address indexed recipient, int256 amount0, int256 amount1, uint160 sqrtPriceX96, uint128 liquidity, int24 tick);
+/// }
+///
+/// enum PoolEvent {
+///     Sync(Sync),
+///     Swap(Swap),
+/// }
+///
+/// let registry = EventRegistry::new()
+///     .register_coalesced(PoolEvent::Sync)
+///     .register(PoolEvent::Swap);
+/// let filter = registry.filter();
+/// ```
+pub struct EventRegistry<T> {
+    decoders: HashMap<B256, Registered<T>>,
+}
+
+impl<T> Clone for EventRegistry<T> {
+    fn clone(&self) -> Self {
+        Self {
+            decoders: self.decoders.clone(),
+        }
+    }
+}
+
+impl<T> Default for EventRegistry<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> EventRegistry<T> {
+    pub fn new() -> Self {
+        Self {
+            decoders: HashMap::new(),
+        }
+    }
+
+    /// Registers the event `E`, whose decoded logs are turned into `T` with
+    /// `wrap`, typically a variant of the enum.
+    ///
+    /// An event can only be registered once per signature: registering another
+    /// event with the signature of a registered one is ignored, with a warning.
+    pub fn register<E: SolEvent + 'static>(
+        self,
+        wrap: impl Fn(E) -> T + Send + Sync + 'static,
+    ) -> Self {
+        self.insert(wrap, false)
+    }
+
+    /// Registers the event `E` like [register](Self::register), for events
+    /// carrying the full state of the contract that emits them, such as the
+    /// UniswapV2 `Sync` event. Only the latest of them matters, so they are
+    /// coalesced per contract in queues with the
+    /// [Coalesce](crate::delivery::DeliveryPolicy::Coalesce) policy. Events
+    /// describing a change, such as swaps, must not be coalesced.
+    pub fn register_coalesced<E: SolEvent + 'static>(
+        self,
+        wrap: impl Fn(E) -> T + Send + Sync + 'static,
+    ) -> Self {
+        self.insert(wrap, true)
+    }
+
+    fn insert<E: SolEvent + 'static>(
+        mut self,
+        wrap: impl Fn(E) -> T + Send + Sync + 'static,
+        coalesce: bool,
+    ) -> Self {
+        if self.decoders.contains_key(&E::SIGNATURE_HASH) {
+            warn!(
+                "Event {} is already registered with signature {}, ignoring it",
+                E::SIGNATURE,
+                E::SIGNATURE_HASH
+            );
+            return self;
+        }
+        let decoder = move |log: &Log| {
+            // logs of other events sharing the signature fail to decode
+            E::decode_log(&log.inner, true)
+                .ok()
+                .map(|log| wrap(log.data))
+        };
+        self.decoders
+            .insert(E::SIGNATURE_HASH, (Arc::new(decoder), coalesce));
+        self
+    }
+
+    /// Returns the signatures of the registered events.
+    pub fn signatures(&self) -> Vec<B256> {
+        self.decoders.keys().copied().collect()
+    }
+
+    /// Returns a filter matching the logs of every registered event.
+    pub fn filter(&self) -> Filter {
+        Filter::new().event_signature(self.signatures())
+    }
+
+    /// Decodes `log`, if it is one of the registered events.
+    pub fn decode(&self, log: &Log) -> Option<DecodedEvent<T>> {
+        let (decoder, coalesce) = self.decoders.get(log.topics().first()?)?;
+        let event = decoder(log)?;
+        Some(DecodedEvent {
+            meta: LogMeta::from(log),
+            event,
+            coalesce: *coalesce,
+        })
+    }
+}
+
+/// A collector that decodes the logs of another collector, such as a
+/// [MultiLogCollector](crate::collectors::multi_log_collector::MultiLogCollector)
+/// subscribed to [EventRegistry::filter], into the events of an [EventRegistry].
+///
+/// Logs that are not one of the registered events are dropped. Restart,
+/// reorg and end-of-block notifications are forwarded as is.
+pub struct MultiEventCollector<T> {
+    inner: Box<dyn Collector<CollectorEvent<Log>>>,
+    registry: EventRegistry<T>,
+}
+
+impl<T> MultiEventCollector<T> {
+    pub fn new(inner: Box<dyn Collector<CollectorEvent<Log>>>, registry: EventRegistry<T>) -> Self {
+        Self { inner, registry }
+    }
+}
+
+#[async_trait]
+impl<T> Collector<CollectorEvent<DecodedEvent<T>>> for MultiEventCollector<T>
+where
+    T: Send + Sync + 'static,
+{
+    async fn get_event_stream(
+        &self,
+    ) -> Result<CollectorStream<'_, CollectorEvent<DecodedEvent<T>>>> {
+        let stream = self.inner.get_event_stream().await?;
+        let stream = stream.filter_map(|event| match event {
+            CollectorEvent::Event(log) => self.registry.decode(&log).map(CollectorEvent::Event),
+            CollectorEvent::Restarted(restarted) => Some(CollectorEvent::Restarted(restarted)),
+            CollectorEvent::Reorg(reorg) => Some(CollectorEvent::Reorg(reorg)),
+            CollectorEvent::BlockEnd(block) => Some(CollectorEvent::BlockEnd(block)),
+        });
+        Ok(Box::pin(stream))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{CollectorRestarted, Reorg};
+    use alloy::primitives::{aliases::U112, LogData, U256};
+    use alloy::sol;
+
+    sol! {
+        event Sync(uint112 reserve0, uint112 reserve1);
+        event Transfer(address indexed from, address indexed to, uint256 value);
+    }
+
+    mod other {
+        alloy::sol! {
+            // the signature of the ERC20 transfer, with the value indexed
+            event Transfer(address indexed from, address indexed to, uint256 indexed value);
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum PoolEvent {
+        Sync(U112, U112),
+        Transfer(U256),
+    }
+
+    fn log<E: SolEvent>(event: &E) -> Log {
+        Log {
+            inner: alloy::primitives::Log {
+                address: Address::repeat_byte(1),
+                data: event.encode_log_data(),
+            },
+            block_number: Some(10),
+            log_index: Some(3),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_registered_events_are_decoded() {
+        let registry = EventRegistry::new()
+            .register(|sync: Sync| PoolEvent::Sync(sync.reserve0, sync.reserve1))
+            .register(|transfer: Transfer| PoolEvent::Transfer(transfer.value));
+
+        let sync = Sync {
+            reserve0: U112::from(1),
+            reserve1: U112::from(2),
+        };
+        let decoded = registry.decode(&log(&sync)).unwrap();
+        assert_eq!(decoded.event, PoolEvent::Sync(U112::from(1), U112::from(2)));
+        assert_eq!(decoded.meta.address, Address::repeat_byte(1));
+        assert_eq!(decoded.meta.block_number, Some(10));
+        assert_eq!(decoded.meta.log_index, Some(3));
+
+        let transfer = Transfer {
+            from: Address::ZERO,
+            to: Address::ZERO,
+            value: U256::from(5),
+        };
+        let decoded = registry.decode(&log(&transfer)).unwrap();
+        assert_eq!(decoded.event, PoolEvent::Transfer(U256::from(5)));
+
+        let mut unknown = log(&sync);
+        unknown.inner.data = LogData::new_unchecked(vec![B256::repeat_byte(9)], Default::default());
+        assert!(registry.decode(&unknown).is_none());
+    }
+
+    #[test]
+    fn test_only_opted_in_events_are_coalesced() {
+        let registry = EventRegistry::new()
+            .register_coalesced(|sync: Sync| PoolEvent::Sync(sync.reserve0, sync.reserve1))
+            .register(|transfer: Transfer| PoolEvent::Transfer(transfer.value));
+
+        let sync = Sync {
+            reserve0: U112::from(1),
+            reserve1: U112::from(2),
+        };
+        let decoded = registry.decode(&log(&sync)).unwrap();
+        assert_eq!(decoded.coalesce_key(), Some(Address::repeat_byte(1)));
+
+        let transfer = Transfer {
+            from: Address::ZERO,
+            to: Address::ZERO,
+            value: U256::from(5),
+        };
+        let decoded = registry.decode(&log(&transfer)).unwrap();
+        assert_eq!(decoded.coalesce_key(), None);
+    }
+
+    #[test]
+    fn test_duplicate_signatures_keep_the_first_event() {
+        assert_eq!(Transfer::SIGNATURE_HASH, other::Transfer::SIGNATURE_HASH);
+        let registry = EventRegistry::new()
+            .register(|transfer: Transfer| PoolEvent::Transfer(transfer.value))
+            .register(|_: other::Transfer| PoolEvent::Transfer(U256::MAX));
+        assert_eq!(registry.signatures().len(), 1);
+
+        let transfer = Transfer {
+            from: Address::ZERO,
+            to: Address::ZERO,
+            value: U256::from(5),
+        };
+        let decoded = registry.decode(&log(&transfer)).unwrap();
+        assert_eq!(decoded.event, PoolEvent::Transfer(U256::from(5)));
+    }
+
+    struct ReplayCollector(Vec<CollectorEvent<Log>>);
+
+    #[async_trait]
+    impl Collector<CollectorEvent<Log>> for ReplayCollector {
+        async fn get_event_stream(&self) -> Result<CollectorStream<'_, CollectorEvent<Log>>> {
+            Ok(Box::pin(tokio_stream::iter(self.0.clone())))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_notifications_are_forwarded() {
+        let sync = Sync {
+            reserve0: U112::from(1),
+            reserve1: U112::from(2),
+        };
+        let mut unknown = log(&sync);
+        unknown.inner.data = LogData::new_unchecked(vec![B256::repeat_byte(9)], Default::default());
+        let inner = ReplayCollector(vec![
+            CollectorEvent::Restarted(CollectorRestarted {
+                collector: "replay",
+                last_block: Some(9),
+                resumed_block: Some(10),
+                attempts: 1,
+            }),
+            CollectorEvent::Event(log(&sync)),
+            CollectorEvent::Event(unknown),
+            CollectorEvent::BlockEnd(10),
+            CollectorEvent::Reorg(Reorg {
+                collector: "replay",
+                from_block: 10,
+                to_block: 10,
+            }),
+        ]);
+        let registry = EventRegistry::new()
+            .register_coalesced(|sync: Sync| PoolEvent::Sync(sync.reserve0, sync.reserve1));
+        let collector = MultiEventCollector::new(Box::new(inner), registry);
+
+        let events: Vec<_> = collector.get_event_stream().await.unwrap().collect().await;
+        assert_eq!(events.len(), 4);
+        assert!(matches!(&events[0], CollectorEvent::Restarted(r) if r.last_block == Some(9)));
+        assert!(matches!(
+            &events[1],
+            CollectorEvent::Event(decoded) if decoded.event == PoolEvent::Sync(U112::from(1), U112::from(2))
+        ));
+        assert!(matches!(events[2], CollectorEvent::BlockEnd(10)));
+        assert!(matches!(&events[3], CollectorEvent::Reorg(r) if r.from_block == 10));
+    }
+}
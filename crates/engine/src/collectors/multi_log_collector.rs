@@ -1,32 +1,333 @@
-use crate::types::{Collector, CollectorStream};
+use crate::backoff::Backoff;
+use crate::collectors::log_order::{with_block_ordering, BLOCK_FLUSH_DELAY};
+use crate::collectors::pool_set::{scoped_filters, PoolSet, MAX_ADDRESSES_PER_FILTER};
+use crate::collectors::reorg::with_reorgs;
+use crate::collectors::resubscribe::{
+    backfilling_stream, get_logs_in_range, polling_stream, resubscribing_stream, Resubscribe,
+};
+use crate::types::{Collector, CollectorEvent, CollectorStream};
+use alloy::primitives::Address;
 use alloy::providers::Provider;
 use alloy::rpc::types::{Filter, Log};
 use async_trait::async_trait;
 use eyre::Result;
-use std::sync::Arc;
+use futures::{future, stream, StreamExt};
+use std::collections::{BTreeSet, VecDeque};
+use std::{sync::Arc, time::Duration};
+use tokio::sync::watch;
+use tracing::{info, warn};
 
+/// A collector that subscribes to several log [filters](Filter) and merges them
+/// into a single stream of [logs](Log).
+///
+/// If any of the subscriptions drops, all of them are re-established with
+/// [Backoff] and the missed logs are backfilled with `eth_getLogs`. On endpoints
+/// without subscriptions, [MultiLogCollector::with_polling] polls for new logs instead.
+///
+/// Chain reorganizations are reported with [Reorg](crate::types::Reorg) events
+/// in place of the removed logs.
+///
+/// The logs of each block are emitted sorted by transaction and log index,
+/// without the duplicates of overlapping filters, and followed by a
+/// [BlockEnd](CollectorEvent::BlockEnd) marker.
+///
+/// With [MultiLogCollector::with_pool_set], the filters are scoped to the pools
+/// tracked by a strategy and resubscribed whenever pools are added or removed.
 pub struct MultiLogCollector<P: Provider> {
     provider: Arc<P>,
     filters: Vec<Filter>,
+    /// The pools the filters are scoped to, if any
+    pools: Option<PoolSet>,
+    max_addresses_per_filter: usize,
+    backoff: Backoff,
+    poll_interval: Option<Duration>,
+    /// How long the logs of a block are buffered after the last log received
+    block_flush_delay: Duration,
+    /// The first block to backfill logs from before streaming new ones
+    start_block: Option<u64>,
 }
 
 impl<P: Provider> MultiLogCollector<P> {
     pub fn new(provider: Arc<P>, filters: Vec<Filter>) -> Self {
-        Self { provider, filters }
+        Self {
+            provider,
+            filters,
+            pools: None,
+            max_addresses_per_filter: MAX_ADDRESSES_PER_FILTER,
+            backoff: Backoff::default(),
+            poll_interval: None,
+            block_flush_delay: BLOCK_FLUSH_DELAY,
+            start_block: None,
+        }
     }
-}
 
-#[async_trait]
-impl<P: Provider + 'static> Collector<Log> for MultiLogCollector<P> {
-    async fn get_event_stream(&self) -> Result<CollectorStream<'_, Log>> {
+    /// Sets the backoff used when resubscribing after the stream ends.
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Polls the chain head every `interval` instead of subscribing, fetching
+    /// new logs with `eth_getLogs`. Use this with HTTP endpoints, which do not
+    /// support subscriptions.
+    pub fn with_polling(mut self, interval: Duration) -> Self {
+        self.poll_interval = Some(interval);
+        self
+    }
+
+    /// Backfills the logs from `block` to the chain head with `eth_getLogs`
+    /// before streaming new logs, for example to catch up from a checkpoint.
+    /// With a [PoolSet], the backfill starts once the set has pools.
+    pub fn with_start_block(mut self, block: u64) -> Self {
+        self.start_block = Some(block);
+        self
+    }
+
+    /// Sets how long the logs of a block are buffered after the last log
+    /// received, before the block is considered complete. Logs of the block
+    /// received afterwards are emitted out of order.
+    pub fn with_block_flush_delay(mut self, delay: Duration) -> Self {
+        self.block_flush_delay = delay;
+        self
+    }
+
+    /// Only collects the logs emitted by the pools in `pools`. The collector
+    /// resubscribes whenever pools are added or removed, and backfills the
+    /// recent logs of the added pools.
+    pub fn with_pool_set(mut self, pools: PoolSet) -> Self {
+        self.pools = Some(pools);
+        self
+    }
+
+    /// Sets the maximum number of pool addresses per subscription. Larger pool
+    /// sets are split across several subscriptions.
+    pub fn with_max_addresses_per_filter(mut self, max: usize) -> Self {
+        self.max_addresses_per_filter = max;
+        self
+    }
+
+    /// Returns the filters scoped to `addresses`.
+    fn filters_for(&self, addresses: &BTreeSet<Address>) -> Vec<Filter> {
+        scoped_filters(&self.filters, addresses, self.max_addresses_per_filter)
+    }
+
+    /// Waits until the pool set, if any, has pools. Strategies fill their set
+    /// once their pools are loaded, concurrently with the collectors.
+    async fn wait_for_pools(&self) {
+        let Some(pools) = &self.pools else {
+            return;
+        };
+        let mut pools = pools.subscribe();
+        if pools.borrow().is_empty() {
+            info!("MultiLogCollector waiting for pools before backfilling");
+        }
+        // the collector holds the pool set, so the channel never closes
+        let _ = pools.wait_for(|addresses| !addresses.is_empty()).await;
+    }
+
+    /// Returns the filters to subscribe to and backfill with.
+    fn current_filters(&self) -> Vec<Filter> {
+        match &self.pools {
+            Some(pools) => self.filters_for(&pools.addresses()),
+            None => self.filters.clone(),
+        }
+    }
+
+    /// Subscribes to every filter. The merged stream ends as soon as one of the
+    /// subscriptions ends, so that a single dropped filter is not left deaf.
+    async fn subscribe_filters(&self, filters: &[Filter]) -> Result<CollectorStream<'_, Log>> {
+        if filters.is_empty() {
+            return Ok(Box::pin(stream::pending()));
+        }
         let mut streams = Vec::new();
-        for filter in &self.filters {
-            let stream = self.provider.subscribe_logs(&filter).await?;
-            let stream = stream.into_stream();
-            streams.push(stream);
+        for filter in filters {
+            let sub = self.provider.subscribe_logs(filter).await?;
+            let stream = sub
+                .into_stream()
+                .map(Some)
+                .chain(stream::once(future::ready(None)));
+            streams.push(Box::pin(stream));
         }
 
-        let combined_stream = futures::stream::select_all(streams);
+        let combined_stream = stream::select_all(streams)
+            .take_while(|log| future::ready(log.is_some()))
+            .filter_map(future::ready);
         Ok(Box::pin(combined_stream))
     }
+
+    async fn get_logs(
+        &self,
+        filters: &[Filter],
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<Vec<Log>> {
+        let mut logs = vec![];
+        for filter in filters {
+            logs.extend(
+                get_logs_in_range(self.provider.as_ref(), filter, from_block, to_block).await?,
+            );
+        }
+        logs.sort_by_key(|log| (log.block_number, log.log_index));
+        Ok(logs)
+    }
+}
+
+impl<P: Provider + 'static> MultiLogCollector<P> {
+    /// Subscribes to the logs of the pools in the set, and resubscribes in place
+    /// whenever the set changes. The logs emitted by added pools since the last
+    /// block seen are backfilled, since they may predate the new subscriptions.
+    /// The stream ends if a subscription ends or cannot be re-established.
+    async fn pool_scoped_stream(
+        &self,
+        mut pools: watch::Receiver<BTreeSet<Address>>,
+    ) -> Result<CollectorStream<'_, Log>> {
+        let addresses = pools.borrow_and_update().clone();
+        let live = self
+            .subscribe_filters(&self.filters_for(&addresses))
+            .await?;
+        let state = PoolScopedState {
+            live,
+            pools,
+            addresses,
+            last_block: Some(self.provider.get_block_number().await?),
+            pending: VecDeque::new(),
+        };
+
+        let stream = stream::unfold(state, move |mut state| async move {
+            loop {
+                if let Some(log) = state.pending.pop_front() {
+                    return Some((log, state));
+                }
+
+                tokio::select! {
+                    log = state.live.next() => {
+                        let log = log?;
+                        state.track_block(log.block_number);
+                        return Some((log, state));
+                    }
+                    // the collector holds the pool set, so the channel never closes
+                    Ok(()) = state.pools.changed() => {
+                        let addresses = state.pools.borrow_and_update().clone();
+                        if let Err(e) = self.rescope(&mut state, addresses).await {
+                            warn!("MultiLogCollector failed to resubscribe to pools: {}", e);
+                            return None;
+                        }
+                    }
+                }
+            }
+        });
+        Ok(Box::pin(stream))
+    }
+
+    /// Replaces the subscriptions of `state` with subscriptions scoped to
+    /// `addresses`, and queues the recent logs of the added pools.
+    async fn rescope<'a>(
+        &'a self,
+        state: &mut PoolScopedState<'a>,
+        addresses: BTreeSet<Address>,
+    ) -> Result<()> {
+        // subscribe before dropping the old subscriptions, so that no log falls in between
+        state.live = self
+            .subscribe_filters(&self.filters_for(&addresses))
+            .await?;
+        let added: BTreeSet<_> = addresses.difference(&state.addresses).copied().collect();
+        info!(
+            "MultiLogCollector now tracking {} pools ({} added, {} removed)",
+            addresses.len(),
+            added.len(),
+            state.addresses.len() + added.len() - addresses.len()
+        );
+        state.addresses = addresses;
+
+        if let (Some(last_block), false) = (state.last_block, added.is_empty()) {
+            let head = self.provider.get_block_number().await?;
+            let logs = self
+                .get_logs(&self.filters_for(&added), last_block, head)
+                .await?;
+            state.pending.extend(logs);
+        }
+        Ok(())
+    }
+}
+
+/// The state of a stream of logs scoped to a [PoolSet].
+struct PoolScopedState<'a> {
+    live: CollectorStream<'a, Log>,
+    pools: watch::Receiver<BTreeSet<Address>>,
+    /// The pools the live subscriptions are scoped to
+    addresses: BTreeSet<Address>,
+    /// The highest block seen so far
+    last_block: Option<u64>,
+    /// Backfilled logs of added pools, waiting to be emitted
+    pending: VecDeque<Log>,
+}
+
+impl PoolScopedState<'_> {
+    fn track_block(&mut self, block: Option<u64>) {
+        if let Some(block) = block {
+            self.last_block = Some(self.last_block.map_or(block, |last| last.max(block)));
+        }
+    }
+}
+
+#[async_trait]
+impl<P: Provider + 'static> Resubscribe<Log> for MultiLogCollector<P> {
+    fn name(&self) -> &'static str {
+        "MultiLogCollector"
+    }
+
+    async fn subscribe(&self) -> Result<CollectorStream<'_, Log>> {
+        if let Some(interval) = self.poll_interval {
+            return polling_stream(self, interval).await;
+        }
+        match &self.pools {
+            Some(pools) => self.pool_scoped_stream(pools.subscribe()).await,
+            None => self.subscribe_filters(&self.filters).await,
+        }
+    }
+
+    async fn head(&self) -> Result<Option<u64>> {
+        Ok(Some(self.provider.get_block_number().await?))
+    }
+
+    async fn backfill(&self, from_block: u64, to_block: u64) -> Result<Vec<Log>> {
+        self.get_logs(&self.current_filters(), from_block, to_block)
+            .await
+    }
+
+    fn block_number(&self, log: &Log) -> Option<u64> {
+        log.block_number
+    }
+}
+
+#[async_trait]
+impl<P: Provider + 'static> Collector<CollectorEvent<Log>> for MultiLogCollector<P> {
+    async fn get_event_stream(&self) -> Result<CollectorStream<'_, CollectorEvent<Log>>> {
+        let stream: CollectorStream<'_, CollectorEvent<Log>> = match self.start_block {
+            // backfilling an empty pool set would fetch no logs and skip the
+            // blocks since `from_block`, so the backfill waits for the pools.
+            // It runs within the stream, so that the engine can still shut down
+            Some(from_block) => {
+                let stream = stream::once(async move {
+                    self.wait_for_pools().await;
+                    backfilling_stream(self, self.backoff.clone(), from_block).await
+                })
+                .filter_map(move |stream| async move {
+                    stream
+                        .map_err(|e| {
+                            warn!(
+                                "MultiLogCollector failed to backfill from block {}: {}",
+                                from_block, e
+                            )
+                        })
+                        .ok()
+                })
+                .flatten();
+                Box::pin(stream)
+            }
+            None => resubscribing_stream(self, self.backoff.clone(), self.subscribe().await?),
+        };
+        let stream = with_reorgs(Resubscribe::name(self), stream);
+        Ok(with_block_ordering(stream, self.block_flush_delay))
+    }
 }
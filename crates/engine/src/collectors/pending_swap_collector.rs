@@ -0,0 +1,622 @@
+use alloy::{
+    consensus::Transaction as _,
+    network::TransactionResponse,
+    primitives::{Address, Bytes, U256},
+    providers::Provider,
+    rpc::types::Transaction,
+    sol,
+    sol_types::{sol_data, SolInterface, SolType},
+};
+use async_trait::async_trait;
+use futures::StreamExt;
+use std::sync::Arc;
+use tx_executor::bindings::{
+    iaerodromerouter::IAerodromeRouter::IAerodromeRouterCalls,
+    iuniswapv2router::IUniswapV2Router::IUniswapV2RouterCalls,
+    iuniswapv3router::IUniswapV3Router::IUniswapV3RouterCalls,
+};
+
+use crate::backoff::Backoff;
+use crate::collectors::resubscribe::{resubscribing_stream, Resubscribe};
+use crate::types::{Collector, CollectorEvent, CollectorStream};
+use eyre::Result;
+
+sol! {
+    interface IUniversalRouter {
+        function execute(bytes commands, bytes[] inputs, uint256 deadline) external payable;
+        function execute(bytes commands, bytes[] inputs) external payable;
+    }
+
+    interface IMulticall {
+        function multicall(bytes[] data) external payable returns (bytes[] memory results);
+        function multicall(uint256 deadline, bytes[] data) external payable returns (bytes[] memory results);
+    }
+}
+
+/// Universal router commands, masked with [COMMAND_TYPE_MASK].
+const V3_SWAP_EXACT_IN: u8 = 0x00;
+const V3_SWAP_EXACT_OUT: u8 = 0x01;
+const V2_SWAP_EXACT_IN: u8 = 0x08;
+const V2_SWAP_EXACT_OUT: u8 = 0x09;
+/// The high bits of a universal router command are flags.
+const COMMAND_TYPE_MASK: u8 = 0x3f;
+/// Multicalls nested deeper than this are not decoded. Routers never nest
+/// them this deep, and mempool calldata is untrusted.
+const MAX_MULTICALL_DEPTH: usize = 4;
+
+/// Inputs of the universal router V3 swap commands:
+/// `(recipient, amount, amountLimit, path, payerIsUser)`.
+type UniversalV3SwapInput = (
+    sol_data::Address,
+    sol_data::Uint<256>,
+    sol_data::Uint<256>,
+    sol_data::Bytes,
+    sol_data::Bool,
+);
+/// Inputs of the universal router V2 swap commands:
+/// `(recipient, amount, amountLimit, path, payerIsUser)`.
+type UniversalV2SwapInput = (
+    sol_data::Address,
+    sol_data::Uint<256>,
+    sol_data::Uint<256>,
+    sol_data::Array<sol_data::Address>,
+    sol_data::Bool,
+);
+
+/// The protocol of the pools a swap goes through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwapProtocol {
+    UniswapV2,
+    UniswapV3,
+    Aerodrome,
+}
+
+/// The amounts of a swap, as bounded by the swapper.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwapAmount {
+    /// Sells exactly `amount_in`, for at least `amount_out_min`
+    ExactIn {
+        amount_in: U256,
+        amount_out_min: U256,
+    },
+    /// Buys exactly `amount_out`, for at most `amount_in_max`
+    ExactOut {
+        amount_out: U256,
+        amount_in_max: U256,
+    },
+}
+
+/// A swap decoded from a pending router call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingSwap {
+    /// The router the transaction is sent to
+    pub router: Address,
+    pub protocol: SwapProtocol,
+    /// The tokens the swap goes through, from the token sold to the token bought
+    pub path: Vec<Address>,
+    pub amount: SwapAmount,
+    /// The recipient of the tokens bought. Universal router swaps may use the
+    /// `MSG_SENDER` (`0x..01`) and `ADDRESS_THIS` (`0x..02`) placeholders.
+    pub recipient: Address,
+    /// The deadline of the swap, if the router call has one
+    pub deadline: Option<U256>,
+}
+
+impl PendingSwap {
+    /// The token sold.
+    pub fn token_in(&self) -> Option<Address> {
+        self.path.first().copied()
+    }
+
+    /// The token bought.
+    pub fn token_out(&self) -> Option<Address> {
+        self.path.last().copied()
+    }
+}
+
+/// A pending transaction, along with the swaps decoded from it.
+#[derive(Debug, Clone)]
+pub struct PendingTransaction {
+    pub tx: Transaction,
+    /// The swaps performed by the transaction, empty if it is not a known router call
+    pub swaps: Vec<PendingSwap>,
+}
+
+/// Decodes the swaps of a call to a Uniswap V2, Uniswap V3, Aerodrome or
+/// universal router. Calls are recognized by their selector, whatever their
+/// destination, and `value` is used as the input amount of ETH swaps.
+/// Returns an empty list for any other call.
+pub fn decode_swaps(router: Address, input: &[u8], value: U256) -> Vec<PendingSwap> {
+    let mut swaps = vec![];
+    decode_into(router, input, value, None, 0, &mut swaps);
+    swaps
+}
+
+/// Decodes the swaps of `input`, a call `depth` multicalls deep.
+fn decode_into(
+    router: Address,
+    input: &[u8],
+    value: U256,
+    deadline: Option<U256>,
+    depth: usize,
+    swaps: &mut Vec<PendingSwap>,
+) {
+    let swap = |protocol, path, amount, recipient, deadline| PendingSwap {
+        router,
+        protocol,
+        path,
+        amount,
+        recipient,
+        deadline,
+    };
+    let exact_in = |amount_in, amount_out_min| SwapAmount::ExactIn {
+        amount_in,
+        amount_out_min,
+    };
+    let exact_out = |amount_out, amount_in_max| SwapAmount::ExactOut {
+        amount_out,
+        amount_in_max,
+    };
+
+    if let Ok(call) = IUniswapV2RouterCalls::abi_decode(input, false) {
+        let protocol = SwapProtocol::UniswapV2;
+        let decoded = match call {
+            IUniswapV2RouterCalls::swapExactTokensForTokens(c) => Some((
+                c.path,
+                exact_in(c.amountIn, c.amountOutMin),
+                c.to,
+                c.deadline,
+            )),
+            IUniswapV2RouterCalls::swapExactTokensForETH(c) => Some((
+                c.path,
+                exact_in(c.amountIn, c.amountOutMin),
+                c.to,
+                c.deadline,
+            )),
+            IUniswapV2RouterCalls::swapExactETHForTokens(c) => {
+                Some((c.path, exact_in(value, c.amountOutMin), c.to, c.deadline))
+            }
+            IUniswapV2RouterCalls::swapTokensForExactTokens(c) => Some((
+                c.path,
+                exact_out(c.amountOut, c.amountInMax),
+                c.to,
+                c.deadline,
+            )),
+            IUniswapV2RouterCalls::swapTokensForExactETH(c) => Some((
+                c.path,
+                exact_out(c.amountOut, c.amountInMax),
+                c.to,
+                c.deadline,
+            )),
+            IUniswapV2RouterCalls::swapETHForExactTokens(c) => {
+                Some((c.path, exact_out(c.amountOut, value), c.to, c.deadline))
+            }
+            _ => None,
+        };
+        if let Some((path, amount, recipient, deadline)) = decoded {
+            swaps.push(swap(protocol, path, amount, recipient, Some(deadline)));
+        }
+        return;
+    }
+
+    if let Ok(call) = IUniswapV3RouterCalls::abi_decode(input, false) {
+        let protocol = SwapProtocol::UniswapV3;
+        let decoded = match call {
+            IUniswapV3RouterCalls::exactInputSingle(c) => {
+                let p = c.params;
+                Some((
+                    vec![p.tokenIn, p.tokenOut],
+                    exact_in(p.amountIn, p.amountOutMinimum),
+                    p.recipient,
+                ))
+            }
+            IUniswapV3RouterCalls::exactInput(c) => {
+                let p = c.params;
+                decode_v3_path(&p.path)
+                    .map(|path| (path, exact_in(p.amountIn, p.amountOutMinimum), p.recipient))
+            }
+            IUniswapV3RouterCalls::exactOutputSingle(c) => {
+                let p = c.params;
+                Some((
+                    vec![p.tokenIn, p.tokenOut],
+                    exact_out(p.amountOut, p.amountInMaximum),
+                    p.recipient,
+                ))
+            }
+            IUniswapV3RouterCalls::exactOutput(c) => {
+                let p = c.params;
+                // exact output paths are encoded from the token bought to the token sold
+                decode_v3_path(&p.path).map(|mut path| {
+                    path.reverse();
+                    (path, exact_out(p.amountOut, p.amountInMaximum), p.recipient)
+                })
+            }
+        };
+        if let Some((path, amount, recipient)) = decoded {
+            swaps.push(swap(protocol, path, amount, recipient, deadline));
+        }
+        return;
+    }
+
+    if let Ok(call) = IAerodromeRouterCalls::abi_decode(input, false) {
+        let protocol = SwapProtocol::Aerodrome;
+        let decoded = match call {
+            IAerodromeRouterCalls::swapExactTokensForTokens(c) => Some((
+                c.routes,
+                exact_in(c.amountIn, c.amountOutMin),
+                c.to,
+                c.deadline,
+            )),
+            IAerodromeRouterCalls::swapExactTokensForTokensSupportingFeeOnTransferTokens(c) => {
+                Some((
+                    c.routes,
+                    exact_in(c.amountIn, c.amountOutMin),
+                    c.to,
+                    c.deadline,
+                ))
+            }
+            IAerodromeRouterCalls::swapExactTokensForETH(c) => Some((
+                c.routes,
+                exact_in(c.amountIn, c.amountOutMin),
+                c.to,
+                c.deadline,
+            )),
+            IAerodromeRouterCalls::swapExactTokensForETHSupportingFeeOnTransferTokens(c) => Some((
+                c.routes,
+                exact_in(c.amountIn, c.amountOutMin),
+                c.to,
+                c.deadline,
+            )),
+            IAerodromeRouterCalls::swapExactETHForTokens(c) => {
+                Some((c.routes, exact_in(value, c.amountOutMin), c.to, c.deadline))
+            }
+            IAerodromeRouterCalls::swapExactETHForTokensSupportingFeeOnTransferTokens(c) => {
+                Some((c.routes, exact_in(value, c.amountOutMin), c.to, c.deadline))
+            }
+            _ => None,
+        };
+        if let Some((routes, amount, recipient, deadline)) = decoded {
+            if let Some(first) = routes.first() {
+                let path = std::iter::once(first.from)
+                    .chain(routes.iter().map(|route| route.to))
+                    .collect();
+                swaps.push(swap(protocol, path, amount, recipient, Some(deadline)));
+            }
+        }
+        return;
+    }
+
+    if let Ok(call) = IUniversalRouter::IUniversalRouterCalls::abi_decode(input, false) {
+        let (commands, inputs, deadline) = match call {
+            IUniversalRouter::IUniversalRouterCalls::execute_0(c) => {
+                (c.commands, c.inputs, Some(c.deadline))
+            }
+            IUniversalRouter::IUniversalRouterCalls::execute_1(c) => (c.commands, c.inputs, None),
+        };
+        for (command, input) in commands.iter().zip(&inputs) {
+            if let Some(swap) = decode_universal_command(router, *command, input, deadline) {
+                swaps.push(swap);
+            }
+        }
+        return;
+    }
+
+    // router multicalls wrap swaps, typically with a deadline for all of them
+    if depth >= MAX_MULTICALL_DEPTH {
+        return;
+    }
+    if let Ok(call) = IMulticall::IMulticallCalls::abi_decode(input, false) {
+        let (calls, deadline) = match call {
+            IMulticall::IMulticallCalls::multicall_0(c) => (c.data, deadline),
+            IMulticall::IMulticallCalls::multicall_1(c) => (c.data, Some(c.deadline)),
+        };
+        for call in calls {
+            decode_into(router, &call, value, deadline, depth + 1, swaps);
+        }
+    }
+}
+
+/// Decodes a single swap command of the universal router.
+fn decode_universal_command(
+    router: Address,
+    command: u8,
+    input: &Bytes,
+    deadline: Option<U256>,
+) -> Option<PendingSwap> {
+    let (protocol, path, recipient, amount, limit) = match command & COMMAND_TYPE_MASK {
+        V3_SWAP_EXACT_IN | V3_SWAP_EXACT_OUT => {
+            let (recipient, amount, limit, path, _) =
+                UniversalV3SwapInput::abi_decode_params(input, false).ok()?;
+            let mut path = decode_v3_path(&path)?;
+            if command & COMMAND_TYPE_MASK == V3_SWAP_EXACT_OUT {
+                path.reverse();
+            }
+            (SwapProtocol::UniswapV3, path, recipient, amount, limit)
+        }
+        V2_SWAP_EXACT_IN | V2_SWAP_EXACT_OUT => {
+            let (recipient, amount, limit, path, _) =
+                UniversalV2SwapInput::abi_decode_params(input, false).ok()?;
+            (SwapProtocol::UniswapV2, path, recipient, amount, limit)
+        }
+        _ => return None,
+    };
+
+    let amount = match command & COMMAND_TYPE_MASK {
+        V3_SWAP_EXACT_IN | V2_SWAP_EXACT_IN => SwapAmount::ExactIn {
+            amount_in: amount,
+            amount_out_min: limit,
+        },
+        _ => SwapAmount::ExactOut {
+            amount_out: amount,
+            amount_in_max: limit,
+        },
+    };
+    Some(PendingSwap {
+        router,
+        protocol,
+        path,
+        amount,
+        recipient,
+        deadline,
+    })
+}
+
+/// Decodes a Uniswap V3 path, made of 20 byte tokens separated by 3 byte fees.
+fn decode_v3_path(path: &[u8]) -> Option<Vec<Address>> {
+    if path.len() < 20 || (path.len() - 20) % 23 != 0 {
+        return None;
+    }
+    let hops = (path.len() - 20) / 23;
+    Some(
+        (0..=hops)
+            .map(|i| Address::from_slice(&path[i * 23..i * 23 + 20]))
+            .collect(),
+    )
+}
+
+/// A collector that listens for full pending transactions in the mempool, and
+/// decodes the swaps of known router calls into [PendingSwap]s.
+///
+/// Uniswap V2 and V3 routers, Aerodrome routers and the universal router are
+/// supported, including swaps wrapped in router multicalls. Transactions can be
+/// restricted to a set of routers with [PendingSwapCollector::with_routers].
+///
+/// When the subscription drops, the collector resubscribes with [Backoff]. Pending
+/// transactions seen by the node while the collector was down cannot be backfilled.
+pub struct PendingSwapCollector<P> {
+    provider: Arc<P>,
+    backoff: Backoff,
+    /// Only transactions sent to these routers are decoded, if not empty
+    routers: Vec<Address>,
+    /// Whether transactions without any decoded swap are skipped
+    swaps_only: bool,
+}
+
+impl<P> PendingSwapCollector<P> {
+    pub fn new(provider: Arc<P>) -> Self {
+        Self {
+            provider,
+            backoff: Backoff::default(),
+            routers: vec![],
+            swaps_only: true,
+        }
+    }
+
+    /// Sets the backoff used when resubscribing after the stream ends.
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Only decodes transactions sent to `routers`. By default, transactions
+    /// sent to any address are decoded.
+    pub fn with_routers(mut self, routers: Vec<Address>) -> Self {
+        self.routers = routers;
+        self
+    }
+
+    /// Sets whether transactions without any decoded swap are skipped, which
+    /// is the default. Otherwise, every pending transaction is emitted.
+    pub fn with_swaps_only(mut self, swaps_only: bool) -> Self {
+        self.swaps_only = swaps_only;
+        self
+    }
+
+    fn decode(&self, tx: Transaction) -> Option<PendingTransaction> {
+        let swaps = match tx.to() {
+            Some(to) if self.routers.is_empty() || self.routers.contains(&to) => {
+                decode_swaps(to, tx.input(), tx.value())
+            }
+            _ => vec![],
+        };
+        if swaps.is_empty() && self.swaps_only {
+            return None;
+        }
+        Some(PendingTransaction { tx, swaps })
+    }
+}
+
+#[async_trait]
+impl<P: Provider> Resubscribe<PendingTransaction> for PendingSwapCollector<P> {
+    fn name(&self) -> &'static str {
+        "PendingSwapCollector"
+    }
+
+    async fn subscribe(&self) -> Result<CollectorStream<'_, PendingTransaction>> {
+        let sub = self.provider.subscribe_full_pending_transactions().await?;
+        let stream = sub.into_stream().filter_map(move |tx| {
+            let pending = self.decode(tx);
+            if let Some(pending) = &pending {
+                tracing::trace!(
+                    "Pending transaction {} with {} swaps",
+                    pending.tx.tx_hash(),
+                    pending.swaps.len()
+                );
+            }
+            futures::future::ready(pending)
+        });
+        Ok(Box::pin(stream))
+    }
+}
+
+/// Implementation of the [Collector](Collector) trait for the [PendingSwapCollector].
+#[async_trait]
+impl<P> Collector<CollectorEvent<PendingTransaction>> for PendingSwapCollector<P>
+where
+    P: Provider,
+{
+    async fn get_event_stream(
+        &self,
+    ) -> Result<CollectorStream<'_, CollectorEvent<PendingTransaction>>> {
+        let stream = self.subscribe().await?;
+        Ok(resubscribing_stream(self, self.backoff.clone(), stream))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::sol_types::SolCall;
+    use tx_executor::bindings::{
+        iuniswapv2router::IUniswapV2Router, iuniswapv3router::IUniswapV3Router,
+    };
+
+    fn token(byte: u8) -> Address {
+        Address::repeat_byte(byte)
+    }
+
+    /// Encodes a V3 path with a 0.05% fee between each token.
+    fn v3_path(tokens: &[Address]) -> Bytes {
+        let mut path = tokens[0].to_vec();
+        for token in &tokens[1..] {
+            path.extend_from_slice(&[0x00, 0x01, 0xf4]);
+            path.extend_from_slice(token.as_slice());
+        }
+        path.into()
+    }
+
+    #[test]
+    fn test_decode_v2_swap() {
+        let router = token(0xaa);
+        let call = IUniswapV2Router::swapExactETHForTokensCall {
+            amountOutMin: U256::from(90),
+            path: vec![token(1), token(2)],
+            to: token(3),
+            deadline: U256::from(1_000),
+        };
+        let swaps = decode_swaps(router, &call.abi_encode(), U256::from(100));
+        assert_eq!(
+            swaps,
+            vec![PendingSwap {
+                router,
+                protocol: SwapProtocol::UniswapV2,
+                path: vec![token(1), token(2)],
+                amount: SwapAmount::ExactIn {
+                    amount_in: U256::from(100),
+                    amount_out_min: U256::from(90),
+                },
+                recipient: token(3),
+                deadline: Some(U256::from(1_000)),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_decode_v3_exact_output_in_multicall() {
+        let call = IUniswapV3Router::exactOutputCall {
+            params: IUniswapV3Router::ExactOutputParams {
+                path: v3_path(&[token(3), token(2), token(1)]),
+                recipient: token(4),
+                amountOut: U256::from(50),
+                amountInMaximum: U256::from(60),
+            },
+        };
+        let multicall = IMulticall::multicall_1Call {
+            deadline: U256::from(7),
+            data: vec![call.abi_encode().into()],
+        };
+        let swaps = decode_swaps(token(0xaa), &multicall.abi_encode(), U256::ZERO);
+        assert_eq!(swaps.len(), 1);
+        assert_eq!(swaps[0].protocol, SwapProtocol::UniswapV3);
+        assert_eq!(swaps[0].path, vec![token(1), token(2), token(3)]);
+        assert_eq!(swaps[0].token_in(), Some(token(1)));
+        assert_eq!(swaps[0].deadline, Some(U256::from(7)));
+        assert_eq!(
+            swaps[0].amount,
+            SwapAmount::ExactOut {
+                amount_out: U256::from(50),
+                amount_in_max: U256::from(60),
+            }
+        );
+    }
+
+    #[test]
+    fn test_decode_universal_router() {
+        let v3_input = UniversalV3SwapInput::abi_encode_params(&(
+            token(9),
+            U256::from(100),
+            U256::from(90),
+            v3_path(&[token(1), token(2)]),
+            true,
+        ));
+        let v2_input = UniversalV2SwapInput::abi_encode_params(&(
+            token(9),
+            U256::from(80),
+            U256::from(70),
+            vec![token(2), token(3)],
+            false,
+        ));
+        // 0x0b (WRAP_ETH) is not a swap, 0x88 is V2_SWAP_EXACT_IN allowed to revert
+        let call = IUniversalRouter::execute_0Call {
+            commands: vec![0x0b, V3_SWAP_EXACT_IN, 0x80 | V2_SWAP_EXACT_IN].into(),
+            inputs: vec![Bytes::new(), v3_input.into(), v2_input.into()],
+            deadline: U256::from(5),
+        };
+        let swaps = decode_swaps(token(0xaa), &call.abi_encode(), U256::ZERO);
+        assert_eq!(swaps.len(), 2);
+        assert_eq!(swaps[0].protocol, SwapProtocol::UniswapV3);
+        assert_eq!(swaps[0].path, vec![token(1), token(2)]);
+        assert_eq!(swaps[1].protocol, SwapProtocol::UniswapV2);
+        assert_eq!(swaps[1].path, vec![token(2), token(3)]);
+        assert_eq!(
+            swaps[1].amount,
+            SwapAmount::ExactIn {
+                amount_in: U256::from(80),
+                amount_out_min: U256::from(70),
+            }
+        );
+        assert_eq!(swaps[1].deadline, Some(U256::from(5)));
+    }
+
+    #[test]
+    fn test_deeply_nested_multicalls_are_not_decoded() {
+        let call = IUniswapV2Router::swapExactETHForTokensCall {
+            amountOutMin: U256::from(90),
+            path: vec![token(1), token(2)],
+            to: token(3),
+            deadline: U256::from(1_000),
+        };
+        let nest = |depth: usize| {
+            (0..depth).fold(call.abi_encode(), |data, _| {
+                IMulticall::multicall_0Call {
+                    data: vec![data.into()],
+                }
+                .abi_encode()
+            })
+        };
+        let router = token(0xaa);
+        assert_eq!(
+            decode_swaps(router, &nest(MAX_MULTICALL_DEPTH), U256::ZERO).len(),
+            1
+        );
+        assert!(decode_swaps(router, &nest(MAX_MULTICALL_DEPTH + 1), U256::ZERO).is_empty());
+        // deeper payloads are dropped without being decoded level by level
+        assert!(decode_swaps(router, &nest(64), U256::ZERO).is_empty());
+    }
+
+    #[test]
+    fn test_decode_unknown_call() {
+        assert!(decode_swaps(token(0xaa), &[0xde, 0xad, 0xbe, 0xef], U256::ZERO).is_empty());
+        assert!(decode_swaps(token(0xaa), &[], U256::ZERO).is_empty());
+    }
+}
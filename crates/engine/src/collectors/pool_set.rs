@@ -0,0 +1,133 @@
+use alloy::primitives::Address;
+use alloy::rpc::types::Filter;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use tokio::sync::watch;
+
+/// Maximum number of addresses in a single log subscription. Larger pool sets
+/// are split across several subscriptions.
+pub const MAX_ADDRESSES_PER_FILTER: usize = 500;
+
+/// The set of pools tracked by a strategy, shared with the collectors that
+/// subscribe to their logs. Collectors scope their filters to the pools in the
+/// set, and resubscribe whenever pools are added or removed.
+///
+/// Cloning the set returns a handle to the same pools.
+#[derive(Debug, Clone)]
+pub struct PoolSet {
+    pools: Arc<watch::Sender<BTreeSet<Address>>>,
+}
+
+impl Default for PoolSet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PoolSet {
+    /// Creates an empty pool set. Collectors do not subscribe to any log until
+    /// pools are added.
+    pub fn new() -> Self {
+        Self {
+            pools: Arc::new(watch::channel(BTreeSet::new()).0),
+        }
+    }
+
+    /// Adds pools to the set. Collectors only resubscribe if a pool was not in
+    /// the set yet.
+    pub fn insert(&self, pools: impl IntoIterator<Item = Address>) {
+        let pools: Vec<_> = pools.into_iter().collect();
+        self.pools.send_if_modified(|set| {
+            let len = set.len();
+            set.extend(pools);
+            set.len() != len
+        });
+    }
+
+    /// Removes pools from the set. Collectors only resubscribe if a pool was
+    /// in the set.
+    pub fn remove(&self, pools: impl IntoIterator<Item = Address>) {
+        let pools: Vec<_> = pools.into_iter().collect();
+        self.pools.send_if_modified(|set| {
+            let len = set.len();
+            for pool in &pools {
+                set.remove(pool);
+            }
+            set.len() != len
+        });
+    }
+
+    /// Returns the pools currently in the set.
+    pub fn addresses(&self) -> BTreeSet<Address> {
+        self.pools.borrow().clone()
+    }
+
+    /// Returns the number of pools in the set.
+    pub fn len(&self) -> usize {
+        self.pools.borrow().len()
+    }
+
+    /// Returns true if the set has no pools.
+    pub fn is_empty(&self) -> bool {
+        self.pools.borrow().is_empty()
+    }
+
+    pub(crate) fn subscribe(&self) -> watch::Receiver<BTreeSet<Address>> {
+        self.pools.subscribe()
+    }
+}
+
+/// Scopes every filter to `addresses`, splitting them into filters of at most
+/// `max_addresses` addresses each. Returns no filter at all if `addresses` is
+/// empty, since a filter without addresses would match every contract.
+pub(crate) fn scoped_filters(
+    filters: &[Filter],
+    addresses: &BTreeSet<Address>,
+    max_addresses: usize,
+) -> Vec<Filter> {
+    let addresses: Vec<_> = addresses.iter().copied().collect();
+    filters
+        .iter()
+        .flat_map(|filter| {
+            addresses
+                .chunks(max_addresses.max(1))
+                .map(|chunk| filter.clone().address(chunk.to_vec()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pool_set_only_notifies_changes() {
+        let pools = PoolSet::new();
+        let mut receiver = pools.subscribe();
+
+        pools.insert([Address::repeat_byte(1), Address::repeat_byte(2)]);
+        assert!(receiver.has_changed().unwrap());
+        receiver.borrow_and_update();
+
+        pools.insert([Address::repeat_byte(1)]);
+        pools.remove([Address::repeat_byte(3)]);
+        assert!(!receiver.has_changed().unwrap());
+
+        pools.remove([Address::repeat_byte(1)]);
+        assert!(receiver.has_changed().unwrap());
+        assert_eq!(pools.len(), 1);
+    }
+
+    #[test]
+    fn test_scoped_filters_split_large_sets() {
+        let filters = vec![Filter::new(), Filter::new()];
+        let addresses: BTreeSet<_> = (1..=5).map(Address::repeat_byte).collect();
+
+        let scoped = scoped_filters(&filters, &addresses, 2);
+        assert_eq!(scoped.len(), 6);
+        let total: usize = scoped.iter().map(|f| f.address.len()).sum();
+        assert_eq!(total, 10);
+
+        assert!(scoped_filters(&filters, &BTreeSet::new(), 2).is_empty());
+    }
+}
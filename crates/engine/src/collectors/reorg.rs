@@ -0,0 +1,200 @@
+use crate::collectors::block_collector::REORG_TRACKING_DEPTH;
+use crate::types::{CollectorEvent, CollectorStream, Reorg};
+use alloy::{primitives::B256, rpc::types::Log};
+use futures::{stream, StreamExt};
+use std::collections::{BTreeMap, BTreeSet};
+use tracing::warn;
+
+/// Turns the logs of a chain reorganization into [Reorg] events.
+///
+/// Subscriptions report reorgs by re-sending the logs of the abandoned branch
+/// with `removed: true`, which are swallowed here. Logs fetched with `eth_getLogs`
+/// never are, so a log whose block hash differs from the one previously seen
+/// at the same height is reported as a reorg as well.
+///
+/// Collectors merging several subscriptions receive the removed logs of a
+/// reorg interleaved with the canonical logs replacing them. The blocks of the
+/// abandoned branch are remembered by hash, so that each fork point is reported
+/// once whatever the order the logs arrive in.
+#[derive(Debug)]
+pub(crate) struct LogReorgTracker {
+    collector: &'static str,
+    /// The hashes of the recent blocks logs were seen in
+    hashes: BTreeMap<u64, B256>,
+    /// The highest block logs were seen in
+    last_block: Option<u64>,
+    /// The blocks, by number and hash, that were abandoned by a reported reorg
+    abandoned: BTreeSet<(u64, B256)>,
+}
+
+impl LogReorgTracker {
+    pub(crate) fn new(collector: &'static str) -> Self {
+        Self {
+            collector,
+            hashes: BTreeMap::new(),
+            last_block: None,
+            abandoned: BTreeSet::new(),
+        }
+    }
+
+    /// Returns the events to emit in place of `event`.
+    pub(crate) fn apply(&mut self, event: CollectorEvent<Log>) -> Vec<CollectorEvent<Log>> {
+        let CollectorEvent::Event(log) = event else {
+            return vec![event];
+        };
+        let (Some(block), Some(hash)) = (log.block_number, log.block_hash) else {
+            return vec![CollectorEvent::Event(log)];
+        };
+
+        if log.removed {
+            // the removed logs of a reorg are reported together, once
+            if self.abandoned.contains(&(block, hash)) {
+                return vec![];
+            }
+            let reorg = self.reorg(block);
+            self.abandoned.insert((block, hash));
+            return vec![reorg];
+        }
+        // the branch may have become canonical again
+        self.abandoned.remove(&(block, hash));
+
+        let mut events = vec![];
+        if self.hashes.get(&block).is_some_and(|known| *known != hash) {
+            events.push(self.reorg(block));
+        }
+        self.hashes.insert(block, hash);
+        self.last_block = Some(self.last_block.map_or(block, |last| last.max(block)));
+        if let Some(oldest) = block.checked_sub(REORG_TRACKING_DEPTH) {
+            self.hashes = self.hashes.split_off(&oldest);
+            self.abandoned = self.abandoned.split_off(&(oldest, B256::ZERO));
+        }
+        events.push(CollectorEvent::Event(log));
+        events
+    }
+
+    /// Forgets the blocks from `from_block` on, and returns the reorg event.
+    fn reorg(&mut self, from_block: u64) -> CollectorEvent<Log> {
+        let to_block = self
+            .last_block
+            .map_or(from_block, |last| last.max(from_block));
+        warn!(
+            "{} detected a reorg of blocks {}..={}",
+            self.collector, from_block, to_block
+        );
+        let abandoned = self.hashes.split_off(&from_block);
+        self.abandoned.extend(abandoned);
+        self.last_block = from_block.checked_sub(1);
+        CollectorEvent::Reorg(Reorg {
+            collector: self.collector,
+            from_block,
+            to_block,
+        })
+    }
+}
+
+/// Wraps a stream of logs so that chain reorganizations are reported with
+/// [Reorg] events instead of removed logs.
+pub(crate) fn with_reorgs<'a>(
+    collector: &'static str,
+    stream: CollectorStream<'a, CollectorEvent<Log>>,
+) -> CollectorStream<'a, CollectorEvent<Log>> {
+    let mut tracker = LogReorgTracker::new(collector);
+    Box::pin(stream.flat_map(move |event| stream::iter(tracker.apply(event))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log(block: u64, hash: u8, removed: bool) -> CollectorEvent<Log> {
+        CollectorEvent::Event(Log {
+            block_number: Some(block),
+            block_hash: Some(B256::repeat_byte(hash)),
+            removed,
+            ..Default::default()
+        })
+    }
+
+    fn reorg(event: &CollectorEvent<Log>) -> Option<(u64, u64)> {
+        match event {
+            CollectorEvent::Reorg(reorg) => Some((reorg.from_block, reorg.to_block)),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_removed_logs_become_reorgs() {
+        let mut tracker = LogReorgTracker::new("test");
+        assert_eq!(tracker.apply(log(1, 1, false)).len(), 1);
+        assert_eq!(tracker.apply(log(2, 2, false)).len(), 1);
+        assert_eq!(tracker.apply(log(3, 3, false)).len(), 1);
+
+        // blocks 2 and 3 are replaced
+        let events = tracker.apply(log(2, 2, true));
+        assert_eq!(events.iter().map(reorg).collect::<Vec<_>>(), [Some((2, 3))]);
+        assert!(tracker.apply(log(2, 2, true)).is_empty());
+        assert!(tracker.apply(log(3, 3, true)).is_empty());
+
+        // the canonical logs are forwarded
+        let events = tracker.apply(log(2, 0x22, false));
+        assert_eq!(events.iter().map(reorg).collect::<Vec<_>>(), [None]);
+        let events = tracker.apply(log(3, 0x33, false));
+        assert_eq!(events.iter().map(reorg).collect::<Vec<_>>(), [None]);
+
+        // removed logs sent from the head down are reported as adjacent reorgs
+        let events = tracker.apply(log(3, 0x33, true));
+        assert_eq!(events.iter().map(reorg).collect::<Vec<_>>(), [Some((3, 3))]);
+        let events = tracker.apply(log(2, 0x22, true));
+        assert_eq!(events.iter().map(reorg).collect::<Vec<_>>(), [Some((2, 2))]);
+    }
+
+    #[test]
+    fn test_conflicting_block_hash_is_a_reorg() {
+        let mut tracker = LogReorgTracker::new("test");
+        tracker.apply(log(1, 1, false));
+        tracker.apply(log(2, 2, false));
+        tracker.apply(log(4, 4, false));
+
+        // a log of another block 2 replaces blocks 2 to 4
+        let events = tracker.apply(log(2, 0x22, false));
+        assert_eq!(
+            events.iter().map(reorg).collect::<Vec<_>>(),
+            [Some((2, 4)), None]
+        );
+        assert_eq!(tracker.apply(log(2, 0x22, false)).len(), 1);
+        assert_eq!(tracker.apply(log(4, 0x44, false)).len(), 1);
+    }
+
+    #[test]
+    fn test_interleaved_subscriptions_report_a_reorg_once() {
+        let mut tracker = LogReorgTracker::new("test");
+        // two subscriptions see logs of blocks 1 to 3
+        for event in [log(1, 1, false), log(2, 2, false), log(2, 2, false)] {
+            tracker.apply(event);
+        }
+        tracker.apply(log(3, 3, false));
+
+        // blocks 2 and 3 are replaced. The first subscription reports its removed
+        // logs and the canonical logs before the second one reports its own
+        let events = [
+            log(2, 2, true),
+            log(3, 3, true),
+            log(2, 0x22, false),
+            log(3, 0x33, false),
+            log(2, 2, true),
+            log(3, 3, true),
+            log(2, 0x22, false),
+            log(3, 0x33, false),
+        ];
+        let reorgs: Vec<_> = events
+            .into_iter()
+            .flat_map(|event| tracker.apply(event))
+            .filter_map(|event| reorg(&event))
+            .collect();
+        assert_eq!(reorgs, [(2, 3)]);
+
+        // a later reorg of the new branch is still reported
+        let events = tracker.apply(log(3, 0x33, true));
+        assert_eq!(events.iter().map(reorg).collect::<Vec<_>>(), [Some((3, 3))]);
+    }
+}
@@ -0,0 +1,323 @@
+use crate::backoff::Backoff;
+use crate::types::{CollectorEvent, CollectorRestarted, CollectorStream};
+use alloy::{
+    providers::Provider,
+    rpc::types::{Filter, Log},
+};
+use async_trait::async_trait;
+use eyre::{eyre, Result};
+use std::{collections::VecDeque, time::Duration};
+use tokio_stream::StreamExt;
+use tracing::{info, warn};
+
+/// Maximum number of blocks requested in a single `eth_getLogs` call when backfilling.
+pub(crate) const BACKFILL_CHUNK_SIZE: u64 = 2_000;
+
+/// A source of events backed by a subscription that can be re-established.
+///
+/// Collectors implement this trait to get a stream that transparently
+/// resubscribes with [Backoff] when the underlying subscription ends, and that
+/// backfills the events missed while it was down.
+#[async_trait]
+pub(crate) trait Resubscribe<T>: Send + Sync {
+    /// Name used in logs and in [CollectorRestarted] events.
+    fn name(&self) -> &'static str;
+
+    /// Opens a new live stream of events.
+    async fn subscribe(&self) -> Result<CollectorStream<'_, T>>;
+
+    /// Returns the current chain head, used as the upper bound of a backfill.
+    async fn head(&self) -> Result<Option<u64>> {
+        Ok(None)
+    }
+
+    /// Fetches the events emitted in `[from_block, to_block]`.
+    async fn backfill(&self, _from_block: u64, _to_block: u64) -> Result<Vec<T>> {
+        Ok(vec![])
+    }
+
+    /// Returns the block an event belongs to. Used to track the backfill cursor.
+    fn block_number(&self, _event: &T) -> Option<u64> {
+        None
+    }
+}
+
+/// Wraps the `live` stream of `source` into a stream that never ends on its own:
+/// whenever the live stream ends, the source is resubscribed following `backoff`,
+/// a [CollectorRestarted] event is emitted and the missed events are backfilled.
+///
+/// The stream only ends once `backoff` runs out of retries.
+pub(crate) fn resubscribing_stream<'a, T, S>(
+    source: &'a S,
+    backoff: Backoff,
+    live: CollectorStream<'a, T>,
+) -> CollectorStream<'a, CollectorEvent<T>>
+where
+    T: Send + 'a,
+    S: Resubscribe<T> + ?Sized,
+{
+    let state = ResubscribeState {
+        source,
+        backoff,
+        live: Some(live),
+        pending: VecDeque::new(),
+        last_block: None,
+        skip_through: None,
+    };
+    state.into_stream()
+}
+
+/// Like [resubscribing_stream], but first emits the events of the blocks from
+/// `from_block` to the chain head, fetched with [Resubscribe::backfill]. The
+/// source is subscribed to before the backfill, so that no event falls in between.
+pub(crate) async fn backfilling_stream<'a, T, S>(
+    source: &'a S,
+    backoff: Backoff,
+    from_block: u64,
+) -> Result<CollectorStream<'a, CollectorEvent<T>>>
+where
+    T: Send + 'a,
+    S: Resubscribe<T> + ?Sized,
+{
+    let name = source.name();
+    let live = source.subscribe().await?;
+    let head = source
+        .head()
+        .await?
+        .ok_or_else(|| eyre!("{} cannot backfill without a chain head", name))?;
+
+    let mut pending = VecDeque::new();
+    if head >= from_block {
+        let events = source.backfill(from_block, head).await?;
+        info!(
+            "{} backfilled {} events from blocks {}..={}",
+            name,
+            events.len(),
+            from_block,
+            head
+        );
+        pending.extend(events.into_iter().map(CollectorEvent::Event));
+    }
+
+    let state = ResubscribeState {
+        source,
+        backoff,
+        live: Some(live),
+        pending,
+        last_block: Some(head),
+        skip_through: Some(head),
+    };
+    Ok(state.into_stream())
+}
+
+struct ResubscribeState<'a, T, S: ?Sized> {
+    source: &'a S,
+    backoff: Backoff,
+    live: Option<CollectorStream<'a, T>>,
+    /// Events waiting to be emitted (restart notifications and backfilled events)
+    pending: VecDeque<CollectorEvent<T>>,
+    /// The highest block seen so far
+    last_block: Option<u64>,
+    /// Live events up to this block were already emitted by a backfill
+    skip_through: Option<u64>,
+}
+
+impl<'a, T, S> ResubscribeState<'a, T, S>
+where
+    T: Send + 'a,
+    S: Resubscribe<T> + ?Sized,
+{
+    fn into_stream(self) -> CollectorStream<'a, CollectorEvent<T>> {
+        Box::pin(futures::stream::unfold(self, |mut state| async move {
+            let event = state.next_event().await?;
+            Some((event, state))
+        }))
+    }
+
+    async fn next_event(&mut self) -> Option<CollectorEvent<T>> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(event);
+            }
+
+            match self.live.as_mut() {
+                Some(live) => match live.next().await {
+                    Some(event) => {
+                        let block = self.source.block_number(&event);
+                        if let (Some(block), Some(skip_through)) = (block, self.skip_through) {
+                            if block <= skip_through {
+                                continue;
+                            }
+                            self.skip_through = None;
+                        }
+                        self.track_block(block);
+                        return Some(CollectorEvent::Event(event));
+                    }
+                    None => {
+                        warn!("{} stream ended, resubscribing...", self.source.name());
+                        self.live = None;
+                    }
+                },
+                None => {
+                    if !self.resubscribe().await {
+                        return None;
+                    }
+                }
+            }
+        }
+    }
+
+    fn track_block(&mut self, block: Option<u64>) {
+        if let Some(block) = block {
+            self.last_block = Some(self.last_block.map_or(block, |last| last.max(block)));
+        }
+    }
+
+    /// Re-establishes the live stream and queues the restart notification followed
+    /// by the backfilled events. Returns false once the backoff gives up.
+    async fn resubscribe(&mut self) -> bool {
+        let name = self.source.name();
+        let mut attempts = 0;
+        loop {
+            if !self.backoff.can_retry(attempts) {
+                warn!("{} giving up after {} attempts", name, attempts);
+                return false;
+            }
+            tokio::time::sleep(self.backoff.delay(attempts)).await;
+            attempts += 1;
+
+            match self.source.subscribe().await {
+                Ok(live) => {
+                    self.live = Some(live);
+                    break;
+                }
+                Err(e) => warn!(
+                    "{} failed to resubscribe (attempt {}): {}",
+                    name, attempts, e
+                ),
+            }
+        }
+
+        let head = self.source.head().await.unwrap_or_else(|e| {
+            warn!("{} failed to fetch chain head: {}", name, e);
+            None
+        });
+        info!(
+            "{} resubscribed after {} attempts (last block: {:?}, head: {:?})",
+            name, attempts, self.last_block, head
+        );
+
+        self.pending
+            .push_back(CollectorEvent::Restarted(CollectorRestarted {
+                collector: name,
+                last_block: self.last_block,
+                resumed_block: head,
+                attempts,
+            }));
+
+        if let (Some(last_block), Some(head)) = (self.last_block, head) {
+            if head > last_block {
+                match self.source.backfill(last_block + 1, head).await {
+                    Ok(events) => {
+                        info!(
+                            "{} backfilled {} events from blocks {}..={}",
+                            name,
+                            events.len(),
+                            last_block + 1,
+                            head
+                        );
+                        for event in events {
+                            let block = self.source.block_number(&event);
+                            self.track_block(block);
+                            self.pending.push_back(CollectorEvent::Event(event));
+                        }
+                        self.skip_through = Some(head);
+                    }
+                    Err(e) => warn!("{} failed to backfill missed events: {}", name, e),
+                }
+            }
+        }
+
+        true
+    }
+}
+
+/// Returns a live stream that polls the chain head of `source` every `interval`,
+/// and fetches the events of new blocks with [Resubscribe::backfill]. This lets
+/// collectors run against HTTP endpoints that do not support subscriptions.
+///
+/// The stream starts at the current head and ends on the first RPC error, so
+/// that [resubscribing_stream] retries with backoff and backfills the blocks
+/// missed in the meantime.
+pub(crate) async fn polling_stream<'a, T, S>(
+    source: &'a S,
+    interval: Duration,
+) -> Result<CollectorStream<'a, T>>
+where
+    T: Send + 'a,
+    S: Resubscribe<T> + ?Sized,
+{
+    let name = source.name();
+    let head = source
+        .head()
+        .await?
+        .ok_or_else(|| eyre!("{} cannot poll without a chain head", name))?;
+
+    let state = (head, VecDeque::new());
+    let stream = futures::stream::unfold(state, move |(mut cursor, mut pending)| async move {
+        loop {
+            if let Some(event) = pending.pop_front() {
+                return Some((event, (cursor, pending)));
+            }
+            tokio::time::sleep(interval).await;
+
+            let head = match source.head().await {
+                Ok(Some(head)) => head,
+                Ok(None) => return None,
+                Err(e) => {
+                    warn!("{} failed to poll chain head: {}", name, e);
+                    return None;
+                }
+            };
+            if head <= cursor {
+                continue;
+            }
+            match source.backfill(cursor + 1, head).await {
+                Ok(events) => {
+                    pending.extend(events);
+                    cursor = head;
+                }
+                Err(e) => {
+                    warn!(
+                        "{} failed to poll blocks {}..={}: {}",
+                        name,
+                        cursor + 1,
+                        head,
+                        e
+                    );
+                    return None;
+                }
+            }
+        }
+    });
+    Ok(Box::pin(stream))
+}
+
+/// Fetches the logs matching `filter` in `[from_block, to_block]`, splitting the
+/// range into chunks of [BACKFILL_CHUNK_SIZE] blocks.
+pub(crate) async fn get_logs_in_range<P: Provider>(
+    provider: &P,
+    filter: &Filter,
+    from_block: u64,
+    to_block: u64,
+) -> Result<Vec<Log>> {
+    let mut logs = vec![];
+    let mut start = from_block;
+    while start <= to_block {
+        let end = to_block.min(start.saturating_add(BACKFILL_CHUNK_SIZE - 1));
+        let chunk_filter = filter.clone().from_block(start).to_block(end);
+        logs.extend(provider.get_logs(&chunk_filter).await?);
+        start = end + 1;
+    }
+    Ok(logs)
+}
@@ -0,0 +1,200 @@
+use crate::types::{Collector, CollectorStream, EngineEvent};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use cron::Schedule;
+use eyre::Result;
+use futures::stream;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::time::{self, Instant, MissedTickBehavior};
+
+/// A tick emitted by an [IntervalCollector] or a [CronCollector].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tick {
+    /// The name of the timer, telling apart the timers of a strategy
+    pub name: &'static str,
+    /// The number of ticks emitted before this one
+    pub count: u64,
+    /// The time the tick was scheduled for, for ticks of a [CronCollector]
+    pub scheduled: Option<DateTime<Utc>>,
+}
+
+impl EngineEvent for Tick {}
+
+/// IntervalCollector emits a [Tick] at a fixed interval, for work that is not
+/// triggered by chain events, such as revaluing pools or taking metrics snapshots.
+///
+/// The first tick is emitted one period after the stream is created. Ticks
+/// missed while the strategy was busy are skipped rather than emitted in a burst.
+///
+/// # Example
+/// ```rust,no_run
+/// use std::time::Duration;
+/// use engine::collectors::timer_collector::IntervalCollector;
+/// use engine::types::Collector;
+/// use futures::StreamExt;
+///
+/// async fn example() {
+///     let collector = IntervalCollector::new(Duration::from_secs(60)).with_name("rebalance");
+///     let mut stream = collector.get_event_stream().await.unwrap();
+///     while let Some(tick) = stream.next().await {
+///         println!("{} #{}", tick.name, tick.count);
+///     }
+/// }
+/// ```
+pub struct IntervalCollector {
+    /// The time between two ticks
+    period: Duration,
+    /// The name of the emitted ticks
+    name: &'static str,
+}
+
+impl IntervalCollector {
+    pub fn new(period: Duration) -> Self {
+        Self {
+            period,
+            name: "interval",
+        }
+    }
+
+    /// Sets the name of the emitted ticks.
+    pub fn with_name(mut self, name: &'static str) -> Self {
+        self.name = name;
+        self
+    }
+}
+
+#[async_trait]
+impl Collector<Tick> for IntervalCollector {
+    async fn get_event_stream(&self) -> Result<CollectorStream<'_, Tick>> {
+        let mut interval = time::interval_at(Instant::now() + self.period, self.period);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        let name = self.name;
+        let stream = stream::unfold((interval, 0), move |(mut interval, count)| async move {
+            interval.tick().await;
+            let tick = Tick {
+                name,
+                count,
+                scheduled: None,
+            };
+            Some((tick, (interval, count + 1)))
+        });
+        Ok(Box::pin(stream))
+    }
+}
+
+/// CronCollector emits a [Tick] at the times of a cron schedule, in UTC, for
+/// work that should run at set times of the day rather than at an interval.
+///
+/// Schedules use the [cron] syntax, with a leading seconds field: `0 */5 * * * *`
+/// ticks every five minutes. Ticks whose time passed while the strategy was
+/// busy are skipped rather than emitted in a burst.
+///
+/// # Example
+/// ```rust,no_run
+/// use engine::collectors::timer_collector::CronCollector;
+/// use engine::types::Collector;
+/// use futures::StreamExt;
+///
+/// async fn example() {
+///     let collector = CronCollector::new("0 0 * * * *").unwrap().with_name("snapshot");
+///     let mut stream = collector.get_event_stream().await.unwrap();
+///     while let Some(tick) = stream.next().await {
+///         println!("{} at {:?}", tick.name, tick.scheduled);
+///     }
+/// }
+/// ```
+pub struct CronCollector {
+    /// The times at which ticks are emitted
+    schedule: Schedule,
+    /// The name of the emitted ticks
+    name: &'static str,
+}
+
+impl CronCollector {
+    /// Creates a collector from a cron expression. Fails if the expression is invalid.
+    pub fn new(expression: &str) -> Result<Self> {
+        let schedule = Schedule::from_str(expression)?;
+        Ok(Self {
+            schedule,
+            name: "cron",
+        })
+    }
+
+    /// Sets the name of the emitted ticks.
+    pub fn with_name(mut self, name: &'static str) -> Self {
+        self.name = name;
+        self
+    }
+}
+
+#[async_trait]
+impl Collector<Tick> for CronCollector {
+    async fn get_event_stream(&self) -> Result<CollectorStream<'_, Tick>> {
+        // the wall clock is only read once: the time of each tick is measured on
+        // the tokio timer from there, which keeps ticks in step with paused time
+        let origin = (Utc::now(), Instant::now());
+
+        let name = self.name;
+        let schedule = &self.schedule;
+        let stream = stream::unfold(0, move |count| async move {
+            let (origin_time, origin_instant) = origin;
+            let elapsed = chrono::Duration::from_std(origin_instant.elapsed()).ok()?;
+            let scheduled = schedule.after(&(origin_time + elapsed)).next()?;
+            let delay = (scheduled - origin_time).to_std().ok()?;
+            time::sleep_until(origin_instant + delay).await;
+
+            let tick = Tick {
+                name,
+                count,
+                scheduled: Some(scheduled),
+            };
+            Some((tick, count + 1))
+        });
+        Ok(Box::pin(stream))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_interval_ticks_after_each_period() {
+        let collector = IntervalCollector::new(Duration::from_secs(60)).with_name("rebalance");
+        let mut stream = collector.get_event_stream().await.unwrap();
+        let start = Instant::now();
+
+        let tick = stream.next().await.unwrap();
+        assert_eq!(tick.name, "rebalance");
+        assert_eq!(tick.count, 0);
+        assert_eq!(start.elapsed(), Duration::from_secs(60));
+
+        // ticks missed while the strategy was busy are skipped
+        time::advance(Duration::from_secs(150)).await;
+        let tick = stream.next().await.unwrap();
+        assert_eq!(tick.count, 1);
+        let tick = stream.next().await.unwrap();
+        assert_eq!(tick.count, 2);
+        assert_eq!(start.elapsed(), Duration::from_secs(240));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cron_ticks_at_scheduled_times() {
+        let collector = CronCollector::new("*/10 * * * * *").unwrap();
+        let mut stream = collector.get_event_stream().await.unwrap();
+
+        let first = stream.next().await.unwrap().scheduled.unwrap();
+        let second = stream.next().await.unwrap();
+        assert_eq!(second.count, 1);
+        assert_eq!(
+            second.scheduled.unwrap() - first,
+            chrono::Duration::seconds(10)
+        );
+        assert_eq!(first.timestamp() % 10, 0);
+
+        assert!(CronCollector::new("not a schedule").is_err());
+    }
+}
@@ -0,0 +1,588 @@
+use alloy::primitives::Address;
+use alloy_chains::NamedChain;
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+use crate::metrics;
+use crate::supervisor::TaskKind;
+
+/// What happens when an item is sent to a subscriber whose queue is full.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DeliveryPolicy {
+    /// Wait until the subscriber makes room, applying backpressure to the producers
+    Block,
+    /// Drop the oldest queued item to make room for the new one. Control items,
+    /// such as reorg notifications, are never dropped.
+    #[default]
+    DropOldest,
+    /// Replace the queued item with the same coalesce key (for example, the
+    /// previous state of the same pool) with the new one. Items are never
+    /// coalesced across an item without a key. Items without a key, or without
+    /// a queued counterpart, fall back to dropping the oldest item.
+    Coalesce,
+}
+
+/// Configuration of the queue between the engine and a strategy or executor.
+#[derive(Debug, Clone)]
+pub struct SubscriptionConfig {
+    /// The number of items that can be queued for the subscriber
+    pub capacity: usize,
+    /// What happens when the queue is full
+    pub policy: DeliveryPolicy,
+    /// The chains whose items are delivered to the subscriber, or None for
+    /// every chain. Items without a chain are always delivered.
+    pub chains: Option<Vec<NamedChain>>,
+}
+
+impl Default for SubscriptionConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 512,
+            policy: DeliveryPolicy::default(),
+            chains: None,
+        }
+    }
+}
+
+impl SubscriptionConfig {
+    pub fn new(policy: DeliveryPolicy) -> Self {
+        Self {
+            policy,
+            ..Default::default()
+        }
+    }
+
+    /// Sets the number of items that can be queued for the subscriber.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Only delivers the items of `chains` to the subscriber.
+    pub fn with_chains(mut self, chains: impl IntoIterator<Item = NamedChain>) -> Self {
+        self.chains = Some(chains.into_iter().collect());
+        self
+    }
+}
+
+/// Delivery counters of a single subscriber, updated as items flow through its queue.
+#[derive(Debug)]
+pub struct LagCounters {
+    name: &'static str,
+    kind: TaskKind,
+    delivered: AtomicU64,
+    dropped: AtomicU64,
+    coalesced: AtomicU64,
+    blocked: AtomicU64,
+    depth: AtomicUsize,
+}
+
+impl LagCounters {
+    pub fn new(name: &'static str, kind: TaskKind) -> Self {
+        Self {
+            name,
+            kind,
+            delivered: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+            coalesced: AtomicU64::new(0),
+            blocked: AtomicU64::new(0),
+            depth: AtomicUsize::new(0),
+        }
+    }
+
+    /// The name of the subscriber
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// The kind of the subscriber
+    pub fn kind(&self) -> TaskKind {
+        self.kind
+    }
+
+    /// The number of items received by the subscriber
+    pub fn delivered(&self) -> u64 {
+        self.delivered.load(Ordering::Relaxed)
+    }
+
+    /// The number of items dropped because the subscriber's queue was full
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// The number of items replaced by a newer item with the same coalesce key
+    pub fn coalesced(&self) -> u64 {
+        self.coalesced.load(Ordering::Relaxed)
+    }
+
+    /// The number of sends that had to wait for the subscriber to make room
+    pub fn blocked(&self) -> u64 {
+        self.blocked.load(Ordering::Relaxed)
+    }
+
+    /// The number of items currently queued for the subscriber
+    pub fn depth(&self) -> usize {
+        self.depth.load(Ordering::Relaxed)
+    }
+
+    fn set_depth(&self, depth: usize) {
+        self.depth.store(depth, Ordering::Relaxed);
+        metrics::queue_depth(self, depth);
+    }
+}
+
+/// Error returned by [Receiver::recv].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum RecvError {
+    /// The subscriber fell behind and this many items were dropped
+    Lagged(u64),
+    /// Every sender was dropped and the queue is empty
+    Closed,
+}
+
+impl fmt::Display for RecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecvError::Lagged(missed) => write!(f, "lagged by {} items", missed),
+            RecvError::Closed => write!(f, "channel closed"),
+        }
+    }
+}
+
+/// An item waiting in the queue of a subscriber.
+struct Queued<T> {
+    /// The coalesce key of the item
+    key: Option<Address>,
+    /// Whether the item is a control item, which is never dropped
+    control: bool,
+    item: T,
+}
+
+struct QueueState<T> {
+    items: VecDeque<Queued<T>>,
+    /// Items dropped since the receiver was last told it lagged
+    missed: u64,
+    senders_closed: bool,
+    receiver_closed: bool,
+}
+
+struct Queue<T> {
+    state: Mutex<QueueState<T>>,
+    capacity: usize,
+    policy: DeliveryPolicy,
+    chains: Option<Vec<NamedChain>>,
+    counters: Arc<LagCounters>,
+    readable: Notify,
+    writable: Notify,
+}
+
+impl<T> Queue<T> {
+    /// Returns true if the subscriber wants the items of `chain`.
+    fn accepts(&self, chain: Option<NamedChain>) -> bool {
+        match (&self.chains, chain) {
+            (Some(chains), Some(chain)) => chains.contains(&chain),
+            _ => true,
+        }
+    }
+
+    async fn push(&self, key: Option<Address>, control: bool, item: T) {
+        let mut item = Some(item);
+        let mut blocked = false;
+        loop {
+            let writable = self.writable.notified();
+            tokio::pin!(writable);
+            // register interest before checking the state so that no wakeup is missed
+            writable.as_mut().enable();
+
+            let full = {
+                let mut state = self.state.lock().unwrap();
+                if state.receiver_closed {
+                    return;
+                }
+
+                if self.policy == DeliveryPolicy::Coalesce && key.is_some() {
+                    // items without a key, such as reorg notifications, keep their
+                    // position: later items are never coalesced into earlier ones
+                    let slot = state
+                        .items
+                        .iter_mut()
+                        .rev()
+                        .take_while(|queued| queued.key.is_some())
+                        .find(|queued| queued.key == key);
+                    if let Some(slot) = slot {
+                        slot.item = item.take().unwrap();
+                        self.counters.coalesced.fetch_add(1, Ordering::Relaxed);
+                        metrics::queue_coalesced(&self.counters);
+                        return;
+                    }
+                }
+
+                let full = state.items.len() >= self.capacity;
+                let mut dropped = false;
+                if full && self.policy != DeliveryPolicy::Block {
+                    // control items are never dropped: the oldest data item makes
+                    // room, or the new item is dropped if it is a data item too.
+                    // A queue of control items alone grows past its capacity
+                    let oldest_data = state.items.iter().position(|queued| !queued.control);
+                    let evicted = oldest_data.and_then(|i| state.items.remove(i)).is_some();
+                    dropped = !evicted && !control;
+                    if evicted || dropped {
+                        state.missed += 1;
+                        self.counters.dropped.fetch_add(1, Ordering::Relaxed);
+                        metrics::queue_dropped(&self.counters);
+                    }
+                }
+                if !dropped && (!full || self.policy != DeliveryPolicy::Block) {
+                    state.items.push_back(Queued {
+                        key,
+                        control,
+                        item: item.take().unwrap(),
+                    });
+                    self.counters.set_depth(state.items.len());
+                }
+                full && self.policy == DeliveryPolicy::Block
+            };
+
+            if !full {
+                self.readable.notify_one();
+                return;
+            }
+            if !blocked {
+                blocked = true;
+                self.counters.blocked.fetch_add(1, Ordering::Relaxed);
+                metrics::queue_blocked(&self.counters);
+            }
+            writable.await;
+        }
+    }
+
+    fn close_senders(&self) {
+        self.state.lock().unwrap().senders_closed = true;
+        self.readable.notify_one();
+    }
+}
+
+struct Shared<T> {
+    queues: Vec<Arc<Queue<T>>>,
+    key: fn(&T) -> Option<Address>,
+    control: fn(&T) -> bool,
+    chain: fn(&T) -> Option<NamedChain>,
+    senders: AtomicUsize,
+}
+
+/// Sends items to every subscriber of a delivery channel, applying each
+/// subscriber's [DeliveryPolicy]. The channel is closed once every sender is dropped.
+pub(crate) struct Sender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T: Clone> Sender<T> {
+    /// Sends an item to every subscriber of its chain. Only waits if a
+    /// subscriber with the [Block](DeliveryPolicy::Block) policy has a full
+    /// queue, in which case the other subscribers still receive the item
+    /// without waiting for it.
+    pub(crate) async fn send(&self, item: T) {
+        let key = (self.shared.key)(&item);
+        let control = (self.shared.control)(&item);
+        let chain = (self.shared.chain)(&item);
+        let pushes = self
+            .shared
+            .queues
+            .iter()
+            .filter(|q| q.accepts(chain))
+            .map(|queue| queue.push(key, control, item.clone()));
+        futures::future::join_all(pushes).await;
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.shared.senders.fetch_add(1, Ordering::AcqRel);
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        if self.shared.senders.fetch_sub(1, Ordering::AcqRel) == 1 {
+            for queue in &self.shared.queues {
+                queue.close_senders();
+            }
+        }
+    }
+}
+
+/// Receives the items sent to a single subscriber.
+pub(crate) struct Receiver<T> {
+    queue: Arc<Queue<T>>,
+}
+
+impl<T> Receiver<T> {
+    /// Receives the next item. Returns [RecvError::Lagged] once after items were
+    /// dropped, then resumes with the oldest item still queued. Cancel safe.
+    pub(crate) async fn recv(&mut self) -> Result<T, RecvError> {
+        loop {
+            {
+                let mut state = self.queue.state.lock().unwrap();
+                if state.missed > 0 {
+                    return Err(RecvError::Lagged(std::mem::take(&mut state.missed)));
+                }
+                if let Some(Queued { item, .. }) = state.items.pop_front() {
+                    let counters = &self.queue.counters;
+                    counters.delivered.fetch_add(1, Ordering::Relaxed);
+                    counters.set_depth(state.items.len());
+                    drop(state);
+                    self.queue.writable.notify_waiters();
+                    return Ok(item);
+                }
+                if state.senders_closed {
+                    return Err(RecvError::Closed);
+                }
+            }
+            self.queue.readable.notified().await;
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let mut state = self.queue.state.lock().unwrap();
+        state.receiver_closed = true;
+        state.items.clear();
+        drop(state);
+        self.queue.writable.notify_waiters();
+    }
+}
+
+/// Creates a delivery channel with one queue per subscriber. `key` returns the
+/// key used to coalesce items for subscribers with the
+/// [Coalesce](DeliveryPolicy::Coalesce) policy, `control` whether an item must
+/// never be dropped, and `chain` the chain used to route items to the
+/// subscribers of that chain.
+pub(crate) fn channel<T>(
+    key: fn(&T) -> Option<Address>,
+    control: fn(&T) -> bool,
+    chain: fn(&T) -> Option<NamedChain>,
+    subscribers: impl IntoIterator<Item = (SubscriptionConfig, Arc<LagCounters>)>,
+) -> (Sender<T>, Vec<Receiver<T>>) {
+    let queues: Vec<_> = subscribers
+        .into_iter()
+        .map(|(config, counters)| {
+            Arc::new(Queue {
+                state: Mutex::new(QueueState {
+                    items: VecDeque::new(),
+                    missed: 0,
+                    senders_closed: false,
+                    receiver_closed: false,
+                }),
+                capacity: config.capacity.max(1),
+                policy: config.policy,
+                chains: config.chains,
+                counters,
+                readable: Notify::new(),
+                writable: Notify::new(),
+            })
+        })
+        .collect();
+    let receivers = queues
+        .iter()
+        .map(|queue| Receiver {
+            queue: queue.clone(),
+        })
+        .collect();
+    let sender = Sender {
+        shared: Arc::new(Shared {
+            queues,
+            key,
+            control,
+            chain,
+            senders: AtomicUsize::new(1),
+        }),
+    };
+    (sender, receivers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn pool(id: u8) -> Address {
+        Address::repeat_byte(id)
+    }
+
+    fn subscriber(
+        policy: DeliveryPolicy,
+        capacity: usize,
+    ) -> (SubscriptionConfig, Arc<LagCounters>) {
+        let config = SubscriptionConfig::new(policy).with_capacity(capacity);
+        let counters = Arc::new(LagCounters::new("test", TaskKind::Strategy));
+        (config, counters)
+    }
+
+    #[tokio::test]
+    async fn test_drop_oldest_reports_lag() {
+        let sub = subscriber(DeliveryPolicy::DropOldest, 2);
+        let counters = sub.1.clone();
+        let (sender, mut receivers) = channel(|_: &u64| None, |_| false, |_| None, [sub]);
+        let receiver = &mut receivers[0];
+
+        for i in 0..5 {
+            sender.send(i).await;
+        }
+        drop(sender);
+
+        assert_eq!(receiver.recv().await, Err(RecvError::Lagged(3)));
+        assert_eq!(receiver.recv().await, Ok(3));
+        assert_eq!(receiver.recv().await, Ok(4));
+        assert_eq!(receiver.recv().await, Err(RecvError::Closed));
+        assert_eq!(counters.dropped(), 3);
+        assert_eq!(counters.delivered(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_coalesce_keeps_latest_per_key() {
+        let sub = subscriber(DeliveryPolicy::Coalesce, 8);
+        let counters = sub.1.clone();
+        let (sender, mut receivers) = channel(
+            |(id, _): &(u8, u64)| Some(pool(*id)),
+            |_| false,
+            |_| None,
+            [sub],
+        );
+        let receiver = &mut receivers[0];
+
+        sender.send((1, 0)).await;
+        sender.send((2, 0)).await;
+        sender.send((1, 1)).await;
+        sender.send((1, 2)).await;
+
+        assert_eq!(receiver.recv().await, Ok((1, 2)));
+        assert_eq!(receiver.recv().await, Ok((2, 0)));
+        assert_eq!(counters.coalesced(), 2);
+        assert_eq!(counters.dropped(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_coalesce_stops_at_unkeyed_items() {
+        let sub = subscriber(DeliveryPolicy::Coalesce, 8);
+        let (sender, mut receivers) = channel(
+            |(id, _): &(u8, u64)| (*id != 0).then(|| pool(*id)),
+            |_| false,
+            |_| None,
+            [sub],
+        );
+        let receiver = &mut receivers[0];
+
+        sender.send((1, 0)).await;
+        sender.send((0, 0)).await;
+        sender.send((1, 1)).await;
+        sender.send((1, 2)).await;
+
+        assert_eq!(receiver.recv().await, Ok((1, 0)));
+        assert_eq!(receiver.recv().await, Ok((0, 0)));
+        assert_eq!(receiver.recv().await, Ok((1, 2)));
+    }
+
+    #[tokio::test]
+    async fn test_control_items_are_never_dropped() {
+        for policy in [DeliveryPolicy::DropOldest, DeliveryPolicy::Coalesce] {
+            let sub = subscriber(policy, 2);
+            // pool 0 stands for control items, such as reorgs
+            let (sender, mut receivers) = channel(
+                |(id, _): &(u8, u64)| (*id != 0).then(|| pool(*id)),
+                |(id, _)| *id == 0,
+                |_| None,
+                [sub],
+            );
+            let receiver = &mut receivers[0];
+
+            sender.send((0, 0)).await;
+            sender.send((1, 0)).await;
+            sender.send((2, 0)).await;
+            sender.send((0, 1)).await;
+            sender.send((0, 2)).await;
+            // the queue only holds control items, so data items are dropped
+            sender.send((3, 0)).await;
+            drop(sender);
+
+            assert_eq!(receiver.recv().await, Err(RecvError::Lagged(3)));
+            assert_eq!(receiver.recv().await, Ok((0, 0)));
+            assert_eq!(receiver.recv().await, Ok((0, 1)));
+            assert_eq!(receiver.recv().await, Ok((0, 2)));
+            assert_eq!(receiver.recv().await, Err(RecvError::Closed));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_block_waits_for_room() {
+        let sub = subscriber(DeliveryPolicy::Block, 1);
+        let counters = sub.1.clone();
+        let (sender, mut receivers) = channel(|_: &u64| None, |_| false, |_| None, [sub]);
+        let mut receiver = receivers.pop().unwrap();
+
+        sender.send(0).await;
+        let send = tokio::spawn(async move { sender.send(1).await });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!send.is_finished());
+        assert_eq!(counters.blocked(), 1);
+
+        assert_eq!(receiver.recv().await, Ok(0));
+        send.await.unwrap();
+        assert_eq!(receiver.recv().await, Ok(1));
+        assert_eq!(receiver.recv().await, Err(RecvError::Closed));
+    }
+
+    #[tokio::test]
+    async fn test_blocked_subscriber_does_not_hold_back_others() {
+        let blocking = subscriber(DeliveryPolicy::Block, 1);
+        let other = subscriber(DeliveryPolicy::DropOldest, 8);
+        let (sender, mut receivers) =
+            channel(|_: &u64| None, |_| false, |_| None, [blocking, other]);
+        let mut other = receivers.pop().unwrap();
+        let mut blocking = receivers.pop().unwrap();
+
+        sender.send(0).await;
+        let send = tokio::spawn(async move { sender.send(1).await });
+        assert_eq!(other.recv().await, Ok(0));
+        let received = tokio::time::timeout(Duration::from_millis(50), other.recv()).await;
+        assert_eq!(received, Ok(Ok(1)));
+        assert!(!send.is_finished());
+
+        assert_eq!(blocking.recv().await, Ok(0));
+        send.await.unwrap();
+        assert_eq!(blocking.recv().await, Ok(1));
+    }
+
+    #[tokio::test]
+    async fn test_items_are_routed_by_chain() {
+        let (config, counters) = subscriber(DeliveryPolicy::DropOldest, 8);
+        let base = (config.clone().with_chains([NamedChain::Base]), counters);
+        let all = subscriber(DeliveryPolicy::DropOldest, 8);
+        let (sender, mut receivers) = channel(
+            |_: &(Option<NamedChain>, u64)| None,
+            |_| false,
+            |(chain, _)| *chain,
+            [base, all],
+        );
+
+        sender.send((Some(NamedChain::Mainnet), 0)).await;
+        sender.send((Some(NamedChain::Base), 1)).await;
+        sender.send((None, 2)).await;
+        drop(sender);
+
+        let base = &mut receivers[0];
+        assert_eq!(base.recv().await, Ok((Some(NamedChain::Base), 1)));
+        assert_eq!(base.recv().await, Ok((None, 2)));
+        assert_eq!(base.recv().await, Err(RecvError::Closed));
+        let all = &mut receivers[1];
+        assert_eq!(all.recv().await, Ok((Some(NamedChain::Mainnet), 0)));
+        assert_eq!(all.recv().await, Ok((Some(NamedChain::Base), 1)));
+        assert_eq!(all.recv().await, Ok((None, 2)));
+    }
+}
@@ -1,13 +1,59 @@
-use tokio::sync::broadcast::{self, Sender};
+use futures::FutureExt;
+use std::collections::HashMap;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, watch};
 use tokio::task::JoinSet;
 use tokio_stream::StreamExt;
-use tracing::{error, info};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
 
-use crate::types::{Collector, Executor, Strategy};
+use crate::checkpoint::{CheckpointStore, Checkpointer};
+use crate::delivery::{
+    self, DeliveryPolicy, LagCounters, Receiver, RecvError, Sender, SubscriptionConfig,
+};
+use crate::metrics;
+use crate::outcome::{ActionId, ActionOutcome, OutcomeReporter};
+use crate::supervisor::{
+    panic_message, ExitReport, RestartPolicy, TaskKind, TaskOutcome, TaskReport,
+};
+use crate::sync::{SyncHandle, SyncPolicy, SyncScheduler};
+use crate::types::{Collector, EngineEvent, Executor, Strategy};
 
 /// The Engine is the core orchestrator of the Artemis MEV framework.
 /// It manages the flow of data between Collectors, Strategies, and Executors.
 ///
+/// Every component runs in its own supervised task: failed components are
+/// restarted according to the [RestartPolicy], and cancelling the shutdown
+/// token stops collectors and strategies, then lets executors drain the
+/// actions that are still in flight.
+///
+/// Each strategy and executor receives its inputs through its own queue, whose
+/// [DeliveryPolicy] decides what happens when it falls behind. Strategies are
+/// notified when they lag and, depending on the [SyncPolicy], resynced.
+///
+/// Strategies are synced according to the [SyncPolicy], or on demand through a
+/// [SyncHandle]. Events received while a strategy syncs are queued and
+/// processed once the sync is done.
+///
+/// A single engine can host components for several chains: collectors wrapped
+/// in a [ChainCollector](crate::chain::ChainCollector) tag their events with
+/// their chain, and strategies only receive the events of the chains they
+/// subscribed to through [SubscriptionConfig::with_chains].
+///
+/// With a [CheckpointStore], the last block fully processed by each strategy is
+/// persisted, so that a restarted bot can backfill what it missed.
+///
+/// Every action is tagged with an [ActionId], and whatever the executors report
+/// about it is routed back to the strategy that emitted it through
+/// [Strategy::on_action_outcome].
+///
+/// Panicking components are only caught (and restarted) when the binary is
+/// built with `panic = "unwind"`; with `panic = "abort"` a panic still takes
+/// the whole process down.
+///
 /// Type Parameters:
 /// - E: The type of events that flow through the system
 /// - A: The type of actions that can be executed
@@ -16,16 +62,70 @@ pub struct Engine<E, A> {
     collectors: Vec<Box<dyn Collector<E>>>,
 
     /// The set of strategies that the engine will use to process events.
-    strategies: Vec<Box<dyn Strategy<E, A>>>,
+    strategies: Vec<Subscriber<Box<dyn Strategy<E, A>>>>,
 
     /// The set of executors that the engine will use to execute actions.
-    executors: Vec<Box<dyn Executor<A>>>,
+    executors: Vec<Subscriber<Box<dyn Executor<A>>>>,
 
-    /// The capacity of the event channel.
+    /// The default queue capacity of each strategy.
     event_channel_capacity: usize,
 
-    /// The capacity of the action channel.
+    /// The default queue capacity of each executor.
     action_channel_capacity: usize,
+
+    /// The policy applied when a component fails.
+    restart_policy: RestartPolicy,
+
+    /// The token used to signal the engine to shut down.
+    shutdown: CancellationToken,
+
+    /// How long executors are given to drain in-flight actions on shutdown.
+    drain_timeout: Duration,
+
+    /// The policy deciding when strategies sync their state.
+    sync_policy: SyncPolicy,
+
+    /// The channel used to request on-demand syncs.
+    sync_requests: Arc<watch::Sender<u64>>,
+
+    /// The store the last block processed by each strategy is saved to, if any.
+    checkpoints: Option<Arc<CheckpointStore>>,
+}
+
+/// A strategy or executor along with the configuration of its queue.
+struct Subscriber<T> {
+    component: T,
+    /// The queue configuration, or None to use the engine's defaults
+    config: Option<SubscriptionConfig>,
+    counters: Arc<LagCounters>,
+}
+
+/// An action along with its id and the channel through which its outcomes are
+/// routed back to the strategy that emitted it.
+#[derive(Debug, Clone)]
+struct Dispatched<A> {
+    id: ActionId,
+    action: A,
+    outcomes: mpsc::UnboundedSender<ActionOutcome<A>>,
+}
+
+/// Assigns ids to the actions emitted by strategies and sends them to the executors.
+#[derive(Clone)]
+struct ActionDispatcher<A> {
+    sender: Sender<Dispatched<A>>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl<A: Send + Clone + 'static> ActionDispatcher<A> {
+    async fn dispatch(&self, action: A, outcomes: &mpsc::UnboundedSender<ActionOutcome<A>>) {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let dispatched = Dispatched {
+            id,
+            action,
+            outcomes: outcomes.clone(),
+        };
+        self.sender.send(dispatched).await
+    }
 }
 
 impl<E, A> Engine<E, A> {
@@ -37,22 +137,77 @@ impl<E, A> Engine<E, A> {
             executors: vec![],
             event_channel_capacity: 512,
             action_channel_capacity: 512,
+            restart_policy: RestartPolicy::default(),
+            shutdown: CancellationToken::new(),
+            drain_timeout: Duration::from_secs(30),
+            sync_policy: SyncPolicy::default(),
+            sync_requests: Arc::new(watch::channel(0).0),
+            checkpoints: None,
         }
     }
 
-    /// Sets the capacity of the event channel.
-    /// This determines how many events can be buffered before backpressure is applied.
+    /// Sets the default capacity of the event queue of each strategy.
+    /// This determines how many events can be buffered before the strategy lags.
     pub fn with_event_channel_capacity(mut self, capacity: usize) -> Self {
         self.event_channel_capacity = capacity;
         self
     }
 
-    /// Sets the capacity of the action channel.
-    /// This determines how many actions can be buffered before backpressure is applied.
+    /// Sets the default capacity of the action queue of each executor.
+    /// This determines how many actions can be buffered before the executor lags.
     pub fn with_action_channel_capacity(mut self, capacity: usize) -> Self {
         self.action_channel_capacity = capacity;
         self
     }
+
+    /// Sets the policy applied when a collector, strategy or executor fails.
+    pub fn with_restart_policy(mut self, policy: RestartPolicy) -> Self {
+        self.restart_policy = policy;
+        self
+    }
+
+    /// Sets the token used to signal the engine to shut down.
+    pub fn with_shutdown_token(mut self, token: CancellationToken) -> Self {
+        self.shutdown = token;
+        self
+    }
+
+    /// Sets how long executors are given to drain in-flight actions on shutdown.
+    pub fn with_drain_timeout(mut self, timeout: Duration) -> Self {
+        self.drain_timeout = timeout;
+        self
+    }
+
+    /// Returns a handle to the shutdown token. Cancelling it stops the engine.
+    pub fn shutdown_token(&self) -> CancellationToken {
+        self.shutdown.clone()
+    }
+
+    /// Sets the policy deciding when strategies sync their state.
+    pub fn with_sync_policy(mut self, policy: SyncPolicy) -> Self {
+        self.sync_policy = policy;
+        self
+    }
+
+    /// Saves the last block fully processed by each strategy to `store`, so
+    /// that collectors can be started from there after a restart.
+    pub fn with_checkpoints(mut self, store: Arc<CheckpointStore>) -> Self {
+        self.checkpoints = Some(store);
+        self
+    }
+
+    /// Returns a handle used to request an on-demand sync of every strategy.
+    pub fn sync_handle(&self) -> SyncHandle {
+        SyncHandle::new(self.sync_requests.clone())
+    }
+
+    /// Returns the delivery counters of every strategy and executor, which can
+    /// be used to monitor how far behind each of them is.
+    pub fn lag_counters(&self) -> Vec<Arc<LagCounters>> {
+        let strategies = self.strategies.iter().map(|s| s.counters.clone());
+        let executors = self.executors.iter().map(|e| e.counters.clone());
+        strategies.chain(executors).collect()
+    }
 }
 
 impl<E, A> Default for Engine<E, A> {
@@ -63,8 +218,8 @@ impl<E, A> Default for Engine<E, A> {
 
 impl<E, A> Engine<E, A>
 where
-    E: Send + Clone + 'static,
-    A: Send + Clone + 'static + std::fmt::Debug,
+    E: EngineEvent + Send + Clone + 'static,
+    A: Send + Sync + Clone + 'static + std::fmt::Debug,
 {
     /// Adds a collector to the engine.
     /// Collectors are responsible for gathering events from external sources.
@@ -75,92 +230,527 @@ where
     /// Adds a strategy to the engine.
     /// Strategies process events and generate actions based on MEV opportunities.
     pub fn add_strategy(&mut self, strategy: Box<dyn Strategy<E, A>>) {
-        self.strategies.push(strategy);
+        self.push_strategy(strategy, None);
+    }
+
+    /// Adds a strategy to the engine, with a custom configuration of its event queue.
+    pub fn add_strategy_with(
+        &mut self,
+        strategy: Box<dyn Strategy<E, A>>,
+        config: SubscriptionConfig,
+    ) {
+        self.push_strategy(strategy, Some(config));
     }
 
     /// Adds an executor to the engine.
     /// Executors are responsible for carrying out actions generated by strategies.
     pub fn add_executor(&mut self, executor: Box<dyn Executor<A>>) {
-        self.executors.push(executor);
+        self.push_executor(executor, None);
+    }
+
+    /// Adds an executor to the engine, with a custom configuration of its action queue.
+    /// Actions have no coalesce key nor chain, so [DeliveryPolicy::Coalesce]
+    /// behaves like [DeliveryPolicy::DropOldest] for executors, and every executor
+    /// receives every action: executors of a single chain should be wrapped in
+    /// an [ExecutorMap](crate::types::ExecutorMap) discarding the actions of other chains.
+    pub fn add_executor_with(
+        &mut self,
+        executor: Box<dyn Executor<A>>,
+        config: SubscriptionConfig,
+    ) {
+        self.push_executor(executor, Some(config));
+    }
+
+    fn push_strategy(
+        &mut self,
+        strategy: Box<dyn Strategy<E, A>>,
+        config: Option<SubscriptionConfig>,
+    ) {
+        let counters = Arc::new(LagCounters::new(strategy.name(), TaskKind::Strategy));
+        self.strategies.push(Subscriber {
+            component: strategy,
+            config,
+            counters,
+        });
+    }
+
+    fn push_executor(
+        &mut self,
+        executor: Box<dyn Executor<A>>,
+        config: Option<SubscriptionConfig>,
+    ) {
+        let counters = Arc::new(LagCounters::new(executor.name(), TaskKind::Executor));
+        self.executors.push(Subscriber {
+            component: executor,
+            config,
+            counters,
+        });
     }
 
     /// The core run loop of the engine. This function will:
-    /// 1. Spawn a thread for each collector, strategy, and executor
+    /// 1. Spawn a supervised task for each collector, strategy, and executor
     /// 2. Set up communication channels between components
-    /// 3. Orchestrate the flow of events and actions
-    /// 4. Handle errors and logging
+    /// 3. Restart failed components according to the restart policy
+    /// 4. On shutdown, stop collectors and strategies and drain in-flight actions
     ///
-    /// Returns a JoinSet of spawned tasks that can be used to await completion
-    /// or handle task failures.
-    pub async fn run(self) -> Result<JoinSet<()>, Box<dyn std::error::Error>> {
-        let (event_sender, _): (Sender<E>, _) = broadcast::channel(self.event_channel_capacity);
-        let (action_sender, _): (Sender<A>, _) = broadcast::channel(self.action_channel_capacity);
+    /// Returns an [ExitReport] once every task has stopped.
+    pub async fn run(self) -> ExitReport {
+        let event_capacity = self.event_channel_capacity;
+        let (event_sender, event_receivers) = delivery::channel(
+            |event: &E| event.coalesce_key(),
+            |event: &E| event.is_control(),
+            |event: &E| event.chain(),
+            self.strategies.iter().map(|strategy| {
+                let config = strategy.config.clone().unwrap_or_else(|| {
+                    SubscriptionConfig::new(DeliveryPolicy::default()).with_capacity(event_capacity)
+                });
+                (config, strategy.counters.clone())
+            }),
+        );
+        let action_capacity = self.action_channel_capacity;
+        let (action_sender, action_receivers) = delivery::channel(
+            |_: &Dispatched<A>| None,
+            |_: &Dispatched<A>| false,
+            |_: &Dispatched<A>| None,
+            self.executors.iter().map(|executor| {
+                let config = executor.config.clone().unwrap_or_else(|| {
+                    SubscriptionConfig::new(DeliveryPolicy::default())
+                        .with_capacity(action_capacity)
+                });
+                (config, executor.counters.clone())
+            }),
+        );
 
         let mut set = JoinSet::new();
+        let mut tasks = HashMap::new();
+
+        for (executor, receiver) in self.executors.into_iter().zip(action_receivers) {
+            let executor = executor.component;
+            let name = executor.name();
+            let handle = set.spawn(run_executor(
+                executor,
+                receiver,
+                self.restart_policy.clone(),
+                self.shutdown.clone(),
+            ));
+            tasks.insert(handle.id(), (name, TaskKind::Executor));
+        }
 
-        for executor in self.executors {
-            let mut receiver = action_sender.subscribe();
-            set.spawn(async move {
-                info!("starting executor... ");
-                loop {
-                    match receiver.recv().await {
-                        Ok(action) => match executor.execute(action).await {
-                            Ok(_) => {}
-                            Err(e) => error!("error executing action: {}", e),
+        let dispatcher = ActionDispatcher {
+            sender: action_sender,
+            next_id: Arc::new(AtomicU64::new(0)),
+        };
+        for (strategy, receiver) in self.strategies.into_iter().zip(event_receivers) {
+            let strategy = strategy.component;
+            let name = strategy.name();
+            let handle = set.spawn(run_strategy(
+                strategy,
+                receiver,
+                dispatcher.clone(),
+                self.restart_policy.clone(),
+                StrategySync {
+                    policy: self.sync_policy.clone(),
+                    requests: self.sync_requests.subscribe(),
+                    checkpoints: self.checkpoints.clone(),
+                },
+                self.shutdown.clone(),
+            ));
+            tasks.insert(handle.id(), (name, TaskKind::Strategy));
+        }
+
+        for collector in self.collectors {
+            let name = collector.name();
+            let handle = set.spawn(run_collector(
+                collector,
+                event_sender.clone(),
+                self.restart_policy.clone(),
+                self.shutdown.clone(),
+            ));
+            tasks.insert(handle.id(), (name, TaskKind::Collector));
+        }
+
+        // Only the tasks hold senders from now on, so that channels close (and
+        // executors stop) once every upstream task has stopped.
+        drop(event_sender);
+        drop(dispatcher);
+
+        let mut report = ExitReport::default();
+        let join_report =
+            |result: Result<(tokio::task::Id, TaskReport), tokio::task::JoinError>| match result {
+                Ok((_, report)) => report,
+                Err(e) => {
+                    let (name, kind) = tasks[&e.id()];
+                    TaskReport {
+                        name: name.to_string(),
+                        kind,
+                        restarts: 0,
+                        outcome: if e.is_cancelled() {
+                            TaskOutcome::Aborted
+                        } else {
+                            TaskOutcome::Failed(e.to_string())
                         },
-                        Err(e) => {
-                            error!("error receiving action: {}", e);
-                            break;
-                        }
                     }
                 }
-            });
+            };
+
+        loop {
+            tokio::select! {
+                _ = self.shutdown.cancelled() => break,
+                result = set.join_next_with_id() => match result {
+                    Some(result) => report.tasks.push(join_report(result)),
+                    None => {
+                        report.drained = true;
+                        return report;
+                    }
+                },
+            }
+        }
+
+        info!("Shutting down, draining in-flight actions...");
+        let drain = async {
+            let mut reports = vec![];
+            while let Some(result) = set.join_next_with_id().await {
+                reports.push(join_report(result));
+            }
+            reports
+        };
+        match tokio::time::timeout(self.drain_timeout, drain).await {
+            Ok(reports) => {
+                report.tasks.extend(reports);
+                report.drained = true;
+            }
+            Err(_) => {
+                warn!("Drain timeout reached, aborting remaining tasks");
+                set.abort_all();
+                while let Some(result) = set.join_next_with_id().await {
+                    report.tasks.push(join_report(result));
+                }
+            }
         }
 
-        for mut strategy in self.strategies {
-            let mut event_receiver = event_sender.subscribe();
-            let action_sender = action_sender.clone();
-            info!("initializing state...");
-            strategy.init_state().await.unwrap();
-
-            set.spawn(async move {
-                info!("starting strategy... ");
-                loop {
-                    match event_receiver.recv().await {
-                        Ok(event) => {
-                            for action in strategy.process_event(event).await {
-                                match action_sender.send(action) {
-                                    Ok(_) => {}
-                                    Err(e) => {
-                                        error!("error sending action: {}", e);
-                                        // break;
-                                    }
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            error!("error receiving event: {}", e);
-                            // break;
-                        }
+        report
+    }
+}
+
+/// Waits before restarting a failed component. Returns false if the component
+/// should not be restarted, either because the restart policy is exhausted or
+/// because the engine is shutting down.
+async fn wait_for_restart(
+    name: &str,
+    policy: &RestartPolicy,
+    restarts: usize,
+    shutdown: &CancellationToken,
+) -> bool {
+    if !policy.can_restart(restarts) {
+        error!("{} exhausted its restart policy", name);
+        return false;
+    }
+
+    let delay = policy.delay(restarts);
+    warn!(
+        "Restarting {} in {:?} (restart #{})",
+        name,
+        delay,
+        restarts + 1
+    );
+    tokio::select! {
+        _ = shutdown.cancelled() => false,
+        _ = tokio::time::sleep(delay) => true,
+    }
+}
+
+/// Supervises a collector: (re)opens its event stream and forwards every event
+/// to the strategies until the engine shuts down.
+async fn run_collector<E: Send + Clone + 'static>(
+    collector: Box<dyn Collector<E>>,
+    event_sender: Sender<E>,
+    policy: RestartPolicy,
+    shutdown: CancellationToken,
+) -> TaskReport {
+    let name = collector.name();
+    let mut restarts = 0;
+
+    let outcome = loop {
+        info!("starting collector {}...", name);
+        let failure = match collector.get_event_stream().await {
+            Ok(mut event_stream) => loop {
+                let next = tokio::select! {
+                    _ = shutdown.cancelled() => None,
+                    next = AssertUnwindSafe(event_stream.next()).catch_unwind() => Some(next),
+                };
+                match next {
+                    None => break None,
+                    Some(Ok(Some(event))) => {
+                        metrics::collector_event(name);
+                        event_sender.send(event).await
                     }
+                    Some(Ok(None)) => break Some("event stream ended".to_string()),
+                    Some(Err(payload)) => break Some(panic_message(payload)),
                 }
-            });
+            },
+            Err(e) => Some(format!("failed to get event stream: {}", e)),
+        };
+
+        let Some(failure) = failure else {
+            break TaskOutcome::Cancelled;
+        };
+        error!("collector {} failed: {}", name, failure);
+        if !wait_for_restart(name, &policy, restarts, &shutdown).await {
+            break if shutdown.is_cancelled() {
+                TaskOutcome::Cancelled
+            } else {
+                TaskOutcome::Failed(failure)
+            };
         }
+        restarts += 1;
+        metrics::task_restarted(name, TaskKind::Collector);
+    };
 
-        for collector in self.collectors {
-            let event_sender = event_sender.clone();
-            set.spawn(async move {
-                info!("starting collector... ");
-                let mut event_stream = collector.get_event_stream().await.unwrap();
-                while let Some(event) = event_stream.next().await {
-                    match event_sender.send(event) {
-                        Ok(_) => {}
-                        Err(e) => error!("error sending event: {}", e),
+    task_report(name, TaskKind::Collector, restarts, outcome)
+}
+
+/// How the state of a strategy is kept consistent: when it is synced, and
+/// where the blocks it processed are checkpointed.
+struct StrategySync {
+    policy: SyncPolicy,
+    requests: watch::Receiver<u64>,
+    checkpoints: Option<Arc<CheckpointStore>>,
+}
+
+/// Supervises a strategy: (re)initializes its state, then feeds it events and
+/// forwards the resulting actions to the executors until the engine shuts down.
+/// Outcomes of actions emitted before a restart are still delivered afterwards.
+async fn run_strategy<E: EngineEvent + Send + Clone + 'static, A: Send + Clone + 'static>(
+    mut strategy: Box<dyn Strategy<E, A>>,
+    mut event_receiver: Receiver<E>,
+    dispatcher: ActionDispatcher<A>,
+    policy: RestartPolicy,
+    sync: StrategySync,
+    shutdown: CancellationToken,
+) -> TaskReport {
+    let name = strategy.name();
+    let mut checkpointer = sync.checkpoints.map(|store| Checkpointer::new(store, name));
+    let mut restarts = 0;
+    let mut outcomes = mpsc::unbounded_channel();
+
+    let outcome = loop {
+        info!("initializing state of {}...", name);
+        let failure = match AssertUnwindSafe(strategy.init_state()).catch_unwind().await {
+            Ok(Ok(())) => {
+                info!("starting strategy {}...", name);
+                let mut scheduler = SyncScheduler::new(sync.policy.clone(), sync.requests.clone());
+                process_events(
+                    &mut strategy,
+                    &mut event_receiver,
+                    &dispatcher,
+                    &mut outcomes,
+                    &mut scheduler,
+                    checkpointer.as_mut(),
+                    &shutdown,
+                )
+                .await
+            }
+            Ok(Err(e)) => Err(format!("failed to initialize state: {}", e)),
+            Err(payload) => Err(panic_message(payload)),
+        };
+
+        let failure = match failure {
+            Ok(outcome) => break outcome,
+            Err(failure) => failure,
+        };
+        error!("strategy {} failed: {}", name, failure);
+        if !wait_for_restart(name, &policy, restarts, &shutdown).await {
+            break if shutdown.is_cancelled() {
+                TaskOutcome::Cancelled
+            } else {
+                TaskOutcome::Failed(failure)
+            };
+        }
+        restarts += 1;
+        metrics::task_restarted(name, TaskKind::Strategy);
+    };
+
+    if let Some(checkpointer) = &checkpointer {
+        checkpointer.flush().await;
+    }
+    task_report(name, TaskKind::Strategy, restarts, outcome)
+}
+
+/// Feeds events and action outcomes to an initialized strategy, syncing its
+/// state whenever the [SyncScheduler] says so. Returns an error if the strategy
+/// panicked.
+async fn process_events<E: EngineEvent + Send + Clone + 'static, A: Send + Clone + 'static>(
+    strategy: &mut Box<dyn Strategy<E, A>>,
+    event_receiver: &mut Receiver<E>,
+    dispatcher: &ActionDispatcher<A>,
+    (outcome_sender, outcome_receiver): &mut (
+        mpsc::UnboundedSender<ActionOutcome<A>>,
+        mpsc::UnboundedReceiver<ActionOutcome<A>>,
+    ),
+    sync: &mut SyncScheduler,
+    mut checkpointer: Option<&mut Checkpointer>,
+    shutdown: &CancellationToken,
+) -> Result<TaskOutcome, String> {
+    loop {
+        if sync.is_pending() {
+            sync_state(strategy, sync).await?;
+        }
+
+        let event = tokio::select! {
+            _ = shutdown.cancelled() => return Ok(TaskOutcome::Cancelled),
+            _ = sync.requested() => continue,
+            // the strategy holds a sender, so the outcome channel never closes
+            Some(outcome) = outcome_receiver.recv() => {
+                let on_outcome = AssertUnwindSafe(async { strategy.on_action_outcome(outcome) });
+                on_outcome.catch_unwind().await.map_err(panic_message)?;
+                continue;
+            }
+            event = event_receiver.recv() => event,
+        };
+
+        match event {
+            Ok(event) => {
+                sync.observe(&event);
+                if let Some(checkpointer) = checkpointer.as_mut() {
+                    checkpointer.observe(&event);
+                }
+                let started = Instant::now();
+                let actions = AssertUnwindSafe(strategy.process_event(event))
+                    .catch_unwind()
+                    .await
+                    .map_err(panic_message)?;
+                metrics::event_processed(strategy.name(), started.elapsed(), actions.len());
+                for action in actions {
+                    dispatcher.dispatch(action, outcome_sender).await;
+                }
+            }
+            Err(RecvError::Lagged(missed)) => {
+                warn!(
+                    "strategy {} lagged, {} events dropped",
+                    strategy.name(),
+                    missed
+                );
+                metrics::strategy_lagged(strategy.name(), missed);
+                strategy.on_lagged(missed);
+                sync.lagged();
+            }
+            Err(RecvError::Closed) if shutdown.is_cancelled() => return Ok(TaskOutcome::Cancelled),
+            Err(RecvError::Closed) => return Ok(TaskOutcome::Completed),
+        }
+    }
+}
+
+/// Syncs the state of a strategy. Events received in the meantime stay in the
+/// strategy's queue and are processed after the sync instead of being
+/// interleaved with it. Returns an error if the strategy panicked.
+async fn sync_state<E: EngineEvent + Send + Clone + 'static, A: Send + Clone + 'static>(
+    strategy: &mut Box<dyn Strategy<E, A>>,
+    sync: &mut SyncScheduler,
+) -> Result<(), String> {
+    let name = strategy.name();
+    info!("syncing state of {}...", name);
+    sync.syncing();
+
+    let started = Instant::now();
+    let result = AssertUnwindSafe(strategy.sync_state()).catch_unwind().await;
+    sync.synced();
+    metrics::strategy_synced(name, started.elapsed(), matches!(result, Ok(Ok(()))));
+    match result {
+        Ok(Ok(())) => debug!("synced state of {}", name),
+        Ok(Err(e)) => error!("failed to sync state of {}: {}", name, e),
+        Err(payload) => return Err(panic_message(payload)),
+    }
+    Ok(())
+}
+
+/// Supervises an executor: executes actions until every strategy has stopped
+/// and the action channel is drained, then waits for the executor to
+/// [drain](Executor::drain). Statuses reported by the executor are routed back
+/// to the strategy that emitted the action.
+async fn run_executor<A: Send + Sync + Clone + 'static>(
+    executor: Box<dyn Executor<A>>,
+    mut receiver: Receiver<Dispatched<A>>,
+    policy: RestartPolicy,
+    shutdown: CancellationToken,
+) -> TaskReport {
+    let name = executor.name();
+    let mut restarts = 0;
+    info!("starting executor {}...", name);
+
+    let outcome = loop {
+        match receiver.recv().await {
+            Ok(Dispatched {
+                id,
+                action,
+                outcomes,
+            }) => match AssertUnwindSafe(
+                executor
+                    .execute_with_reporter(action.clone(), reporter(id, action, name, outcomes)),
+            )
+            .catch_unwind()
+            .await
+            {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => error!("error executing action: {}", e),
+                Err(payload) => {
+                    let failure = panic_message(payload);
+                    error!("executor {} failed: {}", name, failure);
+                    if !policy.can_restart(restarts) {
+                        break TaskOutcome::Failed(failure);
                     }
+                    // the executor keeps draining during shutdown, so the
+                    // restart delay is not interrupted by the shutdown token
+                    tokio::time::sleep(policy.delay(restarts)).await;
+                    restarts += 1;
+                    metrics::task_restarted(name, TaskKind::Executor);
                 }
-            });
+            },
+            Err(RecvError::Lagged(missed)) => {
+                warn!("executor {} lagged, {} actions dropped", name, missed);
+            }
+            Err(RecvError::Closed) => {
+                break if shutdown.is_cancelled() {
+                    TaskOutcome::Cancelled
+                } else {
+                    TaskOutcome::Completed
+                };
+            }
+        }
+    };
+
+    let outcome = match executor.drain().await {
+        Err(e) if !matches!(outcome, TaskOutcome::Failed(_)) => {
+            error!("executor {} failed to drain: {}", name, e);
+            TaskOutcome::Failed(e.to_string())
         }
+        _ => outcome,
+    };
+    task_report(name, TaskKind::Executor, restarts, outcome)
+}
+
+/// Returns a reporter routing the statuses of an action back to its strategy.
+fn reporter<A: Send + Sync + Clone + 'static>(
+    id: ActionId,
+    action: A,
+    executor: &'static str,
+    outcomes: mpsc::UnboundedSender<ActionOutcome<A>>,
+) -> OutcomeReporter {
+    OutcomeReporter::new(move |status| {
+        // the strategy may have stopped, in which case nobody is interested
+        let _ = outcomes.send(ActionOutcome {
+            id,
+            action: action.clone(),
+            executor,
+            status,
+        });
+    })
+}
 
-        Ok(set)
+fn task_report(name: &str, kind: TaskKind, restarts: usize, outcome: TaskOutcome) -> TaskReport {
+    TaskReport {
+        name: name.to_string(),
+        kind,
+        restarts,
+        outcome,
     }
 }
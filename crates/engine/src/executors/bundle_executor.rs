@@ -0,0 +1,344 @@
+use std::{ops::RangeInclusive, sync::Arc, time::Duration};
+
+use crate::executors::relay::{BundleRequest, Relay, RelayClient};
+use crate::executors::OutcomeTasks;
+use crate::metrics;
+use crate::outcome::{ActionStatus, OutcomeReporter};
+use crate::types::Executor;
+use alloy::{
+    eips::eip2718::Encodable2718,
+    network::{Ethereum, EthereumWallet, NetworkWallet, TransactionBuilder},
+    primitives::{keccak256, Bytes, TxHash, U64},
+    providers::Provider,
+    rpc::types::TransactionRequest,
+    signers::local::PrivateKeySigner,
+};
+use async_trait::async_trait;
+use eyre::{eyre, Result};
+use futures::future::join_all;
+use tracing::{info, warn};
+
+/// A transaction included in a bundle.
+#[derive(Debug, Clone)]
+pub enum BundleTx {
+    /// A transaction that is already signed, for example a mempool transaction
+    /// being backrun
+    Signed(Bytes),
+    /// A transaction signed by the executor's wallet. Missing nonce, gas limit
+    /// and fees are filled in by the executor.
+    Unsigned(TransactionRequest),
+}
+
+/// Action to submit a bundle of transactions to MEV relays.
+#[derive(Debug, Clone)]
+pub struct SubmitBundle {
+    /// The transactions of the bundle, in order
+    pub txs: Vec<BundleTx>,
+    /// The first block the bundle targets, or None to target the next block
+    pub target_block: Option<u64>,
+    /// The number of consecutive blocks the bundle is submitted for
+    pub blocks: u64,
+    /// Hashes of the transactions that are allowed to revert
+    pub reverting_tx_hashes: Vec<TxHash>,
+}
+
+/// BundleExecutor submits bundles to a list of relays and block builders with
+/// `eth_sendBundle`, so that transactions never reach the public mempool.
+///
+/// Bundles are optionally simulated with `eth_callBundle` first, then submitted
+/// to every relay for each target block. The executor returns once the bundle
+/// is submitted, and a background task waits for the bundle to land, reporting
+/// it as dropped once the last target block was mined without it. These tasks
+/// are awaited when the executor [drains](Executor::drain).
+///
+/// # Example
+/// ```rust,no_run
+/// use std::sync::Arc;
+/// use alloy::{network::EthereumWallet, providers::Provider, signers::local::PrivateKeySigner};
+/// use engine::executors::bundle_executor::BundleExecutor;
+/// use engine::executors::relay::Relay;
+///
+/// fn example(provider: Arc<impl Provider>, wallet: EthereumWallet, auth: PrivateKeySigner) {
+///     let relays = vec![Relay::flashbots(), Relay::builder("titan", "https://rpc.titanbuilder.xyz")];
+///     let executor = BundleExecutor::new(provider, wallet, auth, relays).with_simulation(true);
+/// }
+/// ```
+pub struct BundleExecutor<P: Provider> {
+    client: Arc<P>,
+    /// The wallet signing the bundle transactions
+    wallet: EthereumWallet,
+    relays: Vec<Relay>,
+    relay_client: RelayClient,
+    /// Whether to simulate bundles before submitting them
+    simulate: bool,
+    /// How often to check whether the bundle landed
+    poll_interval: Duration,
+    /// The tasks waiting for submitted bundles to land
+    outcome_tasks: OutcomeTasks,
+}
+
+impl<P: Provider> BundleExecutor<P> {
+    /// Creates a new BundleExecutor. `auth_signer` signs the requests sent to
+    /// relays and is distinct from the wallet signing the transactions.
+    pub fn new(
+        client: Arc<P>,
+        wallet: EthereumWallet,
+        auth_signer: PrivateKeySigner,
+        relays: Vec<Relay>,
+    ) -> Self {
+        Self {
+            client,
+            wallet,
+            relays,
+            relay_client: RelayClient::new(auth_signer),
+            simulate: false,
+            poll_interval: Duration::from_secs(1),
+            outcome_tasks: OutcomeTasks::default(),
+        }
+    }
+
+    /// Sets whether bundles are simulated with `eth_callBundle` before being
+    /// submitted. Bundles that fail the simulation are dropped.
+    pub fn with_simulation(mut self, simulate: bool) -> Self {
+        self.simulate = simulate;
+        self
+    }
+
+    /// Sets how often the executor checks whether a submitted bundle landed.
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Signs the transactions of the bundle, filling in nonces, gas limits and
+    /// fees. Gas is estimated against the latest block, so transactions that
+    /// depend on earlier transactions of the bundle must set their gas limit.
+    async fn sign_bundle(&self, txs: &[BundleTx]) -> Result<Vec<Bytes>> {
+        let from = NetworkWallet::<Ethereum>::default_signer_address(&self.wallet);
+        let chain_id = self.client.get_chain_id().await?;
+        let mut nonce = self.client.get_transaction_count(from).pending().await?;
+        let fees = self.client.estimate_eip1559_fees(None).await?;
+
+        let mut signed = Vec::with_capacity(txs.len());
+        for tx in txs {
+            let raw = match tx {
+                BundleTx::Signed(raw) => raw.clone(),
+                BundleTx::Unsigned(tx) => {
+                    let mut tx = tx
+                        .clone()
+                        .with_from(from)
+                        .with_chain_id(chain_id)
+                        .with_nonce(nonce);
+                    nonce += 1;
+                    if tx.gas.is_none() {
+                        tx.gas = Some(self.client.estimate_gas(&tx).await?);
+                    }
+                    if tx.gas_price.is_none() && tx.max_fee_per_gas.is_none() {
+                        tx.max_fee_per_gas = Some(fees.max_fee_per_gas);
+                        tx.max_priority_fee_per_gas = Some(fees.max_priority_fee_per_gas);
+                    }
+                    tx.build(&self.wallet).await?.encoded_2718().into()
+                }
+            };
+            signed.push(raw);
+        }
+        Ok(signed)
+    }
+
+    /// Simulates the bundle on the first relay that supports simulation.
+    async fn simulate_bundle(&self, bundle: &BundleRequest) -> Result<()> {
+        let Some(relay) = self.relays.iter().find(|relay| relay.simulation) else {
+            return Err(eyre!("no relay supports simulation"));
+        };
+        let response = self.relay_client.call_bundle(relay, bundle).await?;
+        let failure = response
+            .failures()
+            .find(|(tx_hash, _)| !bundle.reverting_tx_hashes.contains(tx_hash));
+        match failure {
+            Some((tx_hash, reason)) => Err(eyre!("{} failed in simulation: {}", tx_hash, reason)),
+            None => {
+                info!(
+                    "Bundle simulation succeeded (gas used: {}, coinbase diff: {:?})",
+                    response.total_gas_used, response.coinbase_diff
+                );
+                Ok(())
+            }
+        }
+    }
+
+    /// Submits the bundle to every relay. Returns the number of relays that
+    /// accepted it.
+    async fn send_bundle(&self, bundle: &BundleRequest) -> usize {
+        let results = join_all(
+            self.relays
+                .iter()
+                .map(|relay| self.relay_client.send_bundle(relay, bundle)),
+        )
+        .await;
+
+        let mut accepted = 0;
+        for (relay, result) in self.relays.iter().zip(results) {
+            match result {
+                Ok(bundle_hash) => {
+                    info!(
+                        "Bundle submitted to {} for block {} (hash: {:?})",
+                        relay.name, bundle.block_number, bundle_hash
+                    );
+                    accepted += 1;
+                }
+                Err(e) => warn!("Failed to submit bundle to {}: {:?}", relay.name, e),
+            }
+        }
+        accepted
+    }
+}
+
+/// Implementation of the [Executor] trait for [BundleExecutor].
+/// This implementation:
+/// 1. Signs the bundle transactions
+/// 2. Optionally simulates the bundle
+/// 3. Submits the bundle to every relay, for every target block
+/// 4. Spawns a task reporting whether the bundle lands by the last target block
+#[async_trait]
+impl<P: Provider + 'static> Executor<SubmitBundle> for BundleExecutor<P> {
+    async fn execute(&self, action: SubmitBundle) -> Result<()> {
+        self.execute_with_reporter(action, OutcomeReporter::noop())
+            .await
+    }
+
+    async fn drain(&self) -> Result<()> {
+        self.outcome_tasks.join().await
+    }
+
+    async fn execute_with_reporter(
+        &self,
+        action: SubmitBundle,
+        reporter: OutcomeReporter,
+    ) -> Result<()> {
+        let dropped = |reason: String| {
+            warn!("Dropping bundle: {}", reason);
+            reporter.report(ActionStatus::Dropped { reason });
+            Ok(())
+        };
+
+        let current_block = match self.client.get_block_number().await {
+            Ok(block) => block,
+            Err(e) => {
+                let reason = format!("failed to get block number: {}", e);
+                reporter.report(ActionStatus::Failed {
+                    reason: reason.clone(),
+                });
+                return Err(eyre!(reason));
+            }
+        };
+        let target_block = action.target_block.unwrap_or(current_block + 1);
+        let last_block = target_block + action.blocks.max(1) - 1;
+        if last_block <= current_block {
+            return dropped(format!("target block {} already mined", last_block));
+        }
+
+        let txs = match self.sign_bundle(&action.txs).await {
+            Ok(txs) => txs,
+            Err(e) => return dropped(format!("failed to sign bundle: {}", e)),
+        };
+        let Some(tx_hash) = txs.last().map(keccak256) else {
+            return dropped("empty bundle".to_string());
+        };
+
+        let mut bundle = BundleRequest {
+            txs,
+            block_number: U64::from(target_block.max(current_block + 1)),
+            reverting_tx_hashes: action.reverting_tx_hashes,
+        };
+        if self.simulate {
+            if let Err(e) = self.simulate_bundle(&bundle).await {
+                return dropped(format!("bundle simulation failed: {}", e));
+            }
+        }
+
+        let mut accepted = 0;
+        for block in bundle.block_number.to::<u64>()..=last_block {
+            bundle.block_number = U64::from(block);
+            accepted += self.send_bundle(&bundle).await;
+        }
+        if accepted == 0 {
+            let reason = "no relay accepted the bundle".to_string();
+            reporter.report(ActionStatus::Failed {
+                reason: reason.clone(),
+            });
+            return Err(eyre!(reason));
+        }
+        metrics::tx_submitted(self.name());
+        reporter.report(ActionStatus::Submitted { tx_hash });
+
+        report_when_landed(
+            &self.outcome_tasks,
+            self.name(),
+            self.client.clone(),
+            tx_hash,
+            target_block..=last_block,
+            self.poll_interval,
+            reporter,
+        );
+        Ok(())
+    }
+}
+
+/// Reports the outcome of a submitted bundle once its last transaction is
+/// mined, or once the last target block was mined without it, without
+/// blocking the executor.
+fn report_when_landed<P: Provider + 'static>(
+    tasks: &OutcomeTasks,
+    executor: &'static str,
+    client: Arc<P>,
+    tx_hash: TxHash,
+    target_blocks: RangeInclusive<u64>,
+    poll_interval: Duration,
+    reporter: OutcomeReporter,
+) {
+    tasks.spawn(async move {
+        let mut ticker = tokio::time::interval(poll_interval);
+        loop {
+            ticker.tick().await;
+            // the block is read before the receipt, so that a bundle landing in
+            // the last target block is not reported as dropped
+            let block = match client.get_block_number().await {
+                Ok(block) => block,
+                Err(e) => {
+                    warn!("Failed to get block number: {}", e);
+                    continue;
+                }
+            };
+            let receipt = match client.get_transaction_receipt(tx_hash).await {
+                Ok(receipt) => receipt,
+                Err(e) => {
+                    warn!("Failed to get receipt of bundle {}: {}", tx_hash, e);
+                    continue;
+                }
+            };
+            if let Some(receipt) = receipt {
+                metrics::tx_mined(executor, &receipt);
+                info!("Bundle landed in block {:?}", receipt.block_number);
+                if receipt.status() {
+                    reporter.report(ActionStatus::Mined { receipt });
+                } else {
+                    reporter.report(ActionStatus::Reverted {
+                        receipt,
+                        reason: None,
+                    });
+                }
+                return;
+            }
+            if block >= *target_blocks.end() {
+                let reason = format!(
+                    "bundle not included in blocks {}..={}",
+                    target_blocks.start(),
+                    target_blocks.end()
+                );
+                warn!("Dropping bundle: {}", reason);
+                reporter.report(ActionStatus::Dropped { reason });
+                return;
+            }
+        }
+    });
+}
@@ -0,0 +1,347 @@
+use std::{
+    env,
+    path::Path,
+    str::FromStr,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::executors::{
+    encoded_tx_executor::SubmitEncodedTx,
+    gas_bidder::{GasBid, GasBidInfo, GasBidder},
+    mempool_executor::SubmitTxToMempool,
+};
+use crate::outcome::{ActionStatus, OutcomeReporter};
+use crate::types::Executor;
+use alloy::{
+    eips::BlockId,
+    network::TransactionBuilder,
+    primitives::{Address, I256, U256},
+    providers::Provider,
+    rpc::types::TransactionRequest,
+};
+use async_trait::async_trait;
+use eyre::{eyre, Result};
+use serde::Serialize;
+use tokio::{
+    fs::{File, OpenOptions},
+    io::AsyncWriteExt,
+};
+use tracing::{info, warn};
+use tx_executor::bindings::batchexecutor::BatchExecutor::BatchExecutorInstance;
+
+/// What would have happened if an action had been executed at `block_number`.
+#[derive(Debug, Clone, Serialize)]
+pub struct DryRunResult {
+    /// Unix timestamp of the simulation, in seconds
+    pub timestamp: u64,
+    pub chain_id: u64,
+    /// The kind of action that was simulated
+    pub action: String,
+    pub block_number: u64,
+    pub from: Option<Address>,
+    pub to: Option<Address>,
+    pub value: U256,
+    /// Whether the transaction would have been submitted, and succeeded
+    pub success: bool,
+    pub gas_used: Option<u64>,
+    /// The gas price that would have been paid, following the gas bid if any
+    pub gas_price: Option<u128>,
+    /// The total fee that would have been paid, including the L1 data fee
+    pub gas_cost: Option<U256>,
+    /// The profit expected by the strategy, in wei
+    pub expected_profit: Option<U256>,
+    /// The expected profit minus the gas cost
+    pub net_profit: Option<I256>,
+    pub revert_reason: Option<String>,
+    /// Why the transaction would not have been submitted although it succeeded,
+    /// for example because its profit does not cover the gas bid
+    pub drop_reason: Option<String>,
+}
+
+/// Journal recording the results of dry runs. Implemented by [FileJournal],
+/// and by applications to record results elsewhere, such as in a database.
+#[async_trait]
+pub trait Journal: Send + Sync {
+    async fn record(&self, result: &DryRunResult) -> Result<()>;
+}
+
+/// Journal appending results to a file, one JSON object per line.
+pub struct FileJournal {
+    file: tokio::sync::Mutex<File>,
+}
+
+impl FileJournal {
+    /// Opens the journal at `path`, creating it if needed. Existing results are kept.
+    pub async fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await?;
+        Ok(Self {
+            file: tokio::sync::Mutex::new(file),
+        })
+    }
+}
+
+#[async_trait]
+impl Journal for FileJournal {
+    async fn record(&self, result: &DryRunResult) -> Result<()> {
+        let mut line = serde_json::to_vec(result)?;
+        line.push(b'\n');
+        let mut file = self.file.lock().await;
+        file.write_all(&line).await?;
+        file.flush().await?;
+        Ok(())
+    }
+}
+
+/// DryRunExecutor simulates actions instead of executing them, to evaluate a
+/// strategy without sending transactions.
+///
+/// Each transaction is simulated with `eth_call` and `estimate_gas` against the
+/// latest block. The gas cost is computed as the [MempoolExecutor](super::mempool_executor::MempoolExecutor)
+/// would bid, and the result, along with the profit expected by the strategy,
+/// is recorded to a [Journal].
+///
+/// Successful simulations are reported as executed. Reverted ones, and the
+/// ones the live executors would not submit because their profit does not
+/// cover the gas bid, are reported as dropped, so that strategies do not wait
+/// for transactions that will never be mined.
+///
+/// # Example
+/// ```rust,no_run
+/// use std::sync::Arc;
+/// use alloy::providers::Provider;
+/// use engine::executors::dry_run_executor::{DryRunExecutor, FileJournal};
+///
+/// async fn example(provider: Arc<impl Provider>) {
+///     let journal = FileJournal::open("dry-run.jsonl").await.unwrap();
+///     let executor = DryRunExecutor::new(provider, Arc::new(journal));
+/// }
+/// ```
+pub struct DryRunExecutor<P: Provider> {
+    client: Arc<P>,
+    journal: Arc<dyn Journal>,
+    /// Computes the gas price the transactions would have paid
+    gas_bidder: GasBidder<P>,
+    /// The batch executor contract [SubmitEncodedTx] actions are sent to
+    batch_executor: Option<Address>,
+}
+
+impl<P: Provider> DryRunExecutor<P> {
+    /// Creates a new DryRunExecutor. The batch executor contract is read from
+    /// the `EXECUTOR_ADDRESS` environment variable, if set.
+    pub fn new(client: Arc<P>, journal: Arc<dyn Journal>) -> Self {
+        let batch_executor = env::var("EXECUTOR_ADDRESS")
+            .ok()
+            .and_then(|address| Address::from_str(&address).ok());
+        Self {
+            gas_bidder: GasBidder::new(client.clone()),
+            client,
+            journal,
+            batch_executor,
+        }
+    }
+
+    /// Sets the [GasBidder] used to price transactions, for example to account
+    /// for L1 data fees.
+    pub fn with_gas_bidder(mut self, gas_bidder: GasBidder<P>) -> Self {
+        self.gas_bidder = gas_bidder;
+        self
+    }
+
+    /// Sets the batch executor contract [SubmitEncodedTx] actions are sent to.
+    pub fn with_batch_executor(mut self, address: Address) -> Self {
+        self.batch_executor = Some(address);
+        self
+    }
+
+    /// Simulates a transaction at the latest block.
+    async fn simulate(
+        &self,
+        action: &str,
+        tx: TransactionRequest,
+        gas_bid_info: Option<&GasBidInfo>,
+        expected_profit: Option<U256>,
+    ) -> Result<DryRunResult> {
+        let chain_id = self.client.get_chain_id().await?;
+        let block_number = self.client.get_block_number().await?;
+        let block = BlockId::number(block_number);
+        let mut result = DryRunResult {
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+            chain_id,
+            action: action.to_string(),
+            block_number,
+            from: tx.from,
+            to: tx.to.and_then(|to| to.to().copied()),
+            value: tx.value.unwrap_or_default(),
+            success: false,
+            gas_used: None,
+            gas_price: None,
+            gas_cost: None,
+            expected_profit,
+            net_profit: None,
+            revert_reason: None,
+            drop_reason: None,
+        };
+
+        if let Err(e) = self.client.call(&tx).block(block).await {
+            result.revert_reason = Some(e.to_string());
+            return Ok(result);
+        }
+        let gas_used = match self.client.estimate_gas(&tx).block(block).await {
+            Ok(gas_used) => gas_used,
+            Err(e) => {
+                result.revert_reason = Some(e.to_string());
+                return Ok(result);
+            }
+        };
+        result.success = true;
+        result.gas_used = Some(gas_used);
+
+        let market = self.gas_bidder.market_fees().await?;
+        let mut priced = tx.with_gas_limit(gas_used);
+        GasBid::from_market(market.base_fee, market.priority_fee).apply(&mut priced);
+        let l1_fee = self.gas_bidder.l1_fee(&priced).await?;
+
+        // the bid is the max fee, and is paid in full since everything above the base fee is tip
+        let gas_price = match gas_bid_info
+            .map(|info| GasBid::from_profit(info, gas_used, l1_fee, market.base_fee))
+        {
+            Some(Ok(bid)) => bid.max_fee_per_gas,
+            Some(Err(e)) => {
+                // the live executors drop the action without submitting it
+                result.success = false;
+                result.drop_reason = Some(format!("failed to bid for gas: {}", e));
+                return Ok(result);
+            }
+            None => market.base_fee.saturating_add(market.priority_fee),
+        };
+        let gas_cost = (U256::from(gas_used) * U256::from(gas_price)).saturating_add(l1_fee);
+        result.gas_price = Some(gas_price);
+        result.gas_cost = Some(gas_cost);
+        result.net_profit = expected_profit.and_then(|profit| {
+            Some(
+                I256::try_from(profit)
+                    .ok()?
+                    .saturating_sub(I256::try_from(gas_cost).ok()?),
+            )
+        });
+        Ok(result)
+    }
+
+    /// Records the result of a simulation and reports it to the strategy.
+    async fn record(&self, result: Result<DryRunResult>, reporter: OutcomeReporter) -> Result<()> {
+        let result = match result {
+            Ok(result) => result,
+            Err(e) => {
+                reporter.report(ActionStatus::Failed {
+                    reason: e.to_string(),
+                });
+                return Err(e);
+            }
+        };
+
+        info!(
+            "Dry run of {} at block {}: success: {}, gas used: {:?}, gas cost: {:?}, net profit: {:?}, revert reason: {:?}, drop reason: {:?}",
+            result.action,
+            result.block_number,
+            result.success,
+            result.gas_used,
+            result.gas_cost,
+            result.net_profit,
+            result.revert_reason,
+            result.drop_reason
+        );
+        if let Err(e) = self.journal.record(&result).await {
+            warn!("Failed to record dry run result: {:?}", e);
+        }
+
+        match (result.revert_reason, result.drop_reason) {
+            (Some(reason), _) => reporter.report(ActionStatus::Dropped {
+                reason: format!("reverted in dry run: {}", reason),
+            }),
+            (None, Some(reason)) => reporter.report(ActionStatus::Dropped { reason }),
+            (None, None) => reporter.report(ActionStatus::Executed),
+        }
+        Ok(())
+    }
+}
+
+/// Implementation of the [Executor] trait for [DryRunExecutor], simulating
+/// transactions that would be submitted to the mempool.
+#[async_trait]
+impl<P: Provider + 'static> Executor<SubmitTxToMempool> for DryRunExecutor<P> {
+    async fn execute(&self, action: SubmitTxToMempool) -> Result<()> {
+        self.execute_with_reporter(action, OutcomeReporter::noop())
+            .await
+    }
+
+    async fn execute_with_reporter(
+        &self,
+        action: SubmitTxToMempool,
+        reporter: OutcomeReporter,
+    ) -> Result<()> {
+        let gas_bid_info = action.gas_bid_info.as_ref();
+        let expected_profit = gas_bid_info.map(|info| info.total_profit);
+        let result = self
+            .simulate(
+                "SubmitTxToMempool",
+                action.tx,
+                gas_bid_info,
+                expected_profit,
+            )
+            .await;
+        self.record(result, reporter).await
+    }
+}
+
+/// Implementation of the [Executor] trait for [DryRunExecutor], simulating
+/// calls to the batch executor contract.
+#[async_trait]
+impl<P: Provider + 'static> Executor<SubmitEncodedTx> for DryRunExecutor<P> {
+    async fn execute(&self, action: SubmitEncodedTx) -> Result<()> {
+        self.execute_with_reporter(action, OutcomeReporter::noop())
+            .await
+    }
+
+    async fn execute_with_reporter(
+        &self,
+        action: SubmitEncodedTx,
+        reporter: OutcomeReporter,
+    ) -> Result<()> {
+        let Some(address) = self.batch_executor else {
+            let e = eyre!("no batch executor address set");
+            reporter.report(ActionStatus::Failed {
+                reason: e.to_string(),
+            });
+            return Err(e);
+        };
+        let executor: BatchExecutorInstance<(), Arc<P>> =
+            BatchExecutorInstance::new(address, self.client.clone());
+        let expected_profit = action
+            .expected_profit
+            .or(action.gas_bid_info.as_ref().map(|info| info.total_profit));
+
+        let result = match executor.OWNER().call().await {
+            Ok(owner) => {
+                let tx = executor
+                    .batchCall(action.calldata)
+                    .value(action.total_value)
+                    .from(owner._0)
+                    .into_transaction_request();
+                self.simulate(
+                    "SubmitEncodedTx",
+                    tx,
+                    action.gas_bid_info.as_ref(),
+                    expected_profit,
+                )
+                .await
+            }
+            Err(e) => Err(e.into()),
+        };
+        self.record(result, reporter).await
+    }
+}
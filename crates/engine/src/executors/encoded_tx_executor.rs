@@ -1,8 +1,14 @@
 use std::{env, str::FromStr, sync::Arc};
 
+use crate::executors::{
+    gas_bidder::GasBidder, report_when_resolved, tx_manager::TxManager, OutcomeTasks,
+};
+use crate::metrics;
+use crate::outcome::{ActionStatus, OutcomeReporter};
 use crate::types::Executor;
 use alloy::{
     contract::Error,
+    eips::BlockId,
     primitives::{Address, Bytes, U256},
     providers::Provider,
 };
@@ -11,20 +17,15 @@ use eyre::Result;
 use tracing::{info, warn};
 use tx_executor::bindings::batchexecutor::BatchExecutor::BatchExecutorInstance;
 
-#[derive(Debug, Clone)]
-pub struct GasBidInfo {
-    /// Total profit expected from opportunity in wei
-    pub total_profit: U256,
-    /// Percentage of profit to use for gas (0-100)
-    /// For example, 50 means 50% of profit will be used for gas
-    pub bid_percentage: u64,
-}
+pub use crate::executors::gas_bidder::GasBidInfo;
 
 #[derive(Debug, Clone)]
 pub struct SubmitEncodedTx {
     /// The calldata to submit
     pub calldata: Vec<Bytes>,
     pub total_value: U256,
+    /// Profit expected from the transaction in wei, recorded by dry runs
+    pub expected_profit: Option<U256>,
     /// Optional gas bidding information
     pub gas_bid_info: Option<GasBidInfo>,
 }
@@ -34,6 +35,12 @@ pub struct EncodedTxExecutor<P: Provider> {
     #[allow(unused)]
     client: Arc<P>,
     executor: BatchExecutorInstance<(), Arc<P>>,
+    /// The manager tracking submitted transactions, if any
+    tx_manager: Option<Arc<TxManager<P>>>,
+    /// Computes the fees of submitted transactions
+    gas_bidder: GasBidder<P>,
+    /// The tasks reporting the outcome of transactions sent through the manager
+    outcome_tasks: OutcomeTasks,
 }
 
 impl<P: Provider> EncodedTxExecutor<P> {
@@ -44,13 +51,43 @@ impl<P: Provider> EncodedTxExecutor<P> {
         Self {
             client: client.clone(),
             executor,
+            tx_manager: None,
+            gas_bidder: GasBidder::new(client.clone()),
+            outcome_tasks: OutcomeTasks::default(),
         }
     }
+
+    /// Sets the [GasBidder] computing the fees of submitted transactions, for
+    /// example to account for L1 data fees.
+    pub fn with_gas_bidder(mut self, gas_bidder: GasBidder<P>) -> Self {
+        self.gas_bidder = gas_bidder;
+        self
+    }
+
+    /// Sends transactions through a [TxManager] instead of waiting for their
+    /// receipt, so that several transactions can be in flight at once.
+    pub fn with_tx_manager(mut self, tx_manager: Arc<TxManager<P>>) -> Self {
+        self.tx_manager = Some(tx_manager);
+        self
+    }
 }
 
 #[async_trait]
-impl<P: Provider> Executor<SubmitEncodedTx> for EncodedTxExecutor<P> {
+impl<P: Provider + 'static> Executor<SubmitEncodedTx> for EncodedTxExecutor<P> {
     async fn execute(&self, action: SubmitEncodedTx) -> Result<()> {
+        self.execute_with_reporter(action, OutcomeReporter::noop())
+            .await
+    }
+
+    async fn drain(&self) -> Result<()> {
+        self.outcome_tasks.join().await
+    }
+
+    async fn execute_with_reporter(
+        &self,
+        action: SubmitEncodedTx,
+        reporter: OutcomeReporter,
+    ) -> Result<()> {
         let total_value = action.total_value;
         let calldata = action.calldata.clone();
         let gas_bid_info = action.gas_bid_info.clone();
@@ -65,6 +102,10 @@ impl<P: Provider> Executor<SubmitEncodedTx> for EncodedTxExecutor<P> {
         let result = call.estimate_gas().await;
 
         if let Err(e) = result {
+            metrics::gas_estimation_failed(self.name());
+            reporter.report(ActionStatus::Dropped {
+                reason: format!("gas estimation failed: {}", e),
+            });
             match e {
                 Error::TransportError(rpc_err) => {
                     let error_response = rpc_err.as_error_resp();
@@ -82,24 +123,100 @@ impl<P: Provider> Executor<SubmitEncodedTx> for EncodedTxExecutor<P> {
 
         let gas_usage = result.unwrap();
 
-        let bid_gas_price: u128;
-        if let Some(gas_bid_info) = gas_bid_info {
-            let breakeven_gas_price: u128 =
-                gas_bid_info.total_profit.to::<u128>() / u128::from(gas_usage);
-            bid_gas_price = breakeven_gas_price * u128::from(gas_bid_info.bid_percentage) / 100;
-        } else {
-            bid_gas_price = self.client.get_gas_price().await.unwrap();
+        let tx = call.clone().into_transaction_request();
+        let bid = match self
+            .gas_bidder
+            .bid(&tx, gas_usage, gas_bid_info.as_ref())
+            .await
+        {
+            Ok(bid) => bid,
+            Err(e) => {
+                warn!("Error computing gas bid: {:?}", e);
+                reporter.report(ActionStatus::Dropped {
+                    reason: format!("failed to bid for gas: {}", e),
+                });
+                return Ok(());
+            }
+        };
+
+        info!(
+            "Sending tx with max fee: {}, priority fee: {}",
+            bid.max_fee_per_gas, bid.max_priority_fee_per_gas
+        );
+        let call = call
+            .max_fee_per_gas(bid.max_fee_per_gas)
+            .max_priority_fee_per_gas(bid.max_priority_fee_per_gas);
+        if let Some(tx_manager) = &self.tx_manager {
+            let tx = call.gas(gas_usage).into_transaction_request();
+            let handle = match tx_manager
+                .send_with_fee_cap(tx.clone(), bid.max_fee_cap)
+                .await
+            {
+                Ok(handle) => handle,
+                Err(e) => {
+                    reporter.report(ActionStatus::Failed {
+                        reason: e.to_string(),
+                    });
+                    return Err(e);
+                }
+            };
+            metrics::tx_submitted(self.name());
+            reporter.report(ActionStatus::Submitted {
+                tx_hash: handle.tx_hash,
+            });
+            report_when_resolved(
+                &self.outcome_tasks,
+                self.name(),
+                self.client.clone(),
+                tx,
+                handle,
+                reporter,
+            );
+            return Ok(());
         }
 
-        info!("Sending tx with gas price: {}", bid_gas_price);
-        let receipt = call
-            .gas_price(bid_gas_price)
-            .send()
-            .await?
-            .get_receipt()
-            .await?;
+        let pending_tx = match call.send().await {
+            Ok(pending_tx) => pending_tx,
+            Err(e) => {
+                reporter.report(ActionStatus::Failed {
+                    reason: e.to_string(),
+                });
+                return Err(e.into());
+            }
+        };
+        metrics::tx_submitted(self.name());
+        reporter.report(ActionStatus::Submitted {
+            tx_hash: *pending_tx.tx_hash(),
+        });
+
+        let receipt = match pending_tx.get_receipt().await {
+            Ok(receipt) => receipt,
+            Err(e) => {
+                reporter.report(ActionStatus::Failed {
+                    reason: e.to_string(),
+                });
+                return Err(e.into());
+            }
+        };
+        metrics::tx_mined(self.name(), &receipt);
         info!("Transaction receipt: {:?}", receipt);
 
+        if receipt.status() {
+            reporter.report(ActionStatus::Mined { receipt });
+        } else {
+            // replay the call at the block it was mined in to recover the revert reason
+            let reason = match receipt.block_number {
+                Some(block) => call
+                    .block(BlockId::number(block))
+                    .call_raw()
+                    .await
+                    .err()
+                    .map(|e| e.to_string()),
+                None => None,
+            };
+            reporter.report(ActionStatus::Reverted { receipt, reason });
+        }
+
         Ok(())
     }
 }
@@ -0,0 +1,338 @@
+use std::sync::Arc;
+
+use alloy::{
+    consensus::{SignableTransaction, TxEip1559},
+    eips::BlockNumberOrTag,
+    primitives::{address, keccak256, Address, Bytes, U256},
+    providers::Provider,
+    rpc::types::TransactionRequest,
+    sol,
+};
+use eyre::{eyre, Result};
+
+sol! {
+    /// The GasPriceOracle predeploy of OP-stack chains.
+    #[sol(rpc)]
+    interface IGasPriceOracle {
+        function getL1Fee(bytes memory _data) external view returns (uint256);
+    }
+}
+
+/// Address of the GasPriceOracle predeploy on OP-stack chains (Optimism, Base, ...).
+pub const GAS_PRICE_ORACLE: Address = address!("420000000000000000000000000000000000000F");
+
+/// Information about the gas bid for a transaction.
+#[derive(Debug, Clone)]
+pub struct GasBidInfo {
+    /// Total profit expected from opportunity in wei
+    pub total_profit: U256,
+
+    /// Percentage of profit to use for gas (0-100)
+    /// For example, 50 means 50% of profit will be used for gas
+    pub bid_percentage: u64,
+}
+
+/// The EIP-1559 fees of a transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GasBid {
+    pub max_fee_per_gas: u128,
+    pub max_priority_fee_per_gas: u128,
+    /// The highest max fee the transaction can be replaced with while its
+    /// profit still covers it, or None if the bid is not derived from a profit
+    pub max_fee_cap: Option<u128>,
+}
+
+impl GasBid {
+    /// Computes the bid for an opportunity, given the gas it uses, the L1 data
+    /// fee it pays (zero on L1) and the base fee of the next block.
+    ///
+    /// The break-even gas price is the profit left after the L1 data fee,
+    /// divided by the gas used. The bid is `bid_percentage` of it, and is used
+    /// as the max fee so that the transaction never costs more than the bid,
+    /// whatever the base fee ends up being. Everything above the base fee is
+    /// paid as priority fee.
+    ///
+    /// The break-even gas price is kept as the fee cap of the bid, above which
+    /// fee bumps would cost more than the opportunity is worth.
+    ///
+    /// Returns an error if the profit does not cover the base fee.
+    pub fn from_profit(
+        info: &GasBidInfo,
+        gas_used: u64,
+        l1_fee: U256,
+        base_fee: u128,
+    ) -> Result<Self> {
+        if info.bid_percentage > 100 {
+            return Err(eyre!("invalid bid percentage: {}", info.bid_percentage));
+        }
+        if gas_used == 0 {
+            return Err(eyre!("cannot bid for a transaction using no gas"));
+        }
+        let l2_profit = info.total_profit.checked_sub(l1_fee).ok_or_else(|| {
+            eyre!(
+                "profit {} does not cover the L1 data fee {}",
+                info.total_profit,
+                l1_fee
+            )
+        })?;
+        let max_fee_cap: u128 = (l2_profit / U256::from(gas_used)).saturating_to();
+        let budget = l2_profit
+            .checked_mul(U256::from(info.bid_percentage))
+            .ok_or_else(|| eyre!("gas budget overflow"))?
+            / U256::from(100);
+
+        // a bid above u128::MAX is unreachable in practice, capping it keeps it within budget
+        let max_fee_per_gas: u128 = (budget / U256::from(gas_used)).saturating_to();
+        if max_fee_per_gas <= base_fee {
+            return Err(eyre!(
+                "bid {} does not cover the base fee {}",
+                max_fee_per_gas,
+                base_fee
+            ));
+        }
+        Ok(Self {
+            max_fee_per_gas,
+            max_priority_fee_per_gas: max_fee_per_gas - base_fee,
+            max_fee_cap: Some(max_fee_cap),
+        })
+    }
+
+    /// Computes a bid following the market, paying `priority_fee` on top of the
+    /// base fee. The max fee leaves room for the base fee to double, so that
+    /// the transaction stays valid for a few blocks.
+    pub fn from_market(base_fee: u128, priority_fee: u128) -> Self {
+        Self {
+            max_fee_per_gas: base_fee.saturating_mul(2).saturating_add(priority_fee),
+            max_priority_fee_per_gas: priority_fee,
+            max_fee_cap: None,
+        }
+    }
+
+    /// Sets the fees of a transaction, replacing its legacy gas price if any.
+    pub fn apply(&self, tx: &mut TransactionRequest) {
+        tx.gas_price = None;
+        tx.max_fee_per_gas = Some(self.max_fee_per_gas);
+        tx.max_priority_fee_per_gas = Some(self.max_priority_fee_per_gas);
+    }
+}
+
+/// Market fees, as reported by `eth_feeHistory`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MarketFees {
+    /// The base fee of the next block
+    pub base_fee: u128,
+    /// The priority fee paid over the recent blocks, at the configured percentile
+    pub priority_fee: u128,
+}
+
+/// GasBidder computes EIP-1559 fees for transactions, either from the profit
+/// of an opportunity or from the market fees.
+///
+/// On OP-stack chains, transactions also pay an L1 data fee that does not
+/// depend on the gas price. When enabled with [GasBidder::with_l1_data_fee],
+/// it is queried from the [GAS_PRICE_ORACLE] and deducted from the profit
+/// before bidding.
+pub struct GasBidder<P: Provider> {
+    client: Arc<P>,
+    /// Number of blocks requested from `eth_feeHistory`
+    fee_history_blocks: u64,
+    /// Percentile of the priority fees paid in recent blocks to bid
+    reward_percentile: f64,
+    /// Whether transactions pay an L1 data fee
+    l1_data_fee: bool,
+}
+
+impl<P: Provider> GasBidder<P> {
+    pub fn new(client: Arc<P>) -> Self {
+        Self {
+            client,
+            fee_history_blocks: 10,
+            reward_percentile: 50.0,
+            l1_data_fee: false,
+        }
+    }
+
+    /// Sets the number of blocks and the reward percentile used to estimate
+    /// the market priority fee.
+    pub fn with_fee_history(mut self, blocks: u64, reward_percentile: f64) -> Self {
+        self.fee_history_blocks = blocks;
+        self.reward_percentile = reward_percentile;
+        self
+    }
+
+    /// Sets whether transactions pay an L1 data fee, which is the case on
+    /// OP-stack chains like Optimism and Base, as told by `Chain::is_optimism`.
+    /// The fee is charged on top of the gas, so the profit must cover it
+    /// before any of it is bid.
+    pub fn with_l1_data_fee(mut self, l1_data_fee: bool) -> Self {
+        self.l1_data_fee = l1_data_fee;
+        self
+    }
+
+    /// Returns the base fee of the next block and the recent priority fee.
+    pub async fn market_fees(&self) -> Result<MarketFees> {
+        let history = self
+            .client
+            .get_fee_history(
+                self.fee_history_blocks,
+                BlockNumberOrTag::Latest,
+                &[self.reward_percentile],
+            )
+            .await?;
+        let base_fee = history
+            .next_block_base_fee()
+            .ok_or_else(|| eyre!("fee history has no base fee"))?;
+
+        // empty blocks report a reward of zero, which says nothing about the market
+        let rewards: Vec<u128> = history
+            .reward
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|rewards| rewards.first().copied())
+            .filter(|reward| *reward > 0)
+            .collect();
+        let priority_fee = match rewards.len() as u128 {
+            0 => 0,
+            count => rewards.iter().fold(0u128, |sum, r| sum.saturating_add(*r)) / count,
+        };
+        Ok(MarketFees {
+            base_fee,
+            priority_fee,
+        })
+    }
+
+    /// Returns the L1 data fee the transaction pays, or zero if L1 data fees
+    /// are disabled.
+    pub async fn l1_fee(&self, tx: &TransactionRequest) -> Result<U256> {
+        if !self.l1_data_fee {
+            return Ok(U256::ZERO);
+        }
+        let oracle = IGasPriceOracle::new(GAS_PRICE_ORACLE, self.client.clone());
+        let fee = oracle
+            .getL1Fee(unsigned_eip1559_encoding(tx).into())
+            .call()
+            .await?
+            ._0;
+        Ok(fee)
+    }
+
+    /// Estimates the L1 data fee of a transaction with `calldata_size` bytes of
+    /// calldata, before it is built. The calldata is taken as incompressible,
+    /// so that the estimate errs on the high side.
+    pub async fn estimate_l1_fee(&self, calldata_size: usize) -> Result<U256> {
+        if !self.l1_data_fee {
+            return Ok(U256::ZERO);
+        }
+        let calldata: Vec<u8> = (0u64..)
+            .flat_map(|chunk| keccak256(chunk.to_be_bytes()).0)
+            .take(calldata_size)
+            .collect();
+        let tx = TransactionRequest::default().input(Bytes::from(calldata).into());
+        self.l1_fee(&tx).await
+    }
+
+    /// Computes the fees of a transaction using `gas_used` gas. With bid info,
+    /// the bid is derived from the profit of the opportunity, otherwise it
+    /// follows the market.
+    pub async fn bid(
+        &self,
+        tx: &TransactionRequest,
+        gas_used: u64,
+        info: Option<&GasBidInfo>,
+    ) -> Result<GasBid> {
+        let market = self.market_fees().await?;
+        let Some(info) = info else {
+            return Ok(GasBid::from_market(market.base_fee, market.priority_fee));
+        };
+
+        let mut tx = tx.clone();
+        tx.gas = Some(gas_used);
+        GasBid::from_market(market.base_fee, market.priority_fee).apply(&mut tx);
+        let l1_fee = self.l1_fee(&tx).await?;
+        GasBid::from_profit(info, gas_used, l1_fee, market.base_fee)
+    }
+}
+
+/// Encodes a transaction as an unsigned EIP-1559 transaction, which is the
+/// input of `getL1Fee`. Missing fields are left empty: they only change the
+/// size of the encoding, and so the fee, by a few bytes.
+fn unsigned_eip1559_encoding(tx: &TransactionRequest) -> Vec<u8> {
+    TxEip1559 {
+        chain_id: tx.chain_id.unwrap_or_default(),
+        nonce: tx.nonce.unwrap_or_default(),
+        gas_limit: tx.gas.unwrap_or_default(),
+        max_fee_per_gas: tx.max_fee_per_gas.unwrap_or_default(),
+        max_priority_fee_per_gas: tx.max_priority_fee_per_gas.unwrap_or_default(),
+        to: tx.to.unwrap_or_default(),
+        value: tx.value.unwrap_or_default(),
+        access_list: tx.access_list.clone().unwrap_or_default(),
+        input: tx.input.input().cloned().unwrap_or_default(),
+    }
+    .encoded_for_signing()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::network::TransactionBuilder;
+
+    fn info(total_profit: U256, bid_percentage: u64) -> GasBidInfo {
+        GasBidInfo {
+            total_profit,
+            bid_percentage,
+        }
+    }
+
+    #[test]
+    fn test_bid_from_profit() {
+        // 1 ETH of profit, 50% bid, 100k gas => 5000 gwei max fee
+        let profit = U256::from(10u128.pow(18));
+        let bid = GasBid::from_profit(&info(profit, 50), 100_000, U256::ZERO, 1_000).unwrap();
+        assert_eq!(bid.max_fee_per_gas, 5_000_000_000_000);
+        assert_eq!(bid.max_priority_fee_per_gas, 5_000_000_000_000 - 1_000);
+        // replacements can bid up to the whole profit
+        assert_eq!(bid.max_fee_cap, Some(10_000_000_000_000));
+    }
+
+    #[test]
+    fn test_bid_deducts_l1_fee() {
+        let profit = U256::from(2_000_000);
+        let l1_fee = U256::from(1_000_000);
+        let bid = GasBid::from_profit(&info(profit, 100), 1_000, l1_fee, 10).unwrap();
+        assert_eq!(bid.max_fee_per_gas, 1_000);
+
+        assert!(
+            GasBid::from_profit(&info(profit, 100), 1_000, profit + U256::from(1), 10).is_err()
+        );
+    }
+
+    #[test]
+    fn test_bid_does_not_overflow() {
+        let bid = GasBid::from_profit(&info(U256::MAX, 100), 21_000, U256::ZERO, 0);
+        assert!(bid.is_err());
+
+        let bid =
+            GasBid::from_profit(&info(U256::MAX / U256::from(100), 100), 1, U256::ZERO, 0).unwrap();
+        assert_eq!(bid.max_fee_per_gas, u128::MAX);
+    }
+
+    #[test]
+    fn test_bid_below_base_fee() {
+        let bid = GasBid::from_profit(&info(U256::from(1_000_000), 50), 1_000, U256::ZERO, 500);
+        assert!(bid.is_err());
+        assert!(GasBid::from_profit(&info(U256::from(1), 101), 1, U256::ZERO, 0).is_err());
+    }
+
+    #[test]
+    fn test_bid_from_market() {
+        let bid = GasBid::from_market(100, 2);
+        assert_eq!(bid.max_fee_per_gas, 202);
+        assert_eq!(bid.max_priority_fee_per_gas, 2);
+        assert_eq!(bid.max_fee_cap, None);
+
+        let mut tx = TransactionRequest::default().with_gas_price(1);
+        bid.apply(&mut tx);
+        assert_eq!(tx.gas_price, None);
+        assert_eq!(tx.max_fee_per_gas, Some(202));
+    }
+}
@@ -1,23 +1,16 @@
-use std::{
-    ops::{Div, Mul},
-    sync::Arc,
-};
+use std::sync::Arc;
 
+use crate::executors::{
+    gas_bidder::GasBidder, report_when_resolved, revert_reason, tx_manager::TxManager, OutcomeTasks,
+};
+use crate::metrics;
+use crate::outcome::{ActionStatus, OutcomeReporter};
 use crate::types::Executor;
-use alloy::{primitives::U256, providers::Provider, rpc::types::TransactionRequest};
+use alloy::{providers::Provider, rpc::types::TransactionRequest};
 use async_trait::async_trait;
 use eyre::{Context, Result};
 
-/// Information about the gas bid for a transaction.
-#[derive(Debug, Clone)]
-pub struct GasBidInfo {
-    /// Total profit expected from opportunity in wei
-    pub total_profit: U256,
-
-    /// Percentage of profit to use for gas (0-100)
-    /// For example, 50 means 50% of profit will be used for gas
-    pub bid_percentage: u64,
-}
+pub use crate::executors::gas_bidder::GasBidInfo;
 
 /// Action to submit a transaction to the public mempool.
 /// Can optionally include gas bidding information for
@@ -54,22 +47,51 @@ pub struct SubmitTxToMempool {
 pub struct MempoolExecutor<P: Provider> {
     /// The blockchain provider used to submit transactions
     client: Arc<P>,
+    /// The manager tracking submitted transactions, if any
+    tx_manager: Option<Arc<TxManager<P>>>,
+    /// Computes the fees of submitted transactions
+    gas_bidder: GasBidder<P>,
+    /// The tasks reporting the outcome of transactions sent through the manager
+    outcome_tasks: OutcomeTasks,
 }
 
 impl<P: Provider> MempoolExecutor<P> {
     pub fn new(client: Arc<P>) -> Self {
-        Self { client }
+        Self {
+            gas_bidder: GasBidder::new(client.clone()),
+            client,
+            tx_manager: None,
+            outcome_tasks: OutcomeTasks::default(),
+        }
+    }
+
+    /// Sets the [GasBidder] computing the fees of submitted transactions, for
+    /// example to account for L1 data fees.
+    pub fn with_gas_bidder(mut self, gas_bidder: GasBidder<P>) -> Self {
+        self.gas_bidder = gas_bidder;
+        self
+    }
+
+    /// Sends transactions through a [TxManager] instead of waiting for their
+    /// receipt, so that several transactions can be in flight at once.
+    pub fn with_tx_manager(mut self, tx_manager: Arc<TxManager<P>>) -> Self {
+        self.tx_manager = Some(tx_manager);
+        self
     }
 }
 
 /// Implementation of the [Executor] trait for [MempoolExecutor].
 /// This implementation:
 /// 1. Estimates gas usage for the transaction
-/// 2. Calculates optimal gas price (either based on profit or current market price)
+/// 2. Calculates EIP-1559 fees (either based on profit or current market fees)
 /// 3. Submits the transaction to the mempool
-/// 4. Waits for and returns the transaction receipt
+/// 4. Waits for and returns the transaction receipt, unless a [TxManager] is
+///    set, in which case the receipt is reported once the transaction is mined
+///
+/// The transaction hash, the receipt and the revert reason of reverted
+/// transactions are reported back to the strategy.
 #[async_trait]
-impl<P: Provider> Executor<SubmitTxToMempool> for MempoolExecutor<P> {
+impl<P: Provider + 'static> Executor<SubmitTxToMempool> for MempoolExecutor<P> {
     /// Executes a transaction submission to the mempool.
     ///
     /// # Arguments
@@ -82,43 +104,107 @@ impl<P: Provider> Executor<SubmitTxToMempool> for MempoolExecutor<P> {
     /// # Errors
     /// Returns an error if:
     /// * Gas estimation fails
-    /// * Gas price calculation fails
+    /// * Fee calculation fails, or the profit does not cover the fees
     /// * Transaction submission fails
     /// * Transaction receipt retrieval fails
-    async fn execute(&self, mut action: SubmitTxToMempool) -> Result<()> {
-        let gas_usage = self
-            .client
-            .estimate_gas(&action.tx)
+    async fn execute(&self, action: SubmitTxToMempool) -> Result<()> {
+        self.execute_with_reporter(action, OutcomeReporter::noop())
             .await
-            .context("Error estimating gas usage: {}")?;
+    }
 
-        let bid_gas_price: u64;
-        if let Some(gas_bid_info) = action.gas_bid_info {
-            // gas price at which we'd break even, meaning 100% of profit goes to validator
-            let breakeven_gas_price: u64 = gas_bid_info.total_profit.to::<u64>() / gas_usage;
-            // gas price corresponding to bid percentage
-            bid_gas_price = breakeven_gas_price
-                .mul(u64::from(gas_bid_info.bid_percentage))
-                .div(100);
-        } else {
-            bid_gas_price = self
-                .client
-                .get_gas_price()
+    async fn drain(&self) -> Result<()> {
+        self.outcome_tasks.join().await
+    }
+
+    async fn execute_with_reporter(
+        &self,
+        mut action: SubmitTxToMempool,
+        reporter: OutcomeReporter,
+    ) -> Result<()> {
+        let gas_usage = match self.client.estimate_gas(&action.tx).await {
+            Ok(gas_usage) => gas_usage,
+            Err(e) => {
+                metrics::gas_estimation_failed(self.name());
+                reporter.report(ActionStatus::Dropped {
+                    reason: format!("gas estimation failed: {}", e),
+                });
+                return Err(e).context("Error estimating gas usage: {}");
+            }
+        };
+
+        let bid = self
+            .gas_bidder
+            .bid(&action.tx, gas_usage, action.gas_bid_info.as_ref())
+            .await
+            .inspect_err(|e| {
+                reporter.report(ActionStatus::Dropped {
+                    reason: format!("failed to bid for gas: {}", e),
+                })
+            })
+            .context("Error computing gas bid")?;
+
+        bid.apply(&mut action.tx);
+        if let Some(tx_manager) = &self.tx_manager {
+            action.tx.gas = Some(gas_usage);
+            let handle = match tx_manager
+                .send_with_fee_cap(action.tx.clone(), bid.max_fee_cap)
                 .await
-                .context("Error getting gas price: {}")?
-                .try_into()
-                .context("Error converting gas price to u64: {}")?;
+            {
+                Ok(handle) => handle,
+                Err(e) => {
+                    reporter.report(ActionStatus::Failed {
+                        reason: e.to_string(),
+                    });
+                    return Err(e);
+                }
+            };
+            metrics::tx_submitted(self.name());
+            reporter.report(ActionStatus::Submitted {
+                tx_hash: handle.tx_hash,
+            });
+            report_when_resolved(
+                &self.outcome_tasks,
+                self.name(),
+                self.client.clone(),
+                action.tx,
+                handle,
+                reporter,
+            );
+            return Ok(());
         }
 
-        action.tx.gas_price = Some(bid_gas_price.into());
-        let receipt = self
-            .client
-            .send_transaction(action.tx)
-            .await?
-            .get_receipt()
-            .await?;
+        let pending_tx = match self.client.send_transaction(action.tx.clone()).await {
+            Ok(pending_tx) => pending_tx,
+            Err(e) => {
+                reporter.report(ActionStatus::Failed {
+                    reason: e.to_string(),
+                });
+                return Err(e.into());
+            }
+        };
+        metrics::tx_submitted(self.name());
+        reporter.report(ActionStatus::Submitted {
+            tx_hash: *pending_tx.tx_hash(),
+        });
+
+        let receipt = match pending_tx.get_receipt().await {
+            Ok(receipt) => receipt,
+            Err(e) => {
+                reporter.report(ActionStatus::Failed {
+                    reason: e.to_string(),
+                });
+                return Err(e.into());
+            }
+        };
+        metrics::tx_mined(self.name(), &receipt);
 
         println!("Transaction receipt: {:?}", receipt);
+        if receipt.status() {
+            reporter.report(ActionStatus::Mined { receipt });
+        } else {
+            let reason = revert_reason(self.client.as_ref(), &action.tx, &receipt).await;
+            reporter.report(ActionStatus::Reverted { receipt, reason });
+        }
         Ok(())
     }
 }
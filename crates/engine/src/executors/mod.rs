@@ -2,8 +2,164 @@
 //! executing them in different domains. For example, an executor might take a
 //! `SubmitTx` action and submit it to the mempool.
 
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::metrics;
+use crate::outcome::{ActionStatus, OutcomeReporter};
+use alloy::{
+    eips::BlockId,
+    providers::Provider,
+    rpc::types::{TransactionReceipt, TransactionRequest},
+};
+use eyre::{bail, Result};
+use tokio::task::JoinSet;
+use tracing::error;
+use tx_manager::{TxHandle, TxResolution};
+
 pub mod mempool_executor;
 /// This executor submits transactions to the public mempool.
 pub mod sequence_executor;
 
 pub mod encoded_tx_executor;
+
+/// This executor simulates actions instead of executing them, and journals
+/// what would have happened.
+pub mod dry_run_executor;
+
+/// This executor submits bundles to MEV relays and block builders.
+pub mod bundle_executor;
+/// This module computes EIP-1559 fees from the profit of an opportunity or from
+/// the market, including the L1 data fee of OP-stack chains.
+pub mod gas_bidder;
+/// This module contains the JSON-RPC client used to submit bundles to relays.
+pub mod relay;
+/// This module contains the [TxManager](tx_manager::TxManager), which allocates
+/// nonces and tracks pending transactions on behalf of executors.
+pub mod tx_manager;
+
+/// Replays a reverted transaction at the block it was mined in to recover its
+/// revert reason. Best effort: the state the transaction saw may have been
+/// changed by transactions that came before it in the block.
+pub(crate) async fn revert_reason<P: Provider>(
+    client: &P,
+    tx: &TransactionRequest,
+    receipt: &TransactionReceipt,
+) -> Option<String> {
+    let block = BlockId::number(receipt.block_number?);
+    client
+        .call(tx)
+        .block(block)
+        .await
+        .err()
+        .map(|e| e.to_string())
+}
+
+/// The tasks an executor spawns to report the outcome of its transactions
+/// once they are resolved. The executor waits for them when it
+/// [drains](crate::types::Executor::drain), so that no outcome is lost on
+/// shutdown and the tasks that panicked are reported.
+#[derive(Debug, Default)]
+pub(crate) struct OutcomeTasks {
+    tasks: Mutex<JoinSet<()>>,
+    failed: AtomicUsize,
+}
+
+impl OutcomeTasks {
+    /// Spawns a task, and reaps the tasks that already finished.
+    pub(crate) fn spawn(&self, task: impl Future<Output = ()> + Send + 'static) {
+        let mut tasks = self.tasks.lock().unwrap();
+        while let Some(result) = tasks.try_join_next() {
+            self.reap(result);
+        }
+        tasks.spawn(task);
+    }
+
+    /// Waits for every task spawned so far. Fails if a task panicked.
+    pub(crate) async fn join(&self) -> Result<()> {
+        let mut tasks = std::mem::take(&mut *self.tasks.lock().unwrap());
+        while let Some(result) = tasks.join_next().await {
+            self.reap(result);
+        }
+        match self.failed.swap(0, Ordering::Relaxed) {
+            0 => Ok(()),
+            failed => bail!("{} outcome reporting tasks failed", failed),
+        }
+    }
+
+    fn reap(&self, result: Result<(), tokio::task::JoinError>) {
+        if let Err(e) = result {
+            error!("outcome reporting task failed: {}", e);
+            self.failed.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Reports the outcome of a transaction sent through a [TxManager](tx_manager::TxManager)
+/// once it is resolved, without blocking the executor.
+pub(crate) fn report_when_resolved<P: Provider + 'static>(
+    tasks: &OutcomeTasks,
+    executor: &'static str,
+    client: Arc<P>,
+    tx: TransactionRequest,
+    handle: TxHandle,
+    reporter: OutcomeReporter,
+) {
+    tasks.spawn(async move {
+        let status = match handle.resolved().await {
+            TxResolution::Mined(receipt) => {
+                metrics::tx_mined(executor, &receipt);
+                if receipt.status() {
+                    ActionStatus::Mined { receipt }
+                } else {
+                    let reason = revert_reason(client.as_ref(), &tx, &receipt).await;
+                    ActionStatus::Reverted { receipt, reason }
+                }
+            }
+            TxResolution::Cancelled(receipt) => {
+                metrics::tx_mined(executor, &receipt);
+                ActionStatus::Dropped {
+                    reason: "cancelled after staying pending for too long".to_string(),
+                }
+            }
+            TxResolution::Dropped(reason) => ActionStatus::Dropped { reason },
+        };
+        reporter.report(status);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_outcome_tasks_are_joined() {
+        let tasks = OutcomeTasks::default();
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        for i in 0..3 {
+            let sender = sender.clone();
+            tasks.spawn(async move {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                sender.send(i).unwrap();
+            });
+        }
+        tasks.join().await.unwrap();
+        drop(sender);
+        let mut reported = vec![];
+        while let Some(i) = receiver.recv().await {
+            reported.push(i);
+        }
+        reported.sort();
+        assert_eq!(reported, [0, 1, 2]);
+
+        // panics are surfaced once, whether the task was reaped early or joined
+        tasks.spawn(async { panic!("reporting failed") });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        tasks.spawn(async {});
+        tasks.spawn(async { panic!("reporting failed") });
+        assert!(tasks.join().await.is_err());
+        assert!(tasks.join().await.is_ok());
+    }
+}
@@ -0,0 +1,232 @@
+use alloy::{
+    primitives::{hex, keccak256, Bytes, TxHash, B256, U256, U64},
+    signers::{local::PrivateKeySigner, Signer},
+};
+use eyre::{eyre, Context, Result};
+use reqwest::header::CONTENT_TYPE;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// Header carrying the signature of a request, used by relays to identify searchers.
+pub const FLASHBOTS_SIGNATURE_HEADER: &str = "X-Flashbots-Signature";
+
+/// A relay or block builder endpoint that accepts bundles.
+#[derive(Debug, Clone)]
+pub struct Relay {
+    /// The name of the relay, used in logs and errors
+    pub name: String,
+    /// The JSON-RPC endpoint of the relay
+    pub url: String,
+    /// Whether requests are signed with the [FLASHBOTS_SIGNATURE_HEADER]
+    pub sign_requests: bool,
+    /// Whether the relay supports `eth_callBundle`
+    pub simulation: bool,
+}
+
+impl Relay {
+    /// Creates a Flashbots compatible relay, which signs requests and supports simulation.
+    pub fn new(name: impl Into<String>, url: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            url: url.into(),
+            sign_requests: true,
+            simulation: true,
+        }
+    }
+
+    /// Creates a builder endpoint, which only accepts `eth_sendBundle`.
+    pub fn builder(name: impl Into<String>, url: impl Into<String>) -> Self {
+        Self::new(name, url).with_simulation(false)
+    }
+
+    /// The Flashbots relay on mainnet.
+    pub fn flashbots() -> Self {
+        Self::new("flashbots", "https://relay.flashbots.net")
+    }
+
+    /// Sets whether requests are signed with the [FLASHBOTS_SIGNATURE_HEADER].
+    pub fn with_signed_requests(mut self, sign_requests: bool) -> Self {
+        self.sign_requests = sign_requests;
+        self
+    }
+
+    /// Sets whether the relay supports `eth_callBundle`.
+    pub fn with_simulation(mut self, simulation: bool) -> Self {
+        self.simulation = simulation;
+        self
+    }
+}
+
+/// The parameters of an `eth_sendBundle` request.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleRequest {
+    /// The signed transactions of the bundle, in order
+    pub txs: Vec<Bytes>,
+    /// The block the bundle is valid for
+    pub block_number: U64,
+    /// Hashes of the transactions that are allowed to revert
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub reverting_tx_hashes: Vec<TxHash>,
+}
+
+/// The parameters of an `eth_callBundle` request.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CallBundleRequest<'a> {
+    txs: &'a [Bytes],
+    block_number: U64,
+    state_block_number: &'static str,
+}
+
+/// The result of an `eth_callBundle` request.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallBundleResponse {
+    /// The result of each transaction of the bundle
+    pub results: Vec<CallBundleResult>,
+    /// The gas used by the whole bundle
+    #[serde(default)]
+    pub total_gas_used: u64,
+    /// The payment to the block builder, in wei
+    #[serde(default)]
+    pub coinbase_diff: Option<U256>,
+}
+
+impl CallBundleResponse {
+    /// Returns the transactions that failed or reverted, along with the reason.
+    pub fn failures(&self) -> impl Iterator<Item = (TxHash, &str)> {
+        self.results.iter().filter_map(|result| {
+            let reason = result.error.as_ref().or(result.revert.as_ref())?;
+            Some((result.tx_hash, reason.as_str()))
+        })
+    }
+}
+
+/// The result of a single transaction simulated by `eth_callBundle`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallBundleResult {
+    pub tx_hash: TxHash,
+    #[serde(default)]
+    pub gas_used: u64,
+    /// The error, if the transaction failed
+    pub error: Option<String>,
+    /// The revert reason, if the transaction reverted
+    pub revert: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonRpcResponse {
+    #[serde(default)]
+    result: Value,
+    error: Option<JsonRpcError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonRpcError {
+    code: i64,
+    message: String,
+}
+
+/// Client submitting bundles to [relays](Relay) over JSON-RPC.
+///
+/// Requests are signed with a dedicated key, which relays use to identify the
+/// searcher and build its reputation. It does not need to hold any funds.
+#[derive(Debug, Clone)]
+pub struct RelayClient {
+    http: reqwest::Client,
+    signer: PrivateKeySigner,
+}
+
+impl RelayClient {
+    pub fn new(signer: PrivateKeySigner) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            signer,
+        }
+    }
+
+    /// Submits a bundle with `eth_sendBundle`. Returns the bundle hash, if the
+    /// relay returned one (some builders return no result at all).
+    pub async fn send_bundle(&self, relay: &Relay, bundle: &BundleRequest) -> Result<Option<B256>> {
+        let result: Value = self
+            .request(relay, "eth_sendBundle", serde_json::to_value(bundle)?)
+            .await?;
+        let bundle_hash = result
+            .get("bundleHash")
+            .and_then(|hash| serde_json::from_value(hash.clone()).ok());
+        Ok(bundle_hash)
+    }
+
+    /// Simulates a bundle with `eth_callBundle` on top of the latest block.
+    pub async fn call_bundle(
+        &self,
+        relay: &Relay,
+        bundle: &BundleRequest,
+    ) -> Result<CallBundleResponse> {
+        let params = CallBundleRequest {
+            txs: &bundle.txs,
+            block_number: bundle.block_number,
+            state_block_number: "latest",
+        };
+        self.request(relay, "eth_callBundle", serde_json::to_value(params)?)
+            .await
+    }
+
+    async fn request<T: DeserializeOwned>(
+        &self,
+        relay: &Relay,
+        method: &str,
+        params: Value,
+    ) -> Result<T> {
+        let body = serde_json::to_vec(&json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": [params],
+        }))?;
+
+        let mut request = self
+            .http
+            .post(&relay.url)
+            .header(CONTENT_TYPE, "application/json");
+        if relay.sign_requests {
+            request = request.header(FLASHBOTS_SIGNATURE_HEADER, self.signature(&body).await?);
+        }
+
+        let response: JsonRpcResponse = request
+            .body(body)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .with_context(|| format!("{} request to {} failed", method, relay.name))?
+            .json()
+            .await
+            .with_context(|| format!("invalid {} response from {}", method, relay.name))?;
+
+        if let Some(error) = response.error {
+            return Err(eyre!(
+                "{} returned an error for {}: {} (code {})",
+                relay.name,
+                method,
+                error.message,
+                error.code
+            ));
+        }
+        serde_json::from_value(response.result)
+            .with_context(|| format!("invalid {} result from {}", method, relay.name))
+    }
+
+    /// Signs the hash of a request body, in the `address:signature` format
+    /// expected by relays.
+    async fn signature(&self, body: &[u8]) -> Result<String> {
+        let message = keccak256(body).to_string();
+        let signature = self.signer.sign_message(message.as_bytes()).await?;
+        Ok(format!(
+            "{}:{}",
+            self.signer.address(),
+            hex::encode_prefixed(signature.as_bytes())
+        ))
+    }
+}
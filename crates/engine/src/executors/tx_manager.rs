@@ -0,0 +1,612 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use alloy::{
+    eips::eip2718::Encodable2718,
+    network::{Ethereum, EthereumWallet, NetworkWallet, TransactionBuilder},
+    primitives::{Address, TxHash, U256},
+    providers::Provider,
+    rpc::types::{TransactionReceipt, TransactionRequest},
+};
+use eyre::Result;
+use tokio::{sync::oneshot, task::JoinHandle};
+use tracing::{debug, info, warn};
+
+/// Configuration of a [TxManager].
+#[derive(Debug, Clone)]
+pub struct TxManagerConfig {
+    /// How often pending transactions are checked
+    pub poll_interval: Duration,
+    /// Blocks after which a pending transaction is replaced with higher fees
+    pub bump_after_blocks: u64,
+    /// Percentage by which fees are increased on each replacement. Nodes
+    /// usually require at least 10% to accept a replacement.
+    pub bump_percent: u64,
+    /// Maximum number of fee bumps per transaction
+    pub max_bumps: u32,
+    /// Blocks after which a pending transaction is cancelled, or None to never
+    /// cancel transactions
+    pub cancel_after_blocks: Option<u64>,
+}
+
+impl Default for TxManagerConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(1),
+            bump_after_blocks: 2,
+            bump_percent: 15,
+            max_bumps: 3,
+            cancel_after_blocks: Some(10),
+        }
+    }
+}
+
+impl TxManagerConfig {
+    /// Sets how often pending transactions are checked.
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Sets the number of blocks after which a pending transaction is fee bumped,
+    /// and the percentage by which its fees are increased.
+    pub fn with_fee_bump(mut self, after_blocks: u64, percent: u64) -> Self {
+        self.bump_after_blocks = after_blocks;
+        self.bump_percent = percent;
+        self
+    }
+
+    /// Sets the maximum number of fee bumps per transaction.
+    pub fn with_max_bumps(mut self, max_bumps: u32) -> Self {
+        self.max_bumps = max_bumps;
+        self
+    }
+
+    /// Sets the number of blocks after which a pending transaction is cancelled.
+    pub fn with_cancel_after_blocks(mut self, blocks: Option<u64>) -> Self {
+        self.cancel_after_blocks = blocks;
+        self
+    }
+}
+
+/// How a transaction sent through a [TxManager] was resolved.
+#[derive(Debug, Clone)]
+pub enum TxResolution {
+    /// The transaction, or one of its replacements, was mined. It may have reverted.
+    Mined(TransactionReceipt),
+    /// The transaction was stale and was cancelled by a zero-value self-transfer,
+    /// whose receipt is returned
+    Cancelled(TransactionReceipt),
+    /// The transaction is no longer tracked, for example because its nonce was
+    /// used by another transaction
+    Dropped(String),
+}
+
+/// Handle to a transaction sent through a [TxManager].
+#[derive(Debug)]
+pub struct TxHandle {
+    /// The nonce allocated to the transaction
+    pub nonce: u64,
+    /// The hash of the transaction as first sent. Replacements have different hashes.
+    pub tx_hash: TxHash,
+    resolution: oneshot::Receiver<TxResolution>,
+}
+
+impl TxHandle {
+    /// Waits until the transaction is mined, cancelled or dropped.
+    pub async fn resolved(self) -> TxResolution {
+        self.resolution
+            .await
+            .unwrap_or_else(|_| TxResolution::Dropped("transaction manager stopped".to_string()))
+    }
+}
+
+#[derive(Debug, Default)]
+struct WalletState {
+    /// The next nonce to allocate, or None to fetch it from the node
+    next_nonce: Option<u64>,
+    /// Nonces allocated to transactions that could not be sent, allocated
+    /// again before `next_nonce` so that no gap is left
+    released: BTreeSet<u64>,
+    /// Transactions waiting to be mined, by nonce
+    pending: BTreeMap<u64, PendingTx>,
+}
+
+impl WalletState {
+    /// Returns true if a nonce can be allocated without asking the node.
+    fn has_nonce(&self) -> bool {
+        self.next_nonce.is_some() || !self.released.is_empty()
+    }
+
+    /// Allocates a nonce, `fetched` being the pending nonce of the account on
+    /// the node when none is known locally.
+    fn allocate(&mut self, fetched: Option<u64>) -> u64 {
+        if let Some(nonce) = self.released.pop_first() {
+            return nonce;
+        }
+        let nonce = self.next_nonce.or(fetched).unwrap_or_default();
+        self.next_nonce = Some(nonce + 1);
+        nonce
+    }
+
+    /// Gives back the nonce of a transaction that could not be sent. A nonce
+    /// error means the local nonces are out of sync with the node, so they are
+    /// fetched again; otherwise the nonce is reused by the next transaction. If
+    /// it was consumed after all, that transaction fails with a nonce error.
+    fn release(&mut self, nonce: u64, nonce_error: bool) {
+        if nonce_error {
+            self.next_nonce = None;
+            self.released.clear();
+        } else if self.next_nonce.is_some() {
+            self.released.insert(nonce);
+        }
+    }
+}
+
+#[derive(Debug)]
+struct PendingTx {
+    /// The transaction as last sent
+    tx: TransactionRequest,
+    /// Hashes of every version of the transaction that was sent
+    hashes: Vec<TxHash>,
+    /// The hash of the cancellation, if the transaction was cancelled
+    cancel_hash: Option<TxHash>,
+    /// The block at which the transaction was first sent
+    first_block: u64,
+    /// The block at which the last version of the transaction was sent
+    last_block: u64,
+    bumps: u32,
+    /// The highest max fee the transaction can be replaced with
+    max_fee_cap: Option<u128>,
+    resolution: Option<oneshot::Sender<TxResolution>>,
+}
+
+/// What the manager decided to do with a pending transaction.
+enum Decision {
+    Resolve(TxResolution),
+    Replace(TransactionRequest),
+    Cancel(TransactionRequest),
+}
+
+/// TxManager sends transactions on behalf of executors, so that executors
+/// don't block until their transactions are mined.
+///
+/// Nonces are allocated locally for each wallet, which allows several
+/// transactions to be in flight at once. Transactions are signed by the
+/// manager, which tracks them until they are mined:
+/// - Transactions pending for too long are replaced with higher fees
+/// - Stale transactions are cancelled with a zero-value self-transfer
+/// - Receipts are delivered asynchronously through a [TxHandle]
+///
+/// Pending transactions are only checked once the manager is [spawned](TxManager::spawn).
+pub struct TxManager<P: Provider> {
+    client: Arc<P>,
+    wallet: EthereumWallet,
+    config: TxManagerConfig,
+    wallets: Mutex<HashMap<Address, WalletState>>,
+}
+
+impl<P: Provider> TxManager<P> {
+    pub fn new(client: Arc<P>, wallet: EthereumWallet) -> Self {
+        Self {
+            client,
+            wallet,
+            config: TxManagerConfig::default(),
+            wallets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Sets the configuration of the manager.
+    pub fn with_config(mut self, config: TxManagerConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Returns the number of transactions waiting to be mined for `wallet`.
+    pub fn pending_count(&self, wallet: Address) -> usize {
+        let wallets = self.wallets.lock().unwrap();
+        wallets.get(&wallet).map_or(0, |state| state.pending.len())
+    }
+
+    /// Signs and sends a transaction with the next nonce of its sender. The
+    /// sender defaults to the default signer of the wallet, and missing gas
+    /// limit and fees are filled in.
+    pub async fn send(&self, tx: TransactionRequest) -> Result<TxHandle> {
+        self.send_with_fee_cap(tx, None).await
+    }
+
+    /// Sends a transaction like [TxManager::send], but never replaces it with
+    /// a max fee (or gas price) above `max_fee_cap`, typically the break-even
+    /// gas price of the opportunity. Bumps above the cap are skipped, and the
+    /// transaction is left to be mined or cancelled.
+    pub async fn send_with_fee_cap(
+        &self,
+        mut tx: TransactionRequest,
+        max_fee_cap: Option<u128>,
+    ) -> Result<TxHandle> {
+        let from = tx
+            .from
+            .unwrap_or_else(|| NetworkWallet::<Ethereum>::default_signer_address(&self.wallet));
+        tx.from = Some(from);
+        if tx.chain_id.is_none() {
+            tx.chain_id = Some(self.client.get_chain_id().await?);
+        }
+        if tx.gas.is_none() {
+            tx.gas = Some(self.client.estimate_gas(&tx).await?);
+        }
+        if tx.gas_price.is_none() && tx.max_fee_per_gas.is_none() {
+            let fees = self.client.estimate_eip1559_fees(None).await?;
+            tx.max_fee_per_gas = Some(fees.max_fee_per_gas);
+            tx.max_priority_fee_per_gas = Some(fees.max_priority_fee_per_gas);
+        }
+
+        let block = self.client.get_block_number().await?;
+        let nonce = self.allocate_nonce(from).await?;
+        tx.nonce = Some(nonce);
+
+        let tx_hash = match self.sign_and_send(&tx).await {
+            Ok(tx_hash) => tx_hash,
+            Err(e) => {
+                self.wallets
+                    .lock()
+                    .unwrap()
+                    .entry(from)
+                    .or_default()
+                    .release(nonce, is_nonce_error(&e));
+                return Err(e);
+            }
+        };
+        debug!("Sent transaction {} with nonce {}", tx_hash, nonce);
+
+        let (sender, resolution) = oneshot::channel();
+        let pending = PendingTx {
+            tx,
+            hashes: vec![tx_hash],
+            cancel_hash: None,
+            first_block: block,
+            last_block: block,
+            bumps: 0,
+            max_fee_cap,
+            resolution: Some(sender),
+        };
+        let mut wallets = self.wallets.lock().unwrap();
+        wallets
+            .entry(from)
+            .or_default()
+            .pending
+            .insert(nonce, pending);
+
+        Ok(TxHandle {
+            nonce,
+            tx_hash,
+            resolution,
+        })
+    }
+
+    /// Checks every pending transaction once: resolves mined transactions,
+    /// and replaces or cancels transactions that are pending for too long.
+    pub async fn poll(&self) -> Result<()> {
+        let block = self.client.get_block_number().await?;
+        let pending: Vec<(Address, u64)> = {
+            let wallets = self.wallets.lock().unwrap();
+            wallets
+                .iter()
+                .flat_map(|(from, state)| state.pending.keys().map(|nonce| (*from, *nonce)))
+                .collect()
+        };
+
+        let mut account_nonces = HashMap::new();
+        for (from, nonce) in pending {
+            let account_nonce = match account_nonces.get(&from) {
+                Some(account_nonce) => *account_nonce,
+                None => {
+                    let account_nonce = self.client.get_transaction_count(from).latest().await?;
+                    account_nonces.insert(from, account_nonce);
+                    account_nonce
+                }
+            };
+
+            let decision = match self.decide(from, nonce, block, account_nonce).await {
+                Ok(Some(decision)) => decision,
+                Ok(None) => continue,
+                Err(e) => {
+                    warn!("Failed to check transaction with nonce {}: {}", nonce, e);
+                    continue;
+                }
+            };
+            match decision {
+                Decision::Resolve(resolution) => self.resolve(from, nonce, resolution),
+                Decision::Replace(tx) => match self.sign_and_send(&tx).await {
+                    Ok(tx_hash) => {
+                        info!("Replaced transaction with nonce {}: {}", nonce, tx_hash);
+                        self.update(from, nonce, |pending| {
+                            if pending.cancel_hash.is_some() {
+                                // the cancellation itself was replaced
+                                pending.cancel_hash = Some(tx_hash);
+                            }
+                            pending.tx = tx;
+                            pending.hashes.push(tx_hash);
+                            pending.last_block = block;
+                            pending.bumps += 1;
+                        });
+                    }
+                    Err(e) => warn!("Failed to replace transaction with nonce {}: {}", nonce, e),
+                },
+                Decision::Cancel(tx) => match self.sign_and_send(&tx).await {
+                    Ok(tx_hash) => {
+                        info!("Cancelling transaction with nonce {}: {}", nonce, tx_hash);
+                        self.update(from, nonce, |pending| {
+                            pending.tx = tx;
+                            pending.hashes.push(tx_hash);
+                            pending.cancel_hash = Some(tx_hash);
+                            pending.last_block = block;
+                        });
+                    }
+                    Err(e) => warn!("Failed to cancel transaction with nonce {}: {}", nonce, e),
+                },
+            }
+        }
+        Ok(())
+    }
+
+    /// Spawns a task polling pending transactions until the manager is dropped.
+    pub fn spawn(self: &Arc<Self>) -> JoinHandle<()>
+    where
+        P: 'static,
+    {
+        let manager = Arc::downgrade(self);
+        let mut ticker = tokio::time::interval(self.config.poll_interval);
+        tokio::spawn(async move {
+            loop {
+                ticker.tick().await;
+                let Some(manager) = manager.upgrade() else {
+                    break;
+                };
+                if let Err(e) = manager.poll().await {
+                    warn!("Failed to poll pending transactions: {}", e);
+                }
+            }
+        })
+    }
+
+    async fn allocate_nonce(&self, from: Address) -> Result<u64> {
+        let cached = {
+            let wallets = self.wallets.lock().unwrap();
+            wallets.get(&from).is_some_and(WalletState::has_nonce)
+        };
+        let fetched = if cached {
+            None
+        } else {
+            Some(self.client.get_transaction_count(from).pending().await?)
+        };
+
+        // another send may have fetched the nonce in the meantime
+        let mut wallets = self.wallets.lock().unwrap();
+        Ok(wallets.entry(from).or_default().allocate(fetched))
+    }
+
+    async fn sign_and_send(&self, tx: &TransactionRequest) -> Result<TxHash> {
+        let envelope = tx.clone().build(&self.wallet).await?;
+        let pending_tx = self
+            .client
+            .send_raw_transaction(&envelope.encoded_2718())
+            .await?;
+        Ok(*pending_tx.tx_hash())
+    }
+
+    /// Decides what to do with a pending transaction, or returns None to keep waiting.
+    async fn decide(
+        &self,
+        from: Address,
+        nonce: u64,
+        block: u64,
+        account_nonce: u64,
+    ) -> Result<Option<Decision>> {
+        let Some((tx, hashes, cancel_hash, first_block, last_block, bumps, max_fee_cap)) = self
+            .inspect(from, nonce, |pending| {
+                (
+                    pending.tx.clone(),
+                    pending.hashes.clone(),
+                    pending.cancel_hash,
+                    pending.first_block,
+                    pending.last_block,
+                    pending.bumps,
+                    pending.max_fee_cap,
+                )
+            })
+        else {
+            return Ok(None);
+        };
+
+        if account_nonce > nonce {
+            for hash in hashes.iter().rev() {
+                if let Some(receipt) = self.client.get_transaction_receipt(*hash).await? {
+                    let resolution = if Some(receipt.transaction_hash) == cancel_hash {
+                        TxResolution::Cancelled(receipt)
+                    } else {
+                        TxResolution::Mined(receipt)
+                    };
+                    return Ok(Some(Decision::Resolve(resolution)));
+                }
+            }
+            return Ok(Some(Decision::Resolve(TxResolution::Dropped(format!(
+                "nonce {} was used by another transaction",
+                nonce
+            )))));
+        }
+
+        let stale = self
+            .config
+            .cancel_after_blocks
+            .is_some_and(|blocks| block >= first_block + blocks);
+        if stale && cancel_hash.is_none() {
+            let mut cancel = TransactionRequest::default()
+                .with_from(from)
+                .with_to(from)
+                .with_value(U256::ZERO)
+                .with_nonce(nonce)
+                .with_gas_limit(21_000);
+            cancel.chain_id = tx.chain_id;
+            cancel.gas_price = tx.gas_price;
+            cancel.max_fee_per_gas = tx.max_fee_per_gas;
+            cancel.max_priority_fee_per_gas = tx.max_priority_fee_per_gas;
+            bump_fees(&mut cancel, self.config.bump_percent);
+            return Ok(Some(Decision::Cancel(cancel)));
+        }
+
+        let due = block >= last_block + self.config.bump_after_blocks;
+        if due && bumps < self.config.max_bumps {
+            let mut replacement = tx;
+            bump_fees(&mut replacement, self.config.bump_percent);
+            // cancellations are not paid for by the transaction's profit
+            if cancel_hash.is_none() && !within_fee_cap(&replacement, max_fee_cap) {
+                debug!(
+                    "Not bumping transaction with nonce {} above its fee cap",
+                    nonce
+                );
+                return Ok(None);
+            }
+            return Ok(Some(Decision::Replace(replacement)));
+        }
+        Ok(None)
+    }
+
+    fn inspect<T>(&self, from: Address, nonce: u64, f: impl FnOnce(&PendingTx) -> T) -> Option<T> {
+        let wallets = self.wallets.lock().unwrap();
+        wallets.get(&from)?.pending.get(&nonce).map(f)
+    }
+
+    fn update(&self, from: Address, nonce: u64, f: impl FnOnce(&mut PendingTx)) {
+        let mut wallets = self.wallets.lock().unwrap();
+        if let Some(pending) = wallets
+            .get_mut(&from)
+            .and_then(|state| state.pending.get_mut(&nonce))
+        {
+            f(pending);
+        }
+    }
+
+    fn resolve(&self, from: Address, nonce: u64, resolution: TxResolution) {
+        let pending = {
+            let mut wallets = self.wallets.lock().unwrap();
+            wallets
+                .get_mut(&from)
+                .and_then(|state| state.pending.remove(&nonce))
+        };
+        if let Some(sender) = pending.and_then(|mut pending| pending.resolution.take()) {
+            // the handle may have been dropped
+            let _ = sender.send(resolution);
+        }
+    }
+}
+
+/// Increases the fees of a transaction by `percent`, rounding up so that
+/// small fees still increase.
+fn bump_fees(tx: &mut TransactionRequest, percent: u64) {
+    let bump =
+        |fee: u128| fee.saturating_add(fee.saturating_mul(u128::from(percent)).div_ceil(100));
+    tx.gas_price = tx.gas_price.map(bump);
+    tx.max_fee_per_gas = tx.max_fee_per_gas.map(bump);
+    tx.max_priority_fee_per_gas = tx.max_priority_fee_per_gas.map(bump);
+}
+
+/// Returns true if the max fee, or gas price, of a transaction is at most
+/// `max_fee_cap`, or if there is no cap.
+fn within_fee_cap(tx: &TransactionRequest, max_fee_cap: Option<u128>) -> bool {
+    let Some(max_fee_cap) = max_fee_cap else {
+        return true;
+    };
+    tx.max_fee_per_gas
+        .or(tx.gas_price)
+        .is_none_or(|fee| fee <= max_fee_cap)
+}
+
+/// Returns true if the node rejected a transaction because of its nonce: the
+/// nonce was already used, is too far ahead, or is taken by a pending transaction.
+fn is_nonce_error(error: &eyre::Report) -> bool {
+    error.chain().any(|cause| {
+        let message = cause.to_string().to_lowercase();
+        [
+            "nonce",
+            "already known",
+            "replacement transaction underpriced",
+        ]
+        .iter()
+        .any(|pattern| message.contains(pattern))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bump_fees() {
+        let mut legacy = TransactionRequest::default().with_gas_price(100);
+        bump_fees(&mut legacy, 15);
+        assert_eq!(legacy.gas_price, Some(115));
+
+        let mut eip1559 = TransactionRequest::default()
+            .with_max_fee_per_gas(1_000)
+            .with_max_priority_fee_per_gas(1);
+        bump_fees(&mut eip1559, 10);
+        assert_eq!(eip1559.max_fee_per_gas, Some(1_100));
+        assert_eq!(eip1559.max_priority_fee_per_gas, Some(2));
+        assert_eq!(eip1559.gas_price, None);
+
+        let mut max = TransactionRequest::default().with_gas_price(u128::MAX);
+        bump_fees(&mut max, 15);
+        assert_eq!(max.gas_price, Some(u128::MAX));
+    }
+
+    #[test]
+    fn test_within_fee_cap() {
+        let mut tx = TransactionRequest::default().with_max_fee_per_gas(1_000);
+        assert!(within_fee_cap(&tx, None));
+        assert!(within_fee_cap(&tx, Some(1_000)));
+
+        bump_fees(&mut tx, 15);
+        assert!(!within_fee_cap(&tx, Some(1_000)));
+        assert!(!within_fee_cap(
+            &TransactionRequest::default().with_gas_price(1_001),
+            Some(1_000)
+        ));
+    }
+
+    #[test]
+    fn test_failed_sends_release_their_nonce() {
+        let mut state = WalletState::default();
+        assert!(!state.has_nonce());
+        assert_eq!(state.allocate(Some(5)), 5);
+        assert_eq!(state.allocate(None), 6);
+        assert_eq!(state.allocate(None), 7);
+
+        // the nonces of transactions rejected for another reason are reused first
+        state.release(6, false);
+        state.release(5, false);
+        assert_eq!(state.allocate(None), 5);
+        assert_eq!(state.allocate(None), 6);
+        assert_eq!(state.allocate(None), 8);
+
+        // nonce errors resync with the node
+        state.release(8, false);
+        state.release(7, true);
+        assert!(!state.has_nonce());
+        assert_eq!(state.allocate(Some(7)), 7);
+    }
+
+    #[test]
+    fn test_nonce_errors() {
+        assert!(is_nonce_error(&eyre::eyre!("nonce too low")));
+        assert!(is_nonce_error(
+            &eyre::eyre!("replacement transaction underpriced").wrap_err("failed to send")
+        ));
+        assert!(is_nonce_error(&eyre::eyre!("already known")));
+        assert!(!is_nonce_error(&eyre::eyre!(
+            "insufficient funds for gas * price + value"
+        )));
+    }
+}
@@ -26,12 +26,36 @@
 //! These components are tied together by the [Engine](engine::Engine), which is responsible for
 //! orchestrating the flow of data between them.
 
+/// This module contains the [Backoff](backoff::Backoff) policy used when
+/// resubscribing or restarting components.
+pub mod backoff;
+/// This module contains the [Chained](chain::Chained) wrapper tagging events and
+/// actions with their chain, used to run components for several chains in one engine.
+pub mod chain;
+/// This module contains the [CheckpointStore](checkpoint::CheckpointStore) persisting
+/// the last block processed by each strategy.
+pub mod checkpoint;
 /// This module contains [collector](types::Collector) implementations.
 pub mod collectors;
+/// This module contains the per-subscriber queues and [delivery policies](delivery::DeliveryPolicy)
+/// used to pass events and actions between components.
+pub mod delivery;
 /// This module contains the [Engine](engine::Engine) struct, which is responsible
 /// for orchestrating data flows between components
 pub mod engine;
 /// This module contains [executor](types::Executor) implementations.
 pub mod executors;
+/// This module contains the metrics recorded by the engine and the built-in
+/// components. Metrics are only exported if a recorder is installed.
+pub mod metrics;
+/// This module contains the [outcomes](outcome::ActionOutcome) reported by executors
+/// and routed back to the strategies that emitted the actions.
+pub mod outcome;
+/// This module contains the supervision primitives used by the [Engine](engine::Engine):
+/// restart policies, exit reports and shutdown signal handling.
+pub mod supervisor;
+/// This module contains the [SyncPolicy](sync::SyncPolicy) deciding when strategies
+/// sync their state.
+pub mod sync;
 /// This module contains the core type definitions for Artemis.
 pub mod types;
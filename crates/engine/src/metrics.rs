@@ -0,0 +1,127 @@
+use ::metrics::{counter, describe_counter, describe_gauge, describe_histogram, gauge, histogram};
+use alloy::rpc::types::TransactionReceipt;
+use std::time::Duration;
+
+use crate::delivery::LagCounters;
+use crate::supervisor::TaskKind;
+
+pub const COLLECTOR_EVENTS: &str = "engine_collector_events_total";
+pub const STRATEGY_PROCESS_EVENT_SECONDS: &str = "engine_strategy_process_event_seconds";
+pub const STRATEGY_ACTIONS: &str = "engine_strategy_actions_total";
+pub const STRATEGY_LAGGED_EVENTS: &str = "engine_strategy_lagged_events_total";
+pub const STRATEGY_SYNCS: &str = "engine_strategy_syncs_total";
+pub const STRATEGY_SYNC_SECONDS: &str = "engine_strategy_sync_seconds";
+pub const TASK_RESTARTS: &str = "engine_task_restarts_total";
+pub const QUEUE_DEPTH: &str = "engine_queue_depth";
+pub const QUEUE_DROPPED: &str = "engine_queue_dropped_total";
+pub const QUEUE_COALESCED: &str = "engine_queue_coalesced_total";
+pub const QUEUE_BLOCKED: &str = "engine_queue_blocked_total";
+pub const EXECUTOR_GAS_ESTIMATION_FAILURES: &str = "executor_gas_estimation_failures_total";
+pub const EXECUTOR_TXS_SUBMITTED: &str = "executor_txs_submitted_total";
+pub const EXECUTOR_TXS_REVERTED: &str = "executor_txs_reverted_total";
+pub const EXECUTOR_GAS_SPENT_GWEI: &str = "executor_gas_spent_gwei_total";
+
+/// Registers the description of every engine metric with the installed recorder.
+/// Call this once after installing a recorder (for example, a Prometheus exporter).
+pub fn describe() {
+    describe_counter!(COLLECTOR_EVENTS, "Events emitted by each collector");
+    describe_histogram!(
+        STRATEGY_PROCESS_EVENT_SECONDS,
+        "Time spent by each strategy processing a single event"
+    );
+    describe_counter!(STRATEGY_ACTIONS, "Actions emitted by each strategy");
+    describe_counter!(
+        STRATEGY_LAGGED_EVENTS,
+        "Events dropped because a strategy fell behind"
+    );
+    describe_counter!(STRATEGY_SYNCS, "State syncs run by each strategy");
+    describe_histogram!(STRATEGY_SYNC_SECONDS, "Time spent syncing strategy state");
+    describe_counter!(TASK_RESTARTS, "Restarts of supervised tasks");
+    describe_gauge!(QUEUE_DEPTH, "Items queued for each strategy and executor");
+    describe_counter!(QUEUE_DROPPED, "Items dropped from a full queue");
+    describe_counter!(
+        QUEUE_COALESCED,
+        "Items replaced by a newer item with the same key"
+    );
+    describe_counter!(QUEUE_BLOCKED, "Sends that waited for room in a full queue");
+    describe_counter!(
+        EXECUTOR_GAS_ESTIMATION_FAILURES,
+        "Actions dropped because gas estimation failed"
+    );
+    describe_counter!(
+        EXECUTOR_TXS_SUBMITTED,
+        "Transactions submitted by each executor"
+    );
+    describe_counter!(
+        EXECUTOR_TXS_REVERTED,
+        "Submitted transactions that reverted"
+    );
+    describe_counter!(
+        EXECUTOR_GAS_SPENT_GWEI,
+        "Gas fees paid by mined transactions, in gwei"
+    );
+}
+
+pub(crate) fn collector_event(collector: &'static str) {
+    counter!(COLLECTOR_EVENTS, "collector" => collector).increment(1);
+}
+
+pub(crate) fn event_processed(strategy: &'static str, elapsed: Duration, actions: usize) {
+    histogram!(STRATEGY_PROCESS_EVENT_SECONDS, "strategy" => strategy).record(elapsed);
+    counter!(STRATEGY_ACTIONS, "strategy" => strategy).increment(actions as u64);
+}
+
+pub(crate) fn strategy_lagged(strategy: &'static str, missed: u64) {
+    counter!(STRATEGY_LAGGED_EVENTS, "strategy" => strategy).increment(missed);
+}
+
+pub(crate) fn strategy_synced(strategy: &'static str, elapsed: Duration, success: bool) {
+    let status = if success { "ok" } else { "error" };
+    counter!(STRATEGY_SYNCS, "strategy" => strategy, "status" => status).increment(1);
+    histogram!(STRATEGY_SYNC_SECONDS, "strategy" => strategy).record(elapsed);
+}
+
+pub(crate) fn task_restarted(task: &'static str, kind: TaskKind) {
+    counter!(TASK_RESTARTS, "task" => task, "kind" => kind.as_str()).increment(1);
+}
+
+pub(crate) fn queue_depth(queue: &LagCounters, depth: usize) {
+    gauge!(QUEUE_DEPTH, "subscriber" => queue.name(), "kind" => queue.kind().as_str())
+        .set(depth as f64);
+}
+
+pub(crate) fn queue_dropped(queue: &LagCounters) {
+    counter!(QUEUE_DROPPED, "subscriber" => queue.name(), "kind" => queue.kind().as_str())
+        .increment(1);
+}
+
+pub(crate) fn queue_coalesced(queue: &LagCounters) {
+    counter!(QUEUE_COALESCED, "subscriber" => queue.name(), "kind" => queue.kind().as_str())
+        .increment(1);
+}
+
+pub(crate) fn queue_blocked(queue: &LagCounters) {
+    counter!(QUEUE_BLOCKED, "subscriber" => queue.name(), "kind" => queue.kind().as_str())
+        .increment(1);
+}
+
+/// Records an action that was dropped because gas estimation failed.
+pub fn gas_estimation_failed(executor: &'static str) {
+    counter!(EXECUTOR_GAS_ESTIMATION_FAILURES, "executor" => executor).increment(1);
+}
+
+/// Records a transaction submitted by an executor.
+pub fn tx_submitted(executor: &'static str) {
+    counter!(EXECUTOR_TXS_SUBMITTED, "executor" => executor).increment(1);
+}
+
+/// Records the receipt of a transaction submitted by an executor: whether it
+/// reverted, and the gas fees it paid.
+pub fn tx_mined(executor: &'static str, receipt: &TransactionReceipt) {
+    if !receipt.status() {
+        counter!(EXECUTOR_TXS_REVERTED, "executor" => executor).increment(1);
+    }
+    let fee_wei = u128::from(receipt.gas_used).saturating_mul(receipt.effective_gas_price);
+    let fee_gwei = u64::try_from(fee_wei / 1_000_000_000).unwrap_or(u64::MAX);
+    counter!(EXECUTOR_GAS_SPENT_GWEI, "executor" => executor).increment(fee_gwei);
+}
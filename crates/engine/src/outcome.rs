@@ -0,0 +1,107 @@
+use alloy::{primitives::TxHash, rpc::types::TransactionReceipt};
+use std::fmt;
+use std::sync::Arc;
+
+/// Identifier assigned by the [Engine](crate::engine::Engine) to every action
+/// emitted by a strategy. Ids are unique within an engine.
+pub type ActionId = u64;
+
+/// What happened to an action, as reported by the executor handling it.
+/// An executor can report several statuses for the same action, for example
+/// [Submitted](ActionStatus::Submitted) followed by [Mined](ActionStatus::Mined).
+#[derive(Debug, Clone)]
+pub enum ActionStatus {
+    /// The executor did not submit the action, for example because gas estimation failed
+    Dropped { reason: String },
+    /// The transaction was sent
+    Submitted { tx_hash: TxHash },
+    /// The transaction was mined successfully
+    Mined { receipt: TransactionReceipt },
+    /// The transaction was mined but reverted
+    Reverted {
+        receipt: TransactionReceipt,
+        /// The revert reason, if the executor could recover it
+        reason: Option<String>,
+    },
+    /// The action failed with an error
+    Failed { reason: String },
+    /// The action was executed by an executor that does not report detailed outcomes
+    Executed,
+}
+
+impl ActionStatus {
+    /// Returns the hash of the transaction the status refers to, if any.
+    pub fn tx_hash(&self) -> Option<TxHash> {
+        match self {
+            ActionStatus::Submitted { tx_hash } => Some(*tx_hash),
+            ActionStatus::Mined { receipt } | ActionStatus::Reverted { receipt, .. } => {
+                Some(receipt.transaction_hash)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the receipt of the mined transaction, if any.
+    pub fn receipt(&self) -> Option<&TransactionReceipt> {
+        match self {
+            ActionStatus::Mined { receipt } | ActionStatus::Reverted { receipt, .. } => {
+                Some(receipt)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the gas used by the mined transaction, if any.
+    pub fn gas_used(&self) -> Option<u64> {
+        self.receipt().map(|receipt| receipt.gas_used)
+    }
+
+    /// Returns true if no further status will be reported for the action.
+    pub fn is_final(&self) -> bool {
+        !matches!(self, ActionStatus::Submitted { .. })
+    }
+}
+
+/// The outcome of an action, routed back to the strategy that emitted it.
+#[derive(Debug, Clone)]
+pub struct ActionOutcome<A> {
+    /// The id assigned to the action by the engine
+    pub id: ActionId,
+    /// The action, as emitted by the strategy
+    pub action: A,
+    /// The name of the executor that handled the action
+    pub executor: &'static str,
+    /// What happened to the action
+    pub status: ActionStatus,
+}
+
+/// Handle given to executors to report what happened to the action they execute.
+/// The engine routes every report back to the strategy that emitted the action.
+#[derive(Clone)]
+pub struct OutcomeReporter {
+    report: Arc<dyn Fn(ActionStatus) + Send + Sync>,
+}
+
+impl OutcomeReporter {
+    pub fn new(report: impl Fn(ActionStatus) + Send + Sync + 'static) -> Self {
+        Self {
+            report: Arc::new(report),
+        }
+    }
+
+    /// Returns a reporter that discards every report.
+    pub fn noop() -> Self {
+        Self::new(|_| {})
+    }
+
+    /// Reports a status for the action.
+    pub fn report(&self, status: ActionStatus) {
+        (self.report)(status)
+    }
+}
+
+impl fmt::Debug for OutcomeReporter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OutcomeReporter").finish_non_exhaustive()
+    }
+}
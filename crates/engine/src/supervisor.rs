@@ -0,0 +1,185 @@
+use crate::backoff::Backoff;
+use std::any::Any;
+use std::fmt;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+/// Policy applied by the [Engine](crate::engine::Engine) when a collector, strategy
+/// or executor fails (returns an error it cannot recover from, or panics).
+#[derive(Debug, Clone)]
+pub enum RestartPolicy {
+    /// Never restart a failed component
+    Never,
+    /// Restart a failed component, waiting according to the given backoff.
+    /// The backoff's `max_retries` bounds the number of restarts.
+    OnFailure(Backoff),
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        RestartPolicy::OnFailure(Backoff::default().with_max_retries(Some(10)))
+    }
+}
+
+impl RestartPolicy {
+    /// Returns true if a component that was already restarted `restarts` times
+    /// may be restarted again.
+    pub fn can_restart(&self, restarts: usize) -> bool {
+        match self {
+            RestartPolicy::Never => false,
+            RestartPolicy::OnFailure(backoff) => backoff.can_retry(restarts),
+        }
+    }
+
+    /// Returns the delay to wait before the next restart.
+    pub fn delay(&self, restarts: usize) -> Duration {
+        match self {
+            RestartPolicy::Never => Duration::ZERO,
+            RestartPolicy::OnFailure(backoff) => backoff.delay(restarts),
+        }
+    }
+}
+
+/// The kind of component a supervised task is running.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskKind {
+    Collector,
+    Strategy,
+    Executor,
+}
+
+impl TaskKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TaskKind::Collector => "collector",
+            TaskKind::Strategy => "strategy",
+            TaskKind::Executor => "executor",
+        }
+    }
+}
+
+impl fmt::Display for TaskKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// How a supervised task ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskOutcome {
+    /// The task ran to completion (for example, its input channel was closed)
+    Completed,
+    /// The task was stopped by the shutdown signal
+    Cancelled,
+    /// The task was aborted because it did not finish within the drain timeout
+    Aborted,
+    /// The task failed and exhausted its restart policy
+    Failed(String),
+}
+
+/// Report for a single supervised task.
+#[derive(Debug, Clone)]
+pub struct TaskReport {
+    /// The name of the component run by the task
+    pub name: String,
+    /// The kind of component run by the task
+    pub kind: TaskKind,
+    /// The number of times the component was restarted
+    pub restarts: usize,
+    /// How the task ended
+    pub outcome: TaskOutcome,
+}
+
+/// Structured report returned by [Engine::run](crate::engine::Engine::run) once
+/// every task has stopped.
+#[derive(Debug, Clone, Default)]
+pub struct ExitReport {
+    /// The reports of every supervised task, in the order they stopped
+    pub tasks: Vec<TaskReport>,
+    /// Whether in-flight actions were fully drained before the drain timeout
+    pub drained: bool,
+}
+
+impl ExitReport {
+    /// Returns the tasks that failed or had to be aborted.
+    pub fn failures(&self) -> impl Iterator<Item = &TaskReport> {
+        self.tasks
+            .iter()
+            .filter(|task| matches!(task.outcome, TaskOutcome::Failed(_) | TaskOutcome::Aborted))
+    }
+
+    /// Returns true if no task failed and all in-flight actions were drained.
+    pub fn is_clean(&self) -> bool {
+        self.drained && self.failures().next().is_none()
+    }
+}
+
+impl fmt::Display for ExitReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Engine stopped ({} tasks, drained: {})",
+            self.tasks.len(),
+            self.drained
+        )?;
+        for task in &self.tasks {
+            writeln!(
+                f,
+                "  {} {}: {:?} ({} restarts)",
+                task.kind, task.name, task.outcome, task.restarts
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Waits for SIGINT or SIGTERM.
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            warn!("Failed to listen for ctrl-c: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                warn!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => info!("Received SIGINT"),
+        _ = terminate => info!("Received SIGTERM"),
+    }
+}
+
+/// Spawns a task that cancels `token` when SIGINT or SIGTERM is received.
+pub fn cancel_on_shutdown_signal(token: CancellationToken) {
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        token.cancel();
+    });
+}
+
+/// Formats the payload of a caught panic.
+pub(crate) fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        format!("panicked: {}", message)
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        format!("panicked: {}", message)
+    } else {
+        "panicked".to_string()
+    }
+}
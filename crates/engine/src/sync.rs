@@ -0,0 +1,246 @@
+use alloy_chains::NamedChain;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::time::{interval_at, Instant, Interval, MissedTickBehavior};
+
+use crate::types::EngineEvent;
+
+/// Policy describing when the [Engine](crate::engine::Engine) calls
+/// [Strategy::sync_state](crate::types::Strategy::sync_state).
+///
+/// Syncs can be scheduled every `n` blocks, on a fixed time interval, after a
+/// collector restarted, and after a strategy lagged behind its event channel.
+/// A sync can also be requested at any time through a [SyncHandle].
+#[derive(Debug, Clone)]
+pub struct SyncPolicy {
+    /// Sync once the strategy has seen events this many blocks past the last
+    /// sync, on any of the chains it receives events from
+    pub every_blocks: Option<u64>,
+    /// Sync when this much time has passed since the last sync
+    pub every: Option<Duration>,
+    /// Sync after a collector reports that it restarted
+    pub on_collector_restart: bool,
+    /// Sync after the strategy lagged and missed events
+    pub on_lag: bool,
+}
+
+impl Default for SyncPolicy {
+    fn default() -> Self {
+        Self {
+            every_blocks: None,
+            every: None,
+            on_collector_restart: true,
+            on_lag: true,
+        }
+    }
+}
+
+impl SyncPolicy {
+    /// Syncs every `blocks` blocks.
+    pub fn with_block_interval(mut self, blocks: u64) -> Self {
+        self.every_blocks = Some(blocks);
+        self
+    }
+
+    /// Syncs every `interval`.
+    pub fn with_time_interval(mut self, interval: Duration) -> Self {
+        self.every = Some(interval);
+        self
+    }
+
+    /// Sets whether to sync after a collector restarted.
+    pub fn with_sync_on_collector_restart(mut self, enabled: bool) -> Self {
+        self.on_collector_restart = enabled;
+        self
+    }
+
+    /// Sets whether to sync after the strategy lagged.
+    pub fn with_sync_on_lag(mut self, enabled: bool) -> Self {
+        self.on_lag = enabled;
+        self
+    }
+}
+
+/// Handle used to request an on-demand sync of every strategy run by an engine.
+#[derive(Debug, Clone)]
+pub struct SyncHandle {
+    requests: Arc<watch::Sender<u64>>,
+}
+
+impl SyncHandle {
+    pub(crate) fn new(requests: Arc<watch::Sender<u64>>) -> Self {
+        Self { requests }
+    }
+
+    /// Requests a sync. Each strategy syncs once it is done with its current event.
+    pub fn request_sync(&self) {
+        self.requests.send_modify(|requests| *requests += 1);
+    }
+}
+
+/// Tracks when a single strategy is due for a sync.
+pub(crate) struct SyncScheduler {
+    policy: SyncPolicy,
+    requests: watch::Receiver<u64>,
+    ticker: Option<Interval>,
+    /// The highest block seen, per chain
+    last_blocks: HashMap<Option<NamedChain>, u64>,
+    /// The highest block seen at the last sync, per chain
+    last_sync_blocks: HashMap<Option<NamedChain>, u64>,
+    pending: bool,
+}
+
+impl SyncScheduler {
+    pub(crate) fn new(policy: SyncPolicy, mut requests: watch::Receiver<u64>) -> Self {
+        // requests made before the strategy was initialized are already satisfied
+        requests.borrow_and_update();
+        let ticker = policy.every.map(|period| {
+            let mut ticker = interval_at(Instant::now() + period, period);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            ticker
+        });
+        Self {
+            policy,
+            requests,
+            ticker,
+            last_blocks: HashMap::new(),
+            last_sync_blocks: HashMap::new(),
+            pending: false,
+        }
+    }
+
+    /// Returns true if a sync is due.
+    pub(crate) fn is_pending(&self) -> bool {
+        self.pending
+    }
+
+    /// Resolves once a sync is due because of the time interval or an
+    /// on-demand request. Cancel safe.
+    pub(crate) async fn requested(&mut self) {
+        let ticker = &mut self.ticker;
+        let requests = &mut self.requests;
+        tokio::select! {
+            _ = async {
+                match ticker {
+                    Some(ticker) => ticker.tick().await,
+                    None => std::future::pending().await,
+                }
+            } => {}
+            res = requests.changed() => {
+                if res.is_err() {
+                    // every handle was dropped, no more requests can be made
+                    std::future::pending::<()>().await;
+                }
+            }
+        }
+        self.pending = true;
+    }
+
+    /// Records an event received by the strategy.
+    pub(crate) fn observe<E: EngineEvent>(&mut self, event: &E) {
+        if event.is_collector_restart() && self.policy.on_collector_restart {
+            self.pending = true;
+        }
+
+        let Some(block) = event.block_number() else {
+            return;
+        };
+        let chain = event.chain();
+        let last = self.last_blocks.entry(chain).or_insert(block);
+        *last = (*last).max(block);
+        match (self.last_sync_blocks.get(&chain), self.policy.every_blocks) {
+            (None, _) => {
+                self.last_sync_blocks.insert(chain, block);
+            }
+            (Some(&synced), Some(every)) if block >= synced.saturating_add(every) => {
+                self.pending = true
+            }
+            _ => {}
+        }
+    }
+
+    /// Records that the strategy missed events.
+    pub(crate) fn lagged(&mut self) {
+        if self.policy.on_lag {
+            self.pending = true;
+        }
+    }
+
+    /// Records the start of a sync. Requests made after this point trigger another sync.
+    pub(crate) fn syncing(&mut self) {
+        self.pending = false;
+        self.requests.borrow_and_update();
+    }
+
+    /// Records a completed sync.
+    pub(crate) fn synced(&mut self) {
+        self.last_sync_blocks = self.last_blocks.clone();
+        if let Some(ticker) = &mut self.ticker {
+            ticker.reset();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::Chained;
+
+    struct Block(u64);
+
+    impl EngineEvent for Block {
+        fn block_number(&self) -> Option<u64> {
+            Some(self.0)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_block_interval() {
+        let (_tx, rx) = watch::channel(0);
+        let mut scheduler = SyncScheduler::new(SyncPolicy::default().with_block_interval(10), rx);
+
+        scheduler.observe(&Block(100));
+        scheduler.observe(&Block(109));
+        assert!(!scheduler.is_pending());
+
+        scheduler.observe(&Block(110));
+        assert!(scheduler.is_pending());
+
+        scheduler.syncing();
+        scheduler.synced();
+        assert!(!scheduler.is_pending());
+        scheduler.observe(&Block(119));
+        assert!(!scheduler.is_pending());
+        scheduler.observe(&Block(120));
+        assert!(scheduler.is_pending());
+    }
+
+    #[tokio::test]
+    async fn test_block_interval_is_tracked_per_chain() {
+        let (_tx, rx) = watch::channel(0);
+        let mut scheduler = SyncScheduler::new(SyncPolicy::default().with_block_interval(10), rx);
+
+        scheduler.observe(&Chained::new(NamedChain::Mainnet, Block(100)));
+        scheduler.observe(&Chained::new(NamedChain::Base, Block(5000)));
+        scheduler.observe(&Chained::new(NamedChain::Mainnet, Block(109)));
+        assert!(!scheduler.is_pending());
+
+        scheduler.observe(&Chained::new(NamedChain::Base, Block(5010)));
+        assert!(scheduler.is_pending());
+    }
+
+    #[tokio::test]
+    async fn test_on_demand_request() {
+        let (tx, rx) = watch::channel(0);
+        let handle = SyncHandle::new(Arc::new(tx));
+        let mut scheduler = SyncScheduler::new(SyncPolicy::default(), rx);
+
+        handle.request_sync();
+        tokio::time::timeout(Duration::from_secs(1), scheduler.requested())
+            .await
+            .unwrap();
+        assert!(scheduler.is_pending());
+    }
+}
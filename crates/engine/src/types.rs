@@ -1,4 +1,5 @@
-use alloy::rpc::types::TransactionRequest;
+use alloy::{primitives::Address, rpc::types::TransactionRequest};
+use alloy_chains::NamedChain;
 use async_trait::async_trait;
 use eyre::Result;
 use std::pin::Pin;
@@ -7,11 +8,138 @@ use tokio_stream::StreamExt;
 
 use crate::collectors::block_collector::NewBlock;
 use crate::executors::mempool_executor::SubmitTxToMempool;
+use crate::outcome::{ActionOutcome, ActionStatus, OutcomeReporter};
 
 /// A stream of events emitted by a [Collector](Collector).
 /// This type alias represents a pinned, boxed stream that can send events across thread boundaries.
 pub type CollectorStream<'a, E> = Pin<Box<dyn Stream<Item = E> + Send + 'a>>;
 
+/// Emitted by a collector after its underlying subscription was re-established.
+/// Events between `last_block` and `resumed_block` have been backfilled when the
+/// collector supports it, but strategies should treat this as a possible gap.
+#[derive(Debug, Clone)]
+pub struct CollectorRestarted {
+    /// The name of the collector that was restarted
+    pub collector: &'static str,
+    /// The last block seen before the subscription dropped, if any
+    pub last_block: Option<u64>,
+    /// The chain head at the time the subscription was re-established, if known
+    pub resumed_block: Option<u64>,
+    /// The number of attempts it took to re-establish the subscription
+    pub attempts: usize,
+}
+
+/// Emitted by a collector when the chain reorganized: the events it emitted
+/// for blocks `from_block..=to_block` are no longer canonical. Events from the
+/// canonical branch follow, starting at `from_block`.
+#[derive(Debug, Clone)]
+pub struct Reorg {
+    /// The name of the collector that detected the reorg
+    pub collector: &'static str,
+    /// The first block replaced by the reorg, also known as the fork point
+    pub from_block: u64,
+    /// The last block of the abandoned branch seen by the collector
+    pub to_block: u64,
+}
+
+/// Wrapper for the events emitted by the built-in collectors.
+/// Besides the collected events themselves, collectors report changes to the
+/// health of their underlying stream so that strategies can react to them.
+#[derive(Debug, Clone)]
+pub enum CollectorEvent<T> {
+    /// A collected event
+    Event(T),
+    /// The collector's subscription dropped and was re-established
+    Restarted(CollectorRestarted),
+    /// The chain reorganized, invalidating events emitted for recent blocks
+    Reorg(Reorg),
+    /// Every event of this block was emitted, for collectors that order their
+    /// events by block. Strategies can use it to act once per block.
+    BlockEnd(u64),
+}
+
+impl<T> CollectorEvent<T> {
+    /// Returns the collected event, if any.
+    pub fn into_event(self) -> Option<T> {
+        match self {
+            CollectorEvent::Event(event) => Some(event),
+            _ => None,
+        }
+    }
+}
+
+/// Metadata the [Engine](crate::engine::Engine) reads from events to decide when
+/// strategies need to [sync their state](Strategy::sync_state).
+/// Every method has a default, so event types only implement what they can report.
+pub trait EngineEvent {
+    /// Returns the chain the event was collected on, if any. Events without a
+    /// chain are delivered to every strategy, whatever chains it subscribed to.
+    fn chain(&self) -> Option<NamedChain> {
+        None
+    }
+
+    /// Returns the block number the event belongs to, if any.
+    fn block_number(&self) -> Option<u64> {
+        None
+    }
+
+    /// Returns true if the event reports that a collector restarted, meaning
+    /// that events may have been missed.
+    fn is_collector_restart(&self) -> bool {
+        false
+    }
+
+    /// Returns the key used to coalesce queued events for strategies subscribed
+    /// with [DeliveryPolicy::Coalesce](crate::delivery::DeliveryPolicy::Coalesce),
+    /// typically the address of the pool whose state the event describes.
+    fn coalesce_key(&self) -> Option<Address> {
+        None
+    }
+
+    /// Returns true if the event controls how the strategy interprets the
+    /// events around it, such as reorgs, collector restarts and block ends.
+    /// Control events are never dropped when the strategy falls behind.
+    fn is_control(&self) -> bool {
+        self.is_collector_restart()
+    }
+}
+
+impl<T: EngineEvent> EngineEvent for CollectorEvent<T> {
+    fn chain(&self) -> Option<NamedChain> {
+        match self {
+            CollectorEvent::Event(event) => event.chain(),
+            _ => None,
+        }
+    }
+
+    fn block_number(&self) -> Option<u64> {
+        match self {
+            CollectorEvent::Event(event) => event.block_number(),
+            CollectorEvent::Restarted(restarted) => restarted.resumed_block,
+            CollectorEvent::Reorg(_) => None,
+            CollectorEvent::BlockEnd(block) => Some(*block),
+        }
+    }
+
+    fn is_collector_restart(&self) -> bool {
+        matches!(self, CollectorEvent::Restarted(_))
+    }
+
+    fn coalesce_key(&self) -> Option<Address> {
+        match self {
+            CollectorEvent::Event(event) => event.coalesce_key(),
+            _ => None,
+        }
+    }
+
+    fn is_control(&self) -> bool {
+        match self {
+            CollectorEvent::Event(event) => event.is_control(),
+            _ => true,
+        }
+    }
+}
+
 /// Collector trait defines a source of events in the Artemis framework.
 /// Collectors are responsible for gathering events from external sources such as:
 /// - Blockchain events (new blocks, pending transactions)
@@ -23,6 +151,11 @@ pub trait Collector<E>: Send + Sync {
     /// Returns the core event stream for the collector.
     /// This stream will emit events of type E that can be processed by strategies.
     async fn get_event_stream(&self) -> Result<CollectorStream<'_, E>>;
+
+    /// Returns the name of the collector, used in logs and exit reports.
+    fn name(&self) -> &'static str {
+        short_type_name::<Self>()
+    }
 }
 
 /// Strategy trait defines the core logic for identifying and acting on MEV opportunities.
@@ -42,8 +175,9 @@ pub trait Strategy<E, A>: Send + Sync {
     async fn init_state(&mut self) -> Result<()>;
 
     /// Synchronizes the strategy's state with current conditions.
-    /// This can be called periodically to ensure the strategy's state
-    /// remains consistent with external conditions.
+    /// The engine calls this according to its [SyncPolicy](crate::sync::SyncPolicy)
+    /// to ensure the strategy's state remains consistent with external conditions.
+    /// Events received while syncing are queued and processed afterwards.
     async fn sync_state(&mut self) -> Result<()>;
 
     /// Processes an incoming event and generates appropriate actions.
@@ -53,6 +187,21 @@ pub trait Strategy<E, A>: Send + Sync {
     /// Returns a vector of actions to be executed. An empty vector
     /// indicates no action needs to be taken for this event.
     async fn process_event(&mut self, event: E) -> Vec<A>;
+
+    /// Called when the strategy fell behind and `missed` events were dropped
+    /// from its queue. The engine also resyncs the strategy if its
+    /// [SyncPolicy](crate::sync::SyncPolicy) says so.
+    fn on_lagged(&mut self, _missed: u64) {}
+
+    /// Called with every status reported by the executor handling an action
+    /// emitted by this strategy, such as the transaction hash once it was
+    /// submitted, its receipt once mined, or the reason it reverted.
+    fn on_action_outcome(&mut self, _outcome: ActionOutcome<A>) {}
+
+    /// Returns the name of the strategy, used in logs and exit reports.
+    fn name(&self) -> &'static str {
+        short_type_name::<Self>()
+    }
 }
 
 /// Executor trait defines how actions generated by strategies are carried out.
@@ -67,6 +216,46 @@ pub trait Executor<A>: Send + Sync {
     /// - Placing orders on DEXes
     /// - Any other action required by the strategy
     async fn execute(&self, action: A) -> Result<()>;
+
+    /// Executes a single action, reporting what happened to it through `reporter`.
+    /// The report is routed back to the strategy that emitted the action.
+    ///
+    /// The default implementation reports [ActionStatus::Executed] or
+    /// [ActionStatus::Failed] depending on the result of [execute](Executor::execute).
+    /// Executors that submit transactions should override it to report
+    /// transaction hashes, receipts and reverts.
+    async fn execute_with_reporter(&self, action: A, reporter: OutcomeReporter) -> Result<()>
+    where
+        A: Send + 'async_trait,
+    {
+        let result = self.execute(action).await;
+        match &result {
+            Ok(()) => reporter.report(ActionStatus::Executed),
+            Err(e) => reporter.report(ActionStatus::Failed {
+                reason: e.to_string(),
+            }),
+        }
+        result
+    }
+
+    /// Waits for the work the executor runs in the background, such as
+    /// reporting the outcome of submitted transactions. The engine calls it
+    /// once every action was executed; an error marks the executor as failed.
+    async fn drain(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Returns the name of the executor, used in logs and exit reports.
+    fn name(&self) -> &'static str {
+        short_type_name::<Self>()
+    }
+}
+
+/// Returns the type name of `T` without its module path and generic parameters.
+fn short_type_name<T: ?Sized>() -> &'static str {
+    let name = std::any::type_name::<T>();
+    let name = name.split('<').next().unwrap_or(name);
+    name.rsplit("::").next().unwrap_or(name)
 }
 
 /// CollectorMap is a wrapper around a [Collector](Collector) that maps outgoing
@@ -101,6 +290,10 @@ where
         let stream = stream.map(f);
         Ok(Box::pin(stream))
     }
+
+    fn name(&self) -> &'static str {
+        self.collector.name()
+    }
 }
 
 /// ExecutorMap is a wrapper around an [Executor](Executor) that maps incoming
@@ -136,6 +329,22 @@ where
             None => Ok(()),
         }
     }
+
+    async fn execute_with_reporter(&self, action: A1, reporter: OutcomeReporter) -> Result<()> {
+        let action = (self.f)(action);
+        match action {
+            Some(action) => self.executor.execute_with_reporter(action, reporter).await,
+            None => Ok(()),
+        }
+    }
+
+    async fn drain(&self) -> Result<()> {
+        self.executor.drain().await
+    }
+
+    fn name(&self) -> &'static str {
+        self.executor.name()
+    }
 }
 
 /// Convenience enum containing all the events that can be emitted by collectors.
@@ -146,6 +355,10 @@ pub enum Events {
     NewBlock(NewBlock),
     /// A new transaction has been submitted to the mempool
     Transaction(TransactionRequest),
+    /// A collector re-established its subscription
+    CollectorRestarted(CollectorRestarted),
+    /// A collector detected a chain reorganization
+    Reorg(Reorg),
 }
 
 /// Convenience enum containing all the actions that can be executed by executors.
@@ -155,3 +368,22 @@ pub enum Actions {
     /// Submit a transaction to the public mempool
     SubmitTxToMempool(SubmitTxToMempool),
 }
+
+impl EngineEvent for Events {
+    fn block_number(&self) -> Option<u64> {
+        match self {
+            Events::NewBlock(block) => Some(block.number.to::<u64>()),
+            Events::Transaction(_) => None,
+            Events::CollectorRestarted(restarted) => restarted.resumed_block,
+            Events::Reorg(_) => None,
+        }
+    }
+
+    fn is_collector_restart(&self) -> bool {
+        matches!(self, Events::CollectorRestarted(_))
+    }
+
+    fn is_control(&self) -> bool {
+        matches!(self, Events::CollectorRestarted(_) | Events::Reorg(_))
+    }
+}
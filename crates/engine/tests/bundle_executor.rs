@@ -0,0 +1,144 @@
+use alloy::{
+    network::EthereumWallet,
+    primitives::{keccak256, Bytes, TxHash},
+    providers::ProviderBuilder,
+    signers::local::PrivateKeySigner,
+};
+use engine::{
+    executors::{
+        bundle_executor::{BundleExecutor, BundleTx, SubmitBundle},
+        relay::Relay,
+    },
+    outcome::{ActionStatus, OutcomeReporter},
+    types::Executor,
+};
+use mockito::{Matcher, Server, ServerGuard};
+use serde_json::{json, Value};
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex,
+};
+use std::time::Duration;
+
+/// Starts a mock node whose chain head moves forward by one block each time
+/// it is queried, starting at `head`. Bundles never land.
+async fn mock_node(head: u64) -> ServerGuard {
+    let head = AtomicU64::new(head);
+    let mut server = Server::new_async().await;
+    server
+        .mock("POST", "/")
+        .with_body_from_request(move |request| {
+            let request: Value = serde_json::from_slice(request.body().unwrap()).unwrap();
+            let result = match request["method"].as_str().unwrap() {
+                "eth_blockNumber" => json!(format!("{:#x}", head.fetch_add(1, Ordering::SeqCst))),
+                "eth_chainId" => json!("0x1"),
+                "eth_getTransactionCount" => json!("0x0"),
+                "eth_feeHistory" => json!({
+                    "oldestBlock": "0x1",
+                    "baseFeePerGas": ["0x3b9aca00", "0x3b9aca00"],
+                    "gasUsedRatio": [0.5],
+                    "reward": [["0x3b9aca00"]],
+                }),
+                "eth_getTransactionReceipt" => Value::Null,
+                method => panic!("unexpected method {}", method),
+            };
+            json!({ "jsonrpc": "2.0", "id": request["id"], "result": result })
+                .to_string()
+                .into_bytes()
+        })
+        .create_async()
+        .await;
+    server
+}
+
+fn bundle() -> SubmitBundle {
+    SubmitBundle {
+        txs: vec![BundleTx::Signed(Bytes::from_static(&[0x02, 0x01]))],
+        target_block: None,
+        blocks: 1,
+        reverting_tx_hashes: vec![],
+    }
+}
+
+/// Returns a reporter recording every status it receives.
+fn recording_reporter() -> (OutcomeReporter, Arc<Mutex<Vec<ActionStatus>>>) {
+    let statuses = Arc::new(Mutex::new(vec![]));
+    let recorded = statuses.clone();
+    let reporter = OutcomeReporter::new(move |status| recorded.lock().unwrap().push(status));
+    (reporter, statuses)
+}
+
+/// Test that a bundle is submitted to the relay for its target block, and
+/// reported as dropped once that block was mined without it.
+#[tokio::test]
+async fn test_bundle_is_submitted_then_dropped() {
+    let node = mock_node(100).await;
+    let mut relay = Server::new_async().await;
+    let send_bundle = relay
+        .mock("POST", "/")
+        .match_body(Matcher::PartialJson(json!({
+            "method": "eth_sendBundle",
+            "params": [{ "txs": ["0x0201"], "blockNumber": "0x65" }],
+        })))
+        .with_body(
+            json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": { "bundleHash": TxHash::repeat_byte(0xab) },
+            })
+            .to_string(),
+        )
+        .create_async()
+        .await;
+
+    let provider = Arc::new(ProviderBuilder::new().on_http(node.url().parse().unwrap()));
+    let wallet = EthereumWallet::from(PrivateKeySigner::random());
+    let relays = vec![Relay::new("mock", relay.url())];
+    let executor = BundleExecutor::new(provider, wallet, PrivateKeySigner::random(), relays)
+        .with_poll_interval(Duration::from_millis(10));
+
+    let (reporter, statuses) = recording_reporter();
+    executor
+        .execute_with_reporter(bundle(), reporter)
+        .await
+        .unwrap();
+    // draining waits for the bundle to be reported as dropped
+    executor.drain().await.unwrap();
+
+    let statuses = statuses.lock().unwrap();
+    assert_eq!(statuses.len(), 2, "{:?}", statuses);
+    let tx_hash = keccak256([0x02, 0x01]);
+    assert!(matches!(statuses[0], ActionStatus::Submitted { tx_hash: hash } if hash == tx_hash));
+    assert!(
+        matches!(&statuses[1], ActionStatus::Dropped { reason } if reason.contains("101..=101"))
+    );
+    send_bundle.assert_async().await;
+}
+
+/// Test that a failure to read the chain head is reported, instead of leaving
+/// the action without an outcome.
+#[tokio::test]
+async fn test_node_failure_is_reported() {
+    let mut node = Server::new_async().await;
+    node.mock("POST", "/").with_status(500).create_async().await;
+    let mut relay = Server::new_async().await;
+    let send_bundle = relay.mock("POST", "/").expect(0).create_async().await;
+
+    let provider = Arc::new(ProviderBuilder::new().on_http(node.url().parse().unwrap()));
+    let wallet = EthereumWallet::from(PrivateKeySigner::random());
+    let relays = vec![Relay::new("mock", relay.url())];
+    let executor = BundleExecutor::new(provider, wallet, PrivateKeySigner::random(), relays);
+
+    let (reporter, statuses) = recording_reporter();
+    assert!(executor
+        .execute_with_reporter(bundle(), reporter)
+        .await
+        .is_err());
+
+    let statuses = statuses.lock().unwrap();
+    assert_eq!(statuses.len(), 1, "{:?}", statuses);
+    assert!(
+        matches!(&statuses[0], ActionStatus::Failed { reason } if reason.contains("block number"))
+    );
+    send_bundle.assert_async().await;
+}
@@ -0,0 +1,106 @@
+use alloy::{
+    network::TransactionBuilder,
+    node_bindings::Anvil,
+    primitives::{Address, U256},
+    providers::ProviderBuilder,
+    rpc::types::TransactionRequest,
+};
+use engine::{
+    executors::{
+        dry_run_executor::{DryRunExecutor, FileJournal},
+        mempool_executor::{GasBidInfo, SubmitTxToMempool},
+    },
+    types::Executor,
+};
+use serde_json::Value;
+use std::sync::Arc;
+
+/// Simulates `action` with a fresh journal, and returns the recorded results.
+async fn dry_run(port: u16, mut action: SubmitTxToMempool) -> Vec<Value> {
+    let anvil = Anvil::new().port(port).spawn();
+    let provider = Arc::new(ProviderBuilder::new().on_http(anvil.endpoint_url()));
+    let path = std::env::temp_dir().join(format!("dry-run-{}.jsonl", port));
+    let _ = std::fs::remove_file(&path);
+
+    let journal = FileJournal::open(&path).await.unwrap();
+    let executor = DryRunExecutor::new(provider, Arc::new(journal));
+    action.tx = action.tx.with_from(anvil.addresses()[0]);
+    executor.execute(action).await.unwrap();
+
+    std::fs::read_to_string(&path)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+/// Test that a successful transaction is journaled with its gas cost and net profit.
+#[tokio::test]
+async fn test_dry_run_journals_profit() {
+    let profit = U256::from(10u128.pow(18));
+    let action = SubmitTxToMempool {
+        tx: TransactionRequest::default()
+            .with_to(Address::repeat_byte(0x01))
+            .with_value(U256::from(1)),
+        gas_bid_info: Some(GasBidInfo {
+            total_profit: profit,
+            bid_percentage: 10,
+        }),
+    };
+
+    let results = dry_run(8550, action).await;
+    assert_eq!(results.len(), 1);
+    let result = &results[0];
+    assert_eq!(result["success"], true);
+    assert_eq!(result["gas_used"], 21_000);
+    assert_eq!(result["revert_reason"], Value::Null);
+
+    // 10% of the profit goes to gas, the rest is kept
+    let gas_cost: U256 = serde_json::from_value(result["gas_cost"].clone()).unwrap();
+    assert!(gas_cost <= profit / U256::from(10));
+    assert!(gas_cost > profit / U256::from(11));
+    let net_profit: String = serde_json::from_value(result["net_profit"].clone()).unwrap();
+    assert_eq!(net_profit, (profit - gas_cost).to_string());
+}
+
+/// Test that a transaction that would fail is journaled with the reason, and
+/// never sent.
+#[tokio::test]
+async fn test_dry_run_journals_revert_reason() {
+    let action = SubmitTxToMempool {
+        tx: TransactionRequest::default()
+            .with_to(Address::repeat_byte(0x01))
+            .with_value(U256::MAX),
+        gas_bid_info: None,
+    };
+
+    let results = dry_run(8551, action).await;
+    assert_eq!(results.len(), 1);
+    let result = &results[0];
+    assert_eq!(result["success"], false);
+    assert_eq!(result["gas_used"], Value::Null);
+    assert!(result["revert_reason"].is_string(), "{}", result);
+}
+
+/// Test that a transaction whose profit does not cover the gas bid is journaled
+/// as dropped, since the live executors would not submit it.
+#[tokio::test]
+async fn test_dry_run_journals_unprofitable_bid_as_dropped() {
+    let action = SubmitTxToMempool {
+        tx: TransactionRequest::default()
+            .with_to(Address::repeat_byte(0x01))
+            .with_value(U256::from(1)),
+        gas_bid_info: Some(GasBidInfo {
+            total_profit: U256::from(1),
+            bid_percentage: 10,
+        }),
+    };
+
+    let results = dry_run(8554, action).await;
+    assert_eq!(results.len(), 1);
+    let result = &results[0];
+    assert_eq!(result["success"], false);
+    assert_eq!(result["revert_reason"], Value::Null);
+    assert!(result["drop_reason"].is_string(), "{}", result);
+    assert_eq!(result["gas_cost"], Value::Null);
+}
@@ -0,0 +1,152 @@
+use alloy::{
+    primitives::{keccak256, Address, Bytes, PrimitiveSignature, TxHash, U64},
+    signers::local::PrivateKeySigner,
+};
+use engine::executors::relay::{BundleRequest, Relay, RelayClient, FLASHBOTS_SIGNATURE_HEADER};
+use mockito::{Matcher, Server};
+use serde_json::json;
+
+fn bundle() -> BundleRequest {
+    BundleRequest {
+        txs: vec![
+            Bytes::from_static(&[0x02, 0x01]),
+            Bytes::from_static(&[0x02, 0x02]),
+        ],
+        block_number: U64::from(100),
+        reverting_tx_hashes: vec![],
+    }
+}
+
+/// Returns the address that signed a request body, given its signature header.
+fn recover_signer(header: &str, body: &[u8]) -> Option<Address> {
+    let (address, signature) = header.split_once(':')?;
+    let signature: PrimitiveSignature = signature.parse().ok()?;
+    let message = keccak256(body).to_string();
+    let signer = signature.recover_address_from_msg(message).ok()?;
+    (signer.to_string() == address).then_some(signer)
+}
+
+/// Test that bundles are submitted with eth_sendBundle, and that requests are
+/// signed by the auth signer.
+#[tokio::test]
+async fn test_send_bundle_signs_request() {
+    let signer = PrivateKeySigner::random();
+    let expected = signer.address();
+
+    let mut server = Server::new_async().await;
+    let mock = server
+        .mock("POST", "/")
+        .match_header(
+            FLASHBOTS_SIGNATURE_HEADER,
+            Matcher::Regex("^0x[0-9a-fA-F]{40}:0x[0-9a-f]{130}$".to_string()),
+        )
+        .match_body(Matcher::PartialJson(json!({
+            "method": "eth_sendBundle",
+            "params": [{ "txs": ["0x0201", "0x0202"], "blockNumber": "0x64" }],
+        })))
+        .with_body_from_request(move |request| {
+            let header = request.header(FLASHBOTS_SIGNATURE_HEADER)[0]
+                .to_str()
+                .unwrap();
+            let response = match recover_signer(header, request.body().unwrap()) {
+                Some(signer) if signer == expected => json!({
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "result": { "bundleHash": TxHash::repeat_byte(0xab) },
+                }),
+                _ => json!({
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "error": { "code": -32000, "message": "invalid signature" },
+                }),
+            };
+            response.to_string().into_bytes()
+        })
+        .create_async()
+        .await;
+
+    let client = RelayClient::new(signer);
+    let relay = Relay::new("mock", server.url());
+    let bundle_hash = client.send_bundle(&relay, &bundle()).await.unwrap();
+
+    assert_eq!(bundle_hash, Some(TxHash::repeat_byte(0xab)));
+    mock.assert_async().await;
+}
+
+/// Test that builder endpoints can be used without signatures, and that an
+/// empty result is accepted.
+#[tokio::test]
+async fn test_send_bundle_to_builder_without_signature() {
+    let mut server = Server::new_async().await;
+    let mock = server
+        .mock("POST", "/")
+        .match_header(FLASHBOTS_SIGNATURE_HEADER, Matcher::Missing)
+        .with_body(r#"{"jsonrpc":"2.0","id":1,"result":null}"#)
+        .create_async()
+        .await;
+
+    let client = RelayClient::new(PrivateKeySigner::random());
+    let relay = Relay::builder("builder", server.url()).with_signed_requests(false);
+    let bundle_hash = client.send_bundle(&relay, &bundle()).await.unwrap();
+
+    assert_eq!(bundle_hash, None);
+    mock.assert_async().await;
+}
+
+/// Test that eth_callBundle results are parsed, including reverts.
+#[tokio::test]
+async fn test_call_bundle_reports_reverts() {
+    let reverted = TxHash::repeat_byte(0x02);
+    let mut server = Server::new_async().await;
+    let mock = server
+        .mock("POST", "/")
+        .match_body(Matcher::PartialJson(json!({
+            "method": "eth_callBundle",
+            "params": [{ "blockNumber": "0x64", "stateBlockNumber": "latest" }],
+        })))
+        .with_body(
+            json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": {
+                    "bundleHash": TxHash::repeat_byte(0xab),
+                    "coinbaseDiff": "1000",
+                    "totalGasUsed": 150000,
+                    "results": [
+                        { "txHash": TxHash::repeat_byte(0x01), "gasUsed": 21000 },
+                        { "txHash": reverted, "gasUsed": 129000, "revert": "not profitable" },
+                    ],
+                },
+            })
+            .to_string(),
+        )
+        .create_async()
+        .await;
+
+    let client = RelayClient::new(PrivateKeySigner::random());
+    let relay = Relay::new("mock", server.url());
+    let response = client.call_bundle(&relay, &bundle()).await.unwrap();
+
+    assert_eq!(response.total_gas_used, 150000);
+    assert_eq!(response.results.len(), 2);
+    let failures: Vec<_> = response.failures().collect();
+    assert_eq!(failures, vec![(reverted, "not profitable")]);
+    mock.assert_async().await;
+}
+
+/// Test that JSON-RPC errors returned by a relay are surfaced.
+#[tokio::test]
+async fn test_relay_error() {
+    let mut server = Server::new_async().await;
+    server
+        .mock("POST", "/")
+        .with_body(r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32000,"message":"bundle too old"}}"#)
+        .create_async()
+        .await;
+
+    let client = RelayClient::new(PrivateKeySigner::random());
+    let relay = Relay::new("mock", server.url());
+    let error = client.send_bundle(&relay, &bundle()).await.unwrap_err();
+
+    assert!(error.to_string().contains("bundle too old"), "{}", error);
+}
@@ -0,0 +1,147 @@
+use alloy::{
+    network::TransactionBuilder,
+    node_bindings::Anvil,
+    primitives::{Bytes, B256, U64},
+    providers::{Provider, ProviderBuilder, WsConnect},
+    rpc::types::{Filter, TransactionRequest},
+};
+use engine::{
+    backoff::Backoff,
+    collectors::{
+        block_collector::BlockCollector, multi_log_collector::MultiLogCollector, pool_set::PoolSet,
+    },
+    types::{Collector, CollectorEvent},
+};
+use std::{str::FromStr, sync::Arc, time::Duration};
+use tokio_stream::StreamExt;
+
+const ANVIL_PORT: u16 = 8547;
+
+/// Test that the block collector keeps emitting blocks after the node it is
+/// subscribed to is killed and restarted.
+#[tokio::test]
+async fn test_block_collector_survives_node_restart() {
+    let anvil = Anvil::new().port(ANVIL_PORT).block_time(1u64).spawn();
+    let ws = WsConnect::new(anvil.ws_endpoint());
+    let provider = Arc::new(ProviderBuilder::new().on_ws(ws).await.unwrap());
+
+    let backoff = Backoff::default().with_initial_delay(Duration::from_millis(100));
+    let collector = BlockCollector::new(provider.clone()).with_backoff(backoff);
+    let mut stream = collector.get_event_stream().await.unwrap();
+
+    // wait for a few blocks so that the restarted node starts from a lower height
+    let mut last_number = 0;
+    while last_number < 3 {
+        if let Some(CollectorEvent::Event(block)) = stream.next().await {
+            last_number = block.number.to::<u64>();
+        }
+    }
+
+    drop(anvil);
+    let _anvil = Anvil::new().port(ANVIL_PORT).block_time(1u64).spawn();
+
+    let resumed = tokio::time::timeout(Duration::from_secs(60), async {
+        while let Some(event) = stream.next().await {
+            match event {
+                CollectorEvent::Event(block) if block.number.to::<u64>() < last_number => {
+                    return Some(block);
+                }
+                CollectorEvent::Restarted(restarted) => {
+                    assert_eq!(restarted.collector, "BlockCollector");
+                    assert!(restarted.last_block >= Some(last_number));
+                }
+                _ => {}
+            }
+        }
+        None
+    })
+    .await
+    .expect("collector did not resume after the node restarted");
+
+    assert!(resumed.is_some());
+}
+
+/// Test that the block collector polls for new blocks over HTTP, without
+/// skipping any block.
+#[tokio::test]
+async fn test_block_collector_polls_over_http() {
+    let anvil = Anvil::new().port(8552u16).block_time(1u64).spawn();
+    let provider = Arc::new(ProviderBuilder::new().on_http(anvil.endpoint_url()));
+    let collector = BlockCollector::new(provider).with_polling(Duration::from_millis(100));
+    let mut stream = collector.get_event_stream().await.unwrap();
+
+    let mut blocks = vec![];
+    while blocks.len() < 3 {
+        match tokio::time::timeout(Duration::from_secs(5), stream.next()).await {
+            Ok(Some(CollectorEvent::Event(block))) => blocks.push(block),
+            event => panic!("unexpected event: {:?}", event),
+        }
+    }
+    for pair in blocks.windows(2) {
+        assert_eq!(pair[1].number, pair[0].number + U64::from(1));
+        assert_eq!(pair[1].parent_hash, B256::from(pair[0].hash));
+        assert!(!pair[1].reorg);
+    }
+}
+
+/// Test that the logs since the start block are backfilled even when the pool
+/// set is only filled after the stream is opened, as strategies do once their
+/// pools are loaded.
+#[tokio::test]
+async fn test_multi_log_collector_backfills_pools_added_later() {
+    let anvil = Anvil::new().port(8553u16).spawn();
+    let ws = WsConnect::new(anvil.ws_endpoint());
+    let provider = Arc::new(ProviderBuilder::new().on_ws(ws).await.unwrap());
+    let from = anvil.addresses()[0];
+
+    // a contract emitting an empty log on every call
+    let runtime = "600080a000";
+    let code = Bytes::from_str(&format!("6005600c60003960056000f3{}", runtime)).unwrap();
+    let deploy = TransactionRequest::default()
+        .with_from(from)
+        .with_deploy_code(code);
+    let receipt = provider
+        .send_transaction(deploy)
+        .await
+        .unwrap()
+        .get_receipt()
+        .await
+        .unwrap();
+    let emitter = receipt.contract_address.unwrap();
+    let start_block = provider.get_block_number().await.unwrap() + 1;
+    for _ in 0..2 {
+        let call = TransactionRequest::default()
+            .with_from(from)
+            .with_to(emitter);
+        provider
+            .send_transaction(call)
+            .await
+            .unwrap()
+            .get_receipt()
+            .await
+            .unwrap();
+    }
+
+    let pools = PoolSet::new();
+    let collector = MultiLogCollector::new(provider.clone(), vec![Filter::new()])
+        .with_pool_set(pools.clone())
+        .with_start_block(start_block);
+    let mut stream = collector.get_event_stream().await.unwrap();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        pools.insert([emitter]);
+    });
+
+    let mut blocks = vec![];
+    while blocks.len() < 2 {
+        match tokio::time::timeout(Duration::from_secs(10), stream.next()).await {
+            Ok(Some(CollectorEvent::Event(log))) => {
+                assert_eq!(log.address(), emitter);
+                blocks.push(log.block_number.unwrap());
+            }
+            Ok(Some(_)) => {}
+            event => panic!("unexpected event: {:?}", event),
+        }
+    }
+    assert_eq!(blocks, vec![start_block, start_block + 1]);
+}
@@ -0,0 +1,278 @@
+use alloy::primitives::TxHash;
+use async_trait::async_trait;
+use engine::{
+    backoff::Backoff,
+    engine::Engine,
+    outcome::{ActionOutcome, ActionStatus, OutcomeReporter},
+    supervisor::{RestartPolicy, TaskKind, TaskOutcome},
+    sync::SyncPolicy,
+    types::{Collector, CollectorStream, EngineEvent, Executor, Strategy},
+};
+use eyre::{eyre, Result};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+/// An event emitted once per (fake) block.
+#[derive(Debug, Clone)]
+struct Tick(u64);
+
+impl EngineEvent for Tick {
+    fn block_number(&self) -> Option<u64> {
+        Some(self.0)
+    }
+}
+
+struct CountingCollector;
+
+#[async_trait]
+impl Collector<Tick> for CountingCollector {
+    async fn get_event_stream(&self) -> Result<CollectorStream<'_, Tick>> {
+        let stream = futures::stream::unfold(0u64, |n| async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            Some((Tick(n), n + 1))
+        });
+        Ok(Box::pin(stream))
+    }
+}
+
+/// Fails to initialize on its first attempt.
+struct FlakyStrategy {
+    init_attempts: usize,
+    syncs: Arc<AtomicUsize>,
+}
+
+#[async_trait]
+impl Strategy<Tick, u64> for FlakyStrategy {
+    async fn init_state(&mut self) -> Result<()> {
+        self.init_attempts += 1;
+        if self.init_attempts == 1 {
+            return Err(eyre!("node not ready"));
+        }
+        Ok(())
+    }
+
+    async fn sync_state(&mut self) -> Result<()> {
+        self.syncs.fetch_add(1, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        Ok(())
+    }
+
+    async fn process_event(&mut self, event: Tick) -> Vec<u64> {
+        vec![event.0]
+    }
+}
+
+struct CountingExecutor {
+    executed: Arc<AtomicUsize>,
+}
+
+#[async_trait]
+impl Executor<u64> for CountingExecutor {
+    async fn execute(&self, _action: u64) -> Result<()> {
+        self.executed.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+/// Emits one action per tick and records the outcomes of its actions.
+struct RecordingStrategy {
+    outcomes: Arc<Mutex<Vec<ActionOutcome<u64>>>>,
+}
+
+#[async_trait]
+impl Strategy<Tick, u64> for RecordingStrategy {
+    async fn init_state(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn sync_state(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn process_event(&mut self, event: Tick) -> Vec<u64> {
+        vec![event.0]
+    }
+
+    fn on_action_outcome(&mut self, outcome: ActionOutcome<u64>) {
+        self.outcomes.lock().unwrap().push(outcome);
+    }
+}
+
+/// Reports a submitted transaction for every action, and fails odd actions.
+struct ReportingExecutor;
+
+#[async_trait]
+impl Executor<u64> for ReportingExecutor {
+    async fn execute(&self, _action: u64) -> Result<()> {
+        Ok(())
+    }
+
+    async fn execute_with_reporter(&self, action: u64, reporter: OutcomeReporter) -> Result<()> {
+        reporter.report(ActionStatus::Submitted {
+            tx_hash: TxHash::with_last_byte(action as u8),
+        });
+        if action % 2 == 1 {
+            reporter.report(ActionStatus::Failed {
+                reason: "execution reverted".to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Test that a failing strategy is restarted, and that cancelling the shutdown
+/// token stops every task and drains in-flight actions.
+#[tokio::test]
+async fn test_engine_restarts_failed_strategy_and_shuts_down() {
+    let executed = Arc::new(AtomicUsize::new(0));
+    let policy = RestartPolicy::OnFailure(
+        Backoff::default()
+            .with_initial_delay(Duration::from_millis(10))
+            .with_max_retries(Some(3)),
+    );
+
+    let mut engine: Engine<Tick, u64> = Engine::new().with_restart_policy(policy);
+    engine.add_collector(Box::new(CountingCollector));
+    engine.add_strategy(Box::new(FlakyStrategy {
+        init_attempts: 0,
+        syncs: Arc::new(AtomicUsize::new(0)),
+    }));
+    engine.add_executor(Box::new(CountingExecutor {
+        executed: executed.clone(),
+    }));
+
+    let shutdown = engine.shutdown_token();
+    let handle = tokio::spawn(engine.run());
+
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    shutdown.cancel();
+
+    let report = tokio::time::timeout(Duration::from_secs(5), handle)
+        .await
+        .expect("engine did not shut down")
+        .unwrap();
+
+    assert!(report.is_clean(), "{}", report);
+    assert_eq!(report.tasks.len(), 3);
+    assert!(executed.load(Ordering::SeqCst) > 0);
+
+    let strategy = report
+        .tasks
+        .iter()
+        .find(|task| task.kind == TaskKind::Strategy)
+        .unwrap();
+    assert_eq!(strategy.name, "FlakyStrategy");
+    assert_eq!(strategy.restarts, 1);
+    assert_eq!(strategy.outcome, TaskOutcome::Cancelled);
+}
+
+/// Test that strategies are synced on a block interval and on demand, and that
+/// events received during a sync are queued rather than dropped.
+#[tokio::test]
+async fn test_engine_syncs_strategies() {
+    let executed = Arc::new(AtomicUsize::new(0));
+    let syncs = Arc::new(AtomicUsize::new(0));
+
+    let mut engine: Engine<Tick, u64> =
+        Engine::new().with_sync_policy(SyncPolicy::default().with_block_interval(10));
+    engine.add_collector(Box::new(CountingCollector));
+    engine.add_strategy(Box::new(FlakyStrategy {
+        // skip the failing first attempt
+        init_attempts: 1,
+        syncs: syncs.clone(),
+    }));
+    engine.add_executor(Box::new(CountingExecutor {
+        executed: executed.clone(),
+    }));
+
+    let shutdown = engine.shutdown_token();
+    let sync_handle = engine.sync_handle();
+    let handle = tokio::spawn(engine.run());
+
+    tokio::time::sleep(Duration::from_millis(350)).await;
+    let block_syncs = syncs.load(Ordering::SeqCst);
+    assert!(
+        block_syncs >= 2,
+        "expected block interval syncs, got {}",
+        block_syncs
+    );
+
+    sync_handle.request_sync();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(syncs.load(Ordering::SeqCst) > block_syncs);
+
+    shutdown.cancel();
+    let report = tokio::time::timeout(Duration::from_secs(5), handle)
+        .await
+        .expect("engine did not shut down")
+        .unwrap();
+    assert!(report.is_clean(), "{}", report);
+
+    // every tick emitted before shutdown was executed, none were lost while syncing
+    let executed = executed.load(Ordering::SeqCst);
+    assert!(
+        executed >= 30,
+        "expected buffered events to be processed, got {}",
+        executed
+    );
+}
+
+/// Test that the statuses reported by executors are routed back to the
+/// strategy, tagged with the id of the action they refer to.
+#[tokio::test]
+async fn test_engine_routes_action_outcomes() {
+    let outcomes = Arc::new(Mutex::new(vec![]));
+
+    let mut engine: Engine<Tick, u64> = Engine::new();
+    engine.add_collector(Box::new(CountingCollector));
+    engine.add_strategy(Box::new(RecordingStrategy {
+        outcomes: outcomes.clone(),
+    }));
+    engine.add_executor(Box::new(ReportingExecutor));
+    engine.add_executor(Box::new(CountingExecutor {
+        executed: Arc::new(AtomicUsize::new(0)),
+    }));
+
+    let shutdown = engine.shutdown_token();
+    let handle = tokio::spawn(engine.run());
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    shutdown.cancel();
+    let report = tokio::time::timeout(Duration::from_secs(5), handle)
+        .await
+        .expect("engine did not shut down")
+        .unwrap();
+    assert!(report.is_clean(), "{}", report);
+
+    let outcomes = outcomes.lock().unwrap();
+    assert!(outcomes.len() >= 10, "got {} outcomes", outcomes.len());
+
+    let mut actions = HashMap::new();
+    for outcome in outcomes.iter() {
+        // every status reported for an id refers to the same action
+        assert_eq!(
+            *actions.entry(outcome.id).or_insert(outcome.action),
+            outcome.action
+        );
+        match (&outcome.status, outcome.executor) {
+            (ActionStatus::Submitted { tx_hash }, "ReportingExecutor") => {
+                assert_eq!(*tx_hash, TxHash::with_last_byte(outcome.action as u8))
+            }
+            (ActionStatus::Failed { .. }, "ReportingExecutor") => {
+                assert_eq!(outcome.action % 2, 1)
+            }
+            (ActionStatus::Executed, "CountingExecutor") => {}
+            (status, executor) => panic!("unexpected status {:?} from {}", status, executor),
+        }
+    }
+    // ids are unique per action
+    let mut ids: Vec<_> = actions.values().collect();
+    ids.sort();
+    ids.dedup();
+    assert_eq!(ids.len(), actions.len());
+}
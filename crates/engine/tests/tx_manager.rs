@@ -0,0 +1,87 @@
+use alloy::{
+    network::{EthereumWallet, TransactionBuilder},
+    node_bindings::Anvil,
+    primitives::{Address, U256},
+    providers::{Provider, ProviderBuilder},
+    rpc::types::TransactionRequest,
+    signers::local::PrivateKeySigner,
+};
+use engine::executors::tx_manager::{TxManager, TxManagerConfig, TxResolution};
+use std::{sync::Arc, time::Duration};
+
+fn transfer(value: u64) -> TransactionRequest {
+    TransactionRequest::default()
+        .with_to(Address::repeat_byte(0x01))
+        .with_value(U256::from(value))
+}
+
+/// Test that concurrent transactions get consecutive nonces, and that their
+/// receipts are delivered once they are mined.
+#[tokio::test]
+async fn test_tx_manager_allocates_nonces_and_resolves_receipts() {
+    let anvil = Anvil::new().port(8548u16).spawn();
+    let signer: PrivateKeySigner = anvil.keys()[0].clone().into();
+    let from = signer.address();
+    let provider = Arc::new(ProviderBuilder::new().on_http(anvil.endpoint_url()));
+
+    let config = TxManagerConfig::default().with_poll_interval(Duration::from_millis(100));
+    let manager =
+        Arc::new(TxManager::new(provider.clone(), EthereumWallet::new(signer)).with_config(config));
+    manager.spawn();
+
+    let handles = futures::future::try_join_all((1..=3).map(|value| manager.send(transfer(value))))
+        .await
+        .unwrap();
+    let mut nonces: Vec<_> = handles.iter().map(|handle| handle.nonce).collect();
+    nonces.sort();
+    assert_eq!(nonces, vec![0, 1, 2]);
+
+    for handle in handles {
+        let tx_hash = handle.tx_hash;
+        match tokio::time::timeout(Duration::from_secs(5), handle.resolved())
+            .await
+            .expect("transaction was not resolved")
+        {
+            TxResolution::Mined(receipt) => {
+                assert!(receipt.status());
+                assert_eq!(receipt.transaction_hash, tx_hash);
+            }
+            resolution => panic!("unexpected resolution: {:?}", resolution),
+        }
+    }
+    assert_eq!(manager.pending_count(from), 0);
+}
+
+/// Test that a stale transaction is cancelled with a self-transfer using the same nonce.
+#[tokio::test]
+async fn test_tx_manager_cancels_stale_transactions() {
+    let anvil = Anvil::new().port(8549u16).arg("--no-mining").spawn();
+    let signer: PrivateKeySigner = anvil.keys()[0].clone().into();
+    let from = signer.address();
+    let provider = Arc::new(ProviderBuilder::new().on_http(anvil.endpoint_url()));
+
+    let config = TxManagerConfig::default().with_cancel_after_blocks(Some(0));
+    let manager = TxManager::new(provider.clone(), EthereumWallet::new(signer)).with_config(config);
+
+    let handle = manager.send(transfer(1)).await.unwrap();
+    // the transaction is stale right away, so it is replaced by a cancellation
+    manager.poll().await.unwrap();
+    provider
+        .raw_request::<_, serde_json::Value>("evm_mine".into(), ())
+        .await
+        .unwrap();
+    manager.poll().await.unwrap();
+
+    match handle.resolved().await {
+        TxResolution::Cancelled(receipt) => {
+            assert!(receipt.status());
+            assert_eq!(receipt.to, Some(from));
+        }
+        resolution => panic!("unexpected resolution: {:?}", resolution),
+    }
+    assert_eq!(
+        provider.get_transaction_count(from).await.unwrap(),
+        1,
+        "the cancellation should use the nonce of the stale transaction"
+    );
+}
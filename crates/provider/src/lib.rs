@@ -59,6 +59,17 @@ pub fn get_chain_rpc_url(chain: NamedChain) -> String {
     }
 }
 
+/// Returns the HTTP RPC URL of `chain`, for nodes or providers without pubsub support.
+pub fn get_chain_http_url(chain: NamedChain) -> String {
+    match chain {
+        NamedChain::Mainnet => env::var("MAINNET_RPC_URL").expect("MAINNET_RPC_URL is not set"),
+        NamedChain::Arbitrum => env::var("ARBITRUM_RPC_URL").expect("ARBITRUM_RPC_URL is not set"),
+        NamedChain::Optimism => env::var("OPTIMISM_RPC_URL").expect("OPTIMISM_RPC_URL is not set"),
+        NamedChain::Base => env::var("BASE_RPC_URL").expect("BASE_RPC_URL is not set"),
+        _ => panic!("Chain not supported"),
+    }
+}
+
 pub async fn get_basic_provider(chain: Chain) -> DynProvider {
     let chain = NamedChain::try_from(chain.id()).unwrap();
     let rpc_url = get_chain_rpc_url(chain);
@@ -94,6 +105,25 @@ pub async fn get_signer_provider_arc(chain: Chain, wallet: EthereumWallet) -> Ar
     Arc::new(get_signer_provider(chain, wallet).await)
 }
 
+pub async fn get_http_signer_provider(chain: Chain, wallet: EthereumWallet) -> DynProvider {
+    let chain = NamedChain::try_from(chain.id()).unwrap();
+    let rpc_url = get_chain_http_url(chain);
+
+    let provider = ProviderBuilder::new()
+        .wallet(wallet)
+        .on_builtin(rpc_url.as_str())
+        .await
+        .unwrap()
+        .erased();
+
+    return provider;
+}
+
+pub async fn get_default_http_signer_provider_arc(chain: Chain) -> Arc<DynProvider> {
+    let wallet = get_default_wallet();
+    Arc::new(get_http_signer_provider(chain, wallet).await)
+}
+
 pub async fn get_default_signer_provider(chain: Chain) -> DynProvider {
     let wallet = get_default_wallet();
     get_signer_provider(chain, wallet).await
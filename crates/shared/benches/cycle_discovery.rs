@@ -0,0 +1,158 @@
+//! Compares the cycle discovery of [TokenGraph] with the recursive search the
+//! strategies used before, on random pool sets.
+//!
+//! Run with `cargo bench -p shared --bench cycle_discovery`.
+
+use std::collections::HashSet;
+
+use alloy::primitives::Address;
+use amms::amm::{uniswap_v2::UniswapV2Pool, AutomatedMarketMaker, AMM};
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use shared::token_graph::TokenGraph;
+
+const MAX_HOPS: usize = 3;
+
+fn token(index: u64) -> Address {
+    Address::left_padding_from(&(index + 1).to_be_bytes())
+}
+
+/// Returns `count` pools between `tokens` tokens, every pool trading token 0
+/// with some probability so that the entry token has cycles.
+fn pools(count: u64, tokens: u64) -> Vec<AMM> {
+    // a small linear congruential generator keeps the pool sets reproducible
+    let mut seed = 42u64;
+    let mut next = move |max: u64| {
+        seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (seed >> 33) % max
+    };
+    (0..count)
+        .map(|index| {
+            let token_a = if next(4) == 0 { 0 } else { next(tokens) };
+            let mut token_b = next(tokens);
+            if token_b == token_a {
+                token_b = (token_b + 1) % tokens;
+            }
+            AMM::UniswapV2Pool(UniswapV2Pool {
+                address: Address::left_padding_from(&(index + 1_000_000).to_be_bytes()),
+                token_a: token(token_a),
+                token_b: token(token_b),
+                ..Default::default()
+            })
+        })
+        .collect()
+}
+
+/// The recursive search of `State::get_cycles`, which the token graph replaces.
+fn recursive_cycles(
+    pairs: &[AMM],
+    token_in: Address,
+    token_out: Address,
+    max_hops: i32,
+    current_pairs: &Vec<AMM>,
+    circles: &mut Vec<Vec<AMM>>,
+    seen: &mut HashSet<Address>,
+) -> Vec<Vec<AMM>> {
+    let mut circles_copy = circles.clone();
+    for pair in pairs {
+        let address = pair.address();
+        let tokens = pair.tokens();
+        if seen.contains(&address) {
+            continue;
+        }
+        let temp_out = if token_in == tokens[0] {
+            tokens[1]
+        } else if token_in == tokens[1] {
+            tokens[0]
+        } else {
+            continue;
+        };
+
+        let mut new_seen = seen.clone();
+        new_seen.insert(address);
+        let mut new_pairs = current_pairs.clone();
+        new_pairs.push(pair.clone());
+        if temp_out == token_out {
+            circles_copy.push(new_pairs);
+        } else if max_hops > 1 {
+            circles_copy = recursive_cycles(
+                pairs,
+                temp_out,
+                token_out,
+                max_hops - 1,
+                &new_pairs,
+                &mut circles_copy,
+                &mut new_seen,
+            );
+        }
+    }
+    circles_copy
+}
+
+fn bench_cycle_discovery(c: &mut Criterion) {
+    let mut group = c.benchmark_group("cycle_discovery");
+    group.sample_size(10);
+
+    for count in [50, 200, 500] {
+        let pools = pools(count, count / 4);
+        let entry_token = token(0);
+
+        group.bench_with_input(BenchmarkId::new("recursive", count), &pools, |b, pools| {
+            b.iter(|| {
+                recursive_cycles(
+                    black_box(pools),
+                    entry_token,
+                    entry_token,
+                    MAX_HOPS as i32,
+                    &vec![],
+                    &mut vec![],
+                    &mut HashSet::new(),
+                )
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("graph", count), &pools, |b, pools| {
+            b.iter(|| {
+                let mut graph = TokenGraph::new([entry_token], MAX_HOPS);
+                for pool in black_box(pools) {
+                    graph.add_pool(pool);
+                }
+                graph
+            })
+        });
+
+        let mut graph = TokenGraph::new([entry_token], MAX_HOPS);
+        for pool in pools.iter() {
+            graph.add_pool(pool);
+        }
+        let new_pool = AMM::UniswapV2Pool(UniswapV2Pool {
+            address: Address::repeat_byte(0xff),
+            token_a: entry_token,
+            token_b: token(1),
+            ..Default::default()
+        });
+        group.bench_with_input(
+            BenchmarkId::new("graph_add_pool", count),
+            &new_pool,
+            |b, new_pool| {
+                b.iter_batched(
+                    || graph.clone(),
+                    |mut graph| graph.add_pool(black_box(new_pool)),
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+
+        // constant rates: the search cost does not depend on the prices
+        group.bench_with_input(
+            BenchmarkId::new("negative_cycles", count),
+            &graph,
+            |b, graph| b.iter(|| graph.negative_cycles(|_, _| Some(black_box(1.0)))),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_cycle_discovery);
+criterion_main!(benches);
@@ -1,13 +1,58 @@
 use std::fmt;
 
 use crate::route::simulate_route;
-use alloy::primitives::{keccak256, Address, U256};
-use amms::amm::{AutomatedMarketMaker, AMM};
+use alloy::primitives::{keccak256, Address, I256, U256};
+use amms::{
+    amm::{AutomatedMarketMaker, AMM},
+    errors::AMMError,
+};
+use eyre::{eyre, Result};
+
+/// Gas used by the executor contract around the swaps of a cycle: wrapping,
+/// transfers and the final profitability check.
+pub const CYCLE_BASE_GAS: u64 = 100_000;
+
+/// Gas used by each swap of a cycle.
+pub const CYCLE_HOP_GAS: u64 = 120_000;
+
+/// Calldata of the executor contract around the swaps of a cycle, in bytes.
+pub const CYCLE_BASE_CALLDATA_SIZE: usize = 600;
+
+/// Calldata of each swap of a cycle, in bytes.
+pub const CYCLE_HOP_CALLDATA_SIZE: usize = 350;
+
+/// Premium charged by Aave V3 flash loans, in basis points.
+pub const AAVE_FLASH_LOAN_PREMIUM_BPS: u32 = 5;
+
+/// The optimal input of a trade is searched to within `1 / SIZING_PRECISION`
+/// of the largest input the cycle can take.
+const SIZING_PRECISION: u64 = 10_000;
+
+/// The profit-maximizing trade through a [Cycle], found by [Cycle::optimal_trade].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OptimalTrade {
+    /// The amount of the entry token to trade
+    pub amount_in: U256,
+    /// The amount of the entry token expected back
+    pub amount_out: U256,
+    /// The amount entering each hop, followed by the amount out of the last hop
+    pub hop_amounts: Vec<U256>,
+}
+
+impl OptimalTrade {
+    /// Returns the expected profit, negative if the trade loses money, or
+    /// `I256::MIN` if the amounts do not fit in a signed integer.
+    pub fn profit(&self) -> I256 {
+        signed_difference(self.amount_out, self.amount_in).unwrap_or(I256::MIN)
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct Cycle {
     pub amms: Vec<AMM>,
     pub id: String,
+    /// The token the cycle starts and ends with
+    pub entry_token: Address,
 }
 
 impl fmt::Display for Cycle {
@@ -32,25 +77,46 @@ impl fmt::Display for Cycle {
 
 impl Cycle {
     pub fn new(amms: Vec<AMM>) -> Self {
-        let id = Self::compute_id(&amms);
-        Self { amms, id }
+        let entry_token = Self::find_entry_token(&amms);
+        Self::with_entry_token(entry_token, amms)
+    }
+
+    /// Creates the cycle trading through `amms` in order, starting and ending
+    /// with `entry_token`.
+    pub fn with_entry_token(entry_token: Address, amms: Vec<AMM>) -> Self {
+        let id = Self::compute_id(entry_token, &amms);
+        Self {
+            amms,
+            id,
+            entry_token,
+        }
     }
 
-    pub fn compute_id(amms: &Vec<AMM>) -> String {
-        let tokens: Vec<Address> = amms.iter().map(|pool| pool.tokens()).flatten().collect();
-        let token_strings = tokens
-            .iter()
-            .map(|token| token.to_string())
-            .collect::<Vec<String>>();
-        let concatenated_string = token_strings.join("");
-        let hash = keccak256(concatenated_string);
-        hash.to_string()
+    /// Returns the canonical id of the cycle trading through `amms` from
+    /// `entry_token`. See [canonical_id].
+    pub fn compute_id(entry_token: Address, amms: &[AMM]) -> String {
+        let mut hops = Vec::with_capacity(amms.len());
+        let mut token_in = entry_token;
+        for pool in amms {
+            hops.push((pool.address(), token_in));
+            let tokens = pool.tokens();
+            token_in = if token_in == tokens[0] {
+                tokens[1]
+            } else {
+                tokens[0]
+            };
+        }
+        canonical_id(hops)
     }
 
     pub fn get_entry_token(&self) -> Address {
+        self.entry_token
+    }
+
+    fn find_entry_token(amms: &[AMM]) -> Address {
         // the entry token is the token that is in the first pool and the last pool
-        let first_pool_tokens = self.amms.first().unwrap().tokens();
-        let last_pool_tokens = self.amms.last().unwrap().tokens();
+        let first_pool_tokens = amms.first().unwrap().tokens();
+        let last_pool_tokens = amms.last().unwrap().tokens();
 
         let [first_pool_token_a, first_pool_token_b] = first_pool_tokens.as_slice() else {
             todo!()
@@ -66,28 +132,311 @@ impl Cycle {
         }
     }
 
-    pub fn get_profit(&self, amount_in: U256) -> i128 {
+    /// Returns the profit of trading `amount_in` through the cycle, before
+    /// any cost. Fails if the route cannot be simulated.
+    pub fn get_profit(&self, amount_in: U256) -> Result<I256> {
         let token_in = self.get_entry_token();
-        let amount_out = simulate_route(token_in, amount_in, &self.amms).unwrap();
-        let profit = amount_out.to::<i128>() - amount_in.to::<i128>();
+        let amount_out = simulate_route(token_in, amount_in, &self.amms)?;
+        signed_difference(amount_out, amount_in)
+    }
+
+    pub fn get_profit_no_slippage(&self, amount_in: U256) -> Result<I256> {
+        self.get_profit(amount_in)
+    }
 
-        profit
+    /// Returns the profit of trading 1e18 wei through the cycle, as a fraction
+    /// of the input. Cycles that cannot be simulated rank last.
+    pub fn get_profit_perc(&self) -> f64 {
+        self.profit_perc(U256::from(10u64.pow(18)))
+    }
+
+    /// Returns the profit of trading `amount_in` through the cycle, as a
+    /// fraction of the input. Cycles that cannot be simulated rank last.
+    pub fn profit_perc(&self, amount_in: U256) -> f64 {
+        let Some(profit) = self
+            .get_profit(amount_in)
+            .ok()
+            .and_then(|profit| i128::try_from(profit).ok())
+        else {
+            return f64::NEG_INFINITY;
+        };
+        profit as f64 / f64::from(amount_in)
     }
 
-    pub fn get_profit_no_slippage(&self, amount_in: U256) -> i128 {
+    /// Returns a rough estimate of the gas used to trade through the cycle.
+    pub fn gas_estimate(&self) -> u64 {
+        CYCLE_BASE_GAS + CYCLE_HOP_GAS * self.amms.len() as u64
+    }
+
+    /// Returns a rough estimate of the calldata size of a transaction trading
+    /// through the cycle, which the L1 data fee of OP-stack chains depends on.
+    pub fn calldata_size_estimate(&self) -> usize {
+        CYCLE_BASE_CALLDATA_SIZE + CYCLE_HOP_CALLDATA_SIZE * self.amms.len()
+    }
+
+    /// Evaluates the profit of trading `amount_in` through the cycle, net of
+    /// the execution and funding `costs`.
+    pub fn evaluate(&self, amount_in: U256, costs: &CostModel) -> Result<ProfitBreakdown> {
         let token_in = self.get_entry_token();
-        let amount_out = simulate_route(token_in, amount_in, &self.amms).unwrap();
-        let profit = amount_out.to::<i128>() - amount_in.to::<i128>();
+        let gross_out = simulate_route(token_in, amount_in, &self.amms)?;
+        ProfitBreakdown::new(amount_in, gross_out, costs)
+    }
+
+    /// Simulates a trade of `amount_in` through the cycle, returning the amount
+    /// entering each hop followed by the amount out of the last hop.
+    pub fn simulate_hops(&self, amount_in: U256) -> Result<Vec<U256>, AMMError> {
+        let mut token_in = self.get_entry_token();
+        let mut amounts = Vec::with_capacity(self.amms.len() + 1);
+        amounts.push(amount_in);
+
+        let mut amount = amount_in;
+        for pool in &self.amms {
+            amount = simulate_route(token_in, amount, std::slice::from_ref(pool))?;
+            amounts.push(amount);
 
-        profit
+            let tokens = pool.tokens();
+            token_in = if token_in == tokens[0] {
+                tokens[1]
+            } else {
+                tokens[0]
+            };
+        }
+        Ok(amounts)
     }
 
-    pub fn get_profit_perc(&self) -> f64 {
-        let amount_in = U256::from(10u64.pow(18));
-        let profit = self.get_profit(amount_in);
-        let profit_perc = profit as f64 / (amount_in.to::<i128>() as f64);
-        profit_perc
+    /// Finds the input amount that maximizes the profit of the cycle, up to
+    /// `max_amount_in`, typically the inventory of the entry token.
+    ///
+    /// The profit of a cycle is concave in its input, whatever the pools it
+    /// goes through, so it is maximized with a ternary search over
+    /// [simulate_route]. Inputs the pools cannot fill are treated as
+    /// unprofitable. Returns `None` if no input could be simulated; the
+    /// returned trade may still lose money when the cycle is not profitable.
+    pub fn optimal_trade(&self, max_amount_in: U256) -> Option<OptimalTrade> {
+        let profit = |amount_in: U256| match self.simulate_hops(amount_in) {
+            Ok(amounts) => {
+                signed_difference(amounts[amounts.len() - 1], amount_in).unwrap_or(I256::MIN)
+            }
+            Err(_) => I256::MIN,
+        };
+
+        let lowest_delta = (max_amount_in / U256::from(SIZING_PRECISION)).max(U256::from(1));
+        let amount_in = maximize_profit(U256::ZERO, max_amount_in, lowest_delta, profit);
+        if amount_in.is_zero() {
+            return None;
+        }
+
+        let hop_amounts = self.simulate_hops(amount_in).ok()?;
+        Some(OptimalTrade {
+            amount_in,
+            amount_out: hop_amounts[hop_amounts.len() - 1],
+            hop_amounts,
+        })
+    }
+}
+
+/// Returns the id of the cycle going through `hops`, each hop being a pool and
+/// the token going into it.
+///
+/// The hops are rotated to start with the lowest pool address, so that the same
+/// loop entered from any of its tokens has the same id. The tokens going into
+/// each pool tell a cycle from its reverse, even when both go through the same
+/// pools, and routes through different pools for the same tokens get different ids.
+pub fn canonical_id(mut hops: Vec<(Address, Address)>) -> String {
+    let start = hops
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, (pool, _))| *pool)
+        .map_or(0, |(index, _)| index);
+    hops.rotate_left(start);
+
+    let mut bytes = Vec::with_capacity(hops.len() * 2 * std::mem::size_of::<Address>());
+    for (pool, token_in) in hops {
+        bytes.extend_from_slice(pool.as_slice());
+        bytes.extend_from_slice(token_in.as_slice());
+    }
+    keccak256(bytes).to_string()
+}
+
+/// How the input of a cycle is funded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Funding {
+    /// The input is taken from the inventory of the executor, at no cost
+    #[default]
+    Inventory,
+    /// The input is borrowed with an Aave flash loan, for a premium in basis points
+    AaveFlashLoan { premium_bps: u32 },
+    /// The input is borrowed with a Morpho flash loan, which is free
+    MorphoFlashLoan,
+    /// The input is borrowed with a flash swap, paying the fee of the pool in
+    /// basis points
+    FlashSwap { fee_bps: u32 },
+}
+
+impl Funding {
+    /// Returns the cost of funding `amount_in`, rounded up.
+    pub fn cost(&self, amount_in: U256) -> Result<U256> {
+        let bps = match self {
+            Funding::Inventory | Funding::MorphoFlashLoan => return Ok(U256::ZERO),
+            Funding::AaveFlashLoan { premium_bps } => *premium_bps,
+            Funding::FlashSwap { fee_bps } => *fee_bps,
+        };
+        let fee = amount_in
+            .checked_mul(U256::from(bps))
+            .ok_or_else(|| eyre!("funding cost overflow"))?;
+        Ok(fee.div_ceil(U256::from(10_000)))
+    }
+}
+
+/// The costs of executing a cycle, deducted from its profit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CostModel {
+    /// The gas used by the transaction
+    pub gas_used: u64,
+    /// The gas price paid, in wei
+    pub gas_price: u128,
+    /// The L1 data fee paid on OP-stack chains, in wei
+    pub l1_fee: U256,
+    /// The amount of the entry token worth 1 ether of the native token, used
+    /// to convert gas costs. 1 ether when the entry token is the wrapped
+    /// native token.
+    pub native_price: U256,
+    /// How the input of the cycle is funded
+    pub funding: Funding,
+}
+
+impl CostModel {
+    /// Creates the costs of a transaction using `gas_used` at `gas_price`,
+    /// for a cycle entering with the wrapped native token out of inventory.
+    pub fn new(gas_used: u64, gas_price: u128) -> Self {
+        Self {
+            gas_used,
+            gas_price,
+            l1_fee: U256::ZERO,
+            native_price: U256::from(10u64.pow(18)),
+            funding: Funding::default(),
+        }
+    }
+
+    /// Sets the L1 data fee paid by the transaction.
+    pub fn with_l1_fee(mut self, l1_fee: U256) -> Self {
+        self.l1_fee = l1_fee;
+        self
+    }
+
+    /// Sets the amount of the entry token worth 1 ether of the native token.
+    pub fn with_native_price(mut self, native_price: U256) -> Self {
+        self.native_price = native_price;
+        self
+    }
+
+    /// Sets how the input of the cycle is funded.
+    pub fn with_funding(mut self, funding: Funding) -> Self {
+        self.funding = funding;
+        self
+    }
+
+    /// Converts an amount of the entry token to wei of the native token.
+    /// Returns `None` if the native price is zero or the amount overflows.
+    pub fn to_native(&self, amount: U256) -> Option<U256> {
+        amount
+            .checked_mul(U256::from(10u64.pow(18)))?
+            .checked_div(self.native_price)
+    }
+
+    /// Returns the gas cost of the transaction, L1 data fee included,
+    /// converted to the entry token.
+    pub fn gas_cost(&self) -> Result<U256> {
+        let fee = U256::from(self.gas_used)
+            .checked_mul(U256::from(self.gas_price))
+            .and_then(|fee| fee.checked_add(self.l1_fee))
+            .ok_or_else(|| eyre!("gas cost overflow"))?;
+        let cost = fee
+            .checked_mul(self.native_price)
+            .ok_or_else(|| eyre!("gas cost overflow"))?;
+        Ok(cost.div_ceil(U256::from(10u64.pow(18))))
+    }
+}
+
+/// The profit of a cycle, net of the costs of executing it. Amounts are in
+/// the entry token of the cycle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfitBreakdown {
+    /// The amount traded
+    pub amount_in: U256,
+    /// The amount received back from the last pool
+    pub gross_out: U256,
+    /// The gas cost of the transaction, L1 data fee included
+    pub gas_cost: U256,
+    /// The cost of funding the input
+    pub funding_cost: U256,
+    /// The profit left once the input and every cost are paid back
+    pub net_profit: I256,
+}
+
+impl ProfitBreakdown {
+    /// Computes the net profit of trading `amount_in` for `gross_out`. Fails
+    /// if an amount overflows.
+    pub fn new(amount_in: U256, gross_out: U256, costs: &CostModel) -> Result<Self> {
+        let gas_cost = costs.gas_cost()?;
+        let funding_cost = costs.funding.cost(amount_in)?;
+        let total_in = amount_in
+            .checked_add(gas_cost)
+            .and_then(|total| total.checked_add(funding_cost))
+            .ok_or_else(|| eyre!("cycle cost overflow"))?;
+        Ok(Self {
+            amount_in,
+            gross_out,
+            gas_cost,
+            funding_cost,
+            net_profit: signed_difference(gross_out, total_in)?,
+        })
+    }
+
+    /// Returns the profit before costs.
+    pub fn gross_profit(&self) -> Result<I256> {
+        signed_difference(self.gross_out, self.amount_in)
     }
+
+    /// Returns true if the cycle is worth executing.
+    pub fn is_profitable(&self) -> bool {
+        self.net_profit > I256::ZERO
+    }
+}
+
+/// Returns `a - b`, failing if either amount does not fit in an [I256].
+fn signed_difference(a: U256, b: U256) -> Result<I256> {
+    let a = I256::try_from(a).map_err(|_| eyre!("amount {} overflows", a))?;
+    let b = I256::try_from(b).map_err(|_| eyre!("amount {} overflows", b))?;
+    Ok(a - b)
+}
+
+/// Ternary search for the input in `[domain_min, domain_max]` maximizing `f`,
+/// to within `lowest_delta`.
+///
+/// `f` being concave, the maximum cannot lie beyond the lower of the two inner
+/// points, so only that outer quarter of the domain is discarded at each step.
+fn maximize_profit(
+    mut domain_min: U256,
+    mut domain_max: U256,
+    lowest_delta: U256,
+    f: impl Fn(U256) -> I256,
+) -> U256 {
+    // the inner points are distinct from the bounds once the domain spans 4 inputs
+    let lowest_delta = lowest_delta.max(U256::from(3));
+    while domain_max > domain_min && domain_max - domain_min > lowest_delta {
+        let quarter = (domain_max - domain_min) / U256::from(4);
+        let lower_mid = domain_min + quarter;
+        let upper_mid = domain_max - quarter;
+
+        if f(lower_mid) >= f(upper_mid) {
+            domain_max = upper_mid;
+        } else {
+            domain_min = lower_mid;
+        }
+    }
+
+    (domain_max + domain_min) / U256::from(2)
 }
 
 pub fn sort_cycle_by_profit(cycles: Vec<Cycle>) -> Vec<Cycle> {
@@ -107,3 +456,137 @@ pub fn get_most_profitable_cycle(cycles: Vec<Cycle>) -> Option<Cycle> {
 pub fn get_most_profitable_cycles(cycles: Vec<Cycle>, n: usize) -> Vec<Cycle> {
     sort_cycle_by_profit(cycles).into_iter().take(n).collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amms::amm::uniswap_v2::UniswapV2Pool;
+
+    fn pool(address: u8, reserve_0: u128, reserve_1: u128) -> AMM {
+        AMM::UniswapV2Pool(UniswapV2Pool {
+            address: Address::repeat_byte(address),
+            token_a: Address::repeat_byte(10),
+            token_a_decimals: 18,
+            token_b: Address::repeat_byte(11),
+            token_b_decimals: 18,
+            reserve_0,
+            reserve_1,
+            fee: 300,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_cycle_ids_identify_pools_and_direction() {
+        let eth = 10u128.pow(18);
+        let (a, b, c) = (pool(1, eth, eth), pool(2, eth, eth), pool(3, eth, eth));
+        let token_a = Address::repeat_byte(10);
+        let token_b = Address::repeat_byte(11);
+
+        let forward = Cycle::with_entry_token(token_a, vec![a.clone(), b.clone()]);
+        let reverse = Cycle::with_entry_token(token_a, vec![b.clone(), a.clone()]);
+        let other_pool = Cycle::with_entry_token(token_a, vec![a.clone(), c.clone()]);
+        assert_ne!(forward.id, reverse.id);
+        assert_ne!(forward.id, other_pool.id);
+
+        // the same loop entered from its other token
+        let rotated = Cycle::with_entry_token(token_b, vec![b, a]);
+        assert_eq!(forward.id, rotated.id);
+    }
+
+    #[test]
+    fn test_net_profit_deducts_gas_and_funding() {
+        let eth = U256::from(10u64.pow(18));
+        let gwei = 10u128.pow(9);
+        // 200k gas at 10 gwei plus a 0.001 ether L1 fee, with ether worth 2 entry tokens
+        let costs = CostModel::new(200_000, 10 * gwei)
+            .with_l1_fee(eth / U256::from(1000))
+            .with_native_price(eth * U256::from(2))
+            .with_funding(Funding::AaveFlashLoan {
+                premium_bps: AAVE_FLASH_LOAN_PREMIUM_BPS,
+            });
+
+        let breakdown = ProfitBreakdown::new(eth, eth + eth / U256::from(100), &costs).unwrap();
+        assert_eq!(breakdown.gas_cost, U256::from(6 * 10u64.pow(15)));
+        assert_eq!(breakdown.funding_cost, U256::from(5 * 10u64.pow(14)));
+        assert_eq!(
+            breakdown.net_profit,
+            I256::try_from(35 * 10u64.pow(14)).unwrap()
+        );
+        assert!(breakdown.is_profitable());
+        // 2 tokens are worth 1 ether
+        assert_eq!(costs.to_native(eth), Some(eth / U256::from(2)));
+
+        // a gross profit smaller than the costs is a loss
+        let breakdown = ProfitBreakdown::new(eth, eth + U256::from(1), &costs).unwrap();
+        assert!(breakdown.net_profit < I256::ZERO);
+        assert!(!breakdown.is_profitable());
+
+        assert!(ProfitBreakdown::new(U256::MAX, U256::MAX, &costs).is_err());
+    }
+
+    #[test]
+    fn test_optimal_trade_maximizes_profit() {
+        let eth = 10u128.pow(18);
+        // token b is 10% cheaper in the second pool
+        let cycle = Cycle::new(vec![
+            pool(1, 100 * eth, 200 * eth),
+            pool(2, 110 * eth, 200 * eth),
+        ]);
+
+        let trade = cycle.optimal_trade(U256::from(50 * eth)).unwrap();
+        assert!(trade.profit() > I256::ZERO);
+        assert_eq!(trade.hop_amounts.len(), 3);
+        assert_eq!(trade.hop_amounts[0], trade.amount_in);
+        assert_eq!(trade.hop_amounts[2], trade.amount_out);
+
+        let profit_at = |amount_in: U256| {
+            let amounts = cycle.simulate_hops(amount_in).unwrap();
+            signed_difference(amounts[2], amount_in).unwrap()
+        };
+        assert!(trade.profit() >= profit_at(trade.amount_in / U256::from(2)));
+        assert!(trade.profit() >= profit_at(trade.amount_in * U256::from(2)));
+
+        // the trade never exceeds the inventory
+        let small = cycle.optimal_trade(U256::from(eth / 10)).unwrap();
+        assert!(small.amount_in <= U256::from(eth / 10));
+
+        // amounts too large for a signed profit are never ranked as profitable
+        let overflowing = OptimalTrade {
+            amount_in: U256::ZERO,
+            amount_out: U256::MAX,
+            hop_amounts: vec![U256::ZERO, U256::MAX],
+        };
+        assert_eq!(overflowing.profit(), I256::MIN);
+    }
+
+    #[test]
+    fn test_optimal_trade_matches_the_closed_form_optimum() {
+        let eth = 10u128.pow(18);
+        let (x1, y1, y2, x2) = (100 * eth, 200 * eth, 200 * eth, 110 * eth);
+        let cycle = Cycle::new(vec![pool(1, x1, y1), pool(2, x2, y2)]);
+
+        // the two pools trade like a single pool of reserves `r_in` and
+        // `r_out`, whose profit `γ·a·r_out / (r_in + γ·a) - a` peaks at
+        // `a = (sqrt(γ·r_in·r_out) - r_in) / γ`, with a 0.3% fee
+        let gamma = 0.997;
+        let (x1, y1, y2, x2) = (x1 as f64, y1 as f64, y2 as f64, x2 as f64);
+        let r_in = x1 * y2 / (y2 + gamma * y1);
+        let r_out = gamma * y1 * x2 / (y2 + gamma * y1);
+        let optimum = ((gamma * r_in * r_out).sqrt() - r_in) / gamma;
+
+        // inventories putting the optimum right above the middle of the domain
+        for max_amount_in in [46 * eth / 10, 92 * eth / 10] {
+            let trade = cycle.optimal_trade(U256::from(max_amount_in)).unwrap();
+            let amount_in = f64::from(trade.amount_in);
+            let tolerance = 2.0 * max_amount_in as f64 / SIZING_PRECISION as f64;
+            assert!(
+                (amount_in - optimum).abs() <= tolerance,
+                "{} is not within {} of {}",
+                amount_in,
+                tolerance,
+                optimum
+            );
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use alloy::primitives::I256;
+use dashmap::DashMap;
+
+/// What is known of a cycle since it was registered.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CycleStats {
+    /// The last block the cycle was evaluated at
+    pub last_evaluated_block: Option<u64>,
+    /// The best net profit the cycle was evaluated at
+    pub best_profit: Option<I256>,
+    /// The number of transactions of the cycle that reverted
+    pub revert_count: u64,
+}
+
+/// The cycles tracked by a strategy, keyed by their [canonical id](crate::cycle::canonical_id),
+/// along with their stats.
+///
+/// Cycles are registered when the pools they go through are added, and
+/// removed along with their stats when one of their pools goes inactive.
+#[derive(Debug, Clone, Default)]
+pub struct CycleRegistry {
+    cycles: DashMap<String, CycleStats>,
+}
+
+impl CycleRegistry {
+    /// Registers a cycle. Registering a cycle again keeps its stats.
+    pub fn register(&self, id: &str) {
+        self.cycles.entry(id.to_string()).or_default();
+    }
+
+    /// Removes a cycle and its stats.
+    pub fn remove(&self, id: &str) -> Option<CycleStats> {
+        self.cycles.remove(id).map(|(_, stats)| stats)
+    }
+
+    /// Returns true if the cycle is registered.
+    pub fn contains(&self, id: &str) -> bool {
+        self.cycles.contains_key(id)
+    }
+
+    /// Returns the stats of a cycle, if it is registered.
+    pub fn stats(&self, id: &str) -> Option<CycleStats> {
+        self.cycles.get(id).map(|stats| stats.clone())
+    }
+
+    /// Returns the number of registered cycles.
+    pub fn len(&self) -> usize {
+        self.cycles.len()
+    }
+
+    /// Returns true if no cycle is registered.
+    pub fn is_empty(&self) -> bool {
+        self.cycles.is_empty()
+    }
+
+    /// Records that a registered cycle was evaluated at `block`, for `profit`.
+    pub fn record_evaluation(&self, id: &str, block: u64, profit: I256) {
+        if let Some(mut stats) = self.cycles.get_mut(id) {
+            stats.last_evaluated_block = Some(block);
+            stats.best_profit = Some(stats.best_profit.map_or(profit, |best| best.max(profit)));
+        }
+    }
+
+    /// Records that a transaction of a registered cycle reverted.
+    pub fn record_revert(&self, id: &str) {
+        if let Some(mut stats) = self.cycles.get_mut(id) {
+            stats.revert_count += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats_are_kept_until_the_cycle_is_removed() {
+        let registry = CycleRegistry::default();
+        registry.register("cycle");
+        registry.record_evaluation("cycle", 10, I256::try_from(5).unwrap());
+        registry.record_evaluation("cycle", 11, I256::try_from(-3).unwrap());
+        registry.record_revert("cycle");
+        // stats of unregistered cycles are not recorded
+        registry.record_revert("unknown");
+        assert!(!registry.contains("unknown"));
+
+        registry.register("cycle");
+        let stats = registry.stats("cycle").unwrap();
+        assert_eq!(stats.last_evaluated_block, Some(11));
+        assert_eq!(stats.best_profit, Some(I256::try_from(5).unwrap()));
+        assert_eq!(stats.revert_count, 1);
+
+        assert_eq!(registry.remove("cycle"), Some(stats));
+        assert!(registry.is_empty());
+    }
+}
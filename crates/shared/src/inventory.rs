@@ -0,0 +1,285 @@
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+
+use alloy::primitives::{utils::format_units, Address, I256, U256};
+use amms::amm::{AutomatedMarketMaker, AMM};
+use eyre::{eyre, Result};
+
+/// Where the balance of an inventory token is held.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BalanceSource {
+    /// The balance is held by the executor contract, and traded in place
+    #[default]
+    Executor,
+    /// The balance is held by the wallet sending the transactions, and sent
+    /// to the executor contract with each cycle
+    Wallet,
+}
+
+impl FromStr for BalanceSource {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "executor" => Ok(BalanceSource::Executor),
+            "wallet" => Ok(BalanceSource::Wallet),
+            _ => Err(eyre!("Unknown balance source: {}", s)),
+        }
+    }
+}
+
+/// A token cycles can start and end with, and the amounts of it that can be
+/// traded through a single cycle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InventoryToken {
+    pub address: Address,
+    pub decimals: u8,
+    /// Where the balance of the token is held
+    pub source: BalanceSource,
+    /// The smallest amount worth trading through a single cycle
+    pub min_amount_in: U256,
+    /// The largest amount traded through a single cycle
+    pub max_amount_in: U256,
+}
+
+impl InventoryToken {
+    /// Creates a token held by the executor contract, traded up to one whole
+    /// token per cycle.
+    pub fn new(address: Address, decimals: u8) -> Self {
+        Self {
+            address,
+            decimals,
+            source: BalanceSource::default(),
+            min_amount_in: U256::ZERO,
+            max_amount_in: U256::from(10u64).pow(U256::from(decimals)),
+        }
+    }
+
+    /// Sets where the balance of the token is held.
+    pub fn with_source(mut self, source: BalanceSource) -> Self {
+        self.source = source;
+        self
+    }
+
+    /// Sets the smallest amount worth trading through a single cycle.
+    pub fn with_min_amount_in(mut self, min_amount_in: U256) -> Self {
+        self.min_amount_in = min_amount_in;
+        self
+    }
+
+    /// Sets the largest amount traded through a single cycle.
+    pub fn with_max_amount_in(mut self, max_amount_in: U256) -> Self {
+        self.max_amount_in = max_amount_in;
+        self
+    }
+
+    /// Returns the amount of one whole token.
+    pub fn unit(&self) -> U256 {
+        U256::from(10u64).pow(U256::from(self.decimals))
+    }
+
+    /// Returns `amount` in whole tokens.
+    pub fn to_whole(&self, amount: I256) -> Option<f64> {
+        format_units(amount, self.decimals).ok()?.parse().ok()
+    }
+}
+
+/// The tokens cycles can start and end with, keyed by address, along with the
+/// numeraire their profits are compared in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Inventory {
+    numeraire: Address,
+    tokens: BTreeMap<Address, InventoryToken>,
+}
+
+impl Inventory {
+    /// Creates an inventory of the `numeraire` alone.
+    pub fn new(numeraire: InventoryToken) -> Self {
+        Self {
+            numeraire: numeraire.address,
+            tokens: BTreeMap::from([(numeraire.address, numeraire)]),
+        }
+    }
+
+    /// Adds a token to the inventory. See [Inventory::insert].
+    pub fn with_token(mut self, token: InventoryToken) -> Self {
+        self.insert(token);
+        self
+    }
+
+    /// Adds a token to the inventory, replacing the token with the same address.
+    pub fn insert(&mut self, token: InventoryToken) {
+        self.tokens.insert(token.address, token);
+    }
+
+    /// Returns the token profits are compared in.
+    pub fn numeraire(&self) -> &InventoryToken {
+        &self.tokens[&self.numeraire]
+    }
+
+    /// Returns the token at `address`, if it is in the inventory.
+    pub fn get(&self, address: &Address) -> Option<&InventoryToken> {
+        self.tokens.get(address)
+    }
+
+    /// Returns true if the token at `address` is in the inventory.
+    pub fn contains(&self, address: &Address) -> bool {
+        self.tokens.contains_key(address)
+    }
+
+    /// Returns the addresses of the tokens of the inventory.
+    pub fn addresses(&self) -> impl Iterator<Item = Address> + '_ {
+        self.tokens.keys().copied()
+    }
+
+    /// Returns the tokens of the inventory.
+    pub fn tokens(&self) -> impl Iterator<Item = &InventoryToken> {
+        self.tokens.values()
+    }
+
+    /// Reads the prices of the tokens of the inventory in the numeraire from
+    /// `pools`. See [NumerairePrices::from_pools].
+    pub fn prices<'a>(&self, pools: impl IntoIterator<Item = &'a AMM>) -> NumerairePrices {
+        NumerairePrices::from_pools(self.numeraire, self.addresses(), pools)
+    }
+
+    /// Returns the value of `amount` of `token` in whole numeraire tokens, or
+    /// `None` if the token is not in the inventory or has no price.
+    pub fn to_numeraire(
+        &self,
+        token: &Address,
+        amount: I256,
+        prices: &NumerairePrices,
+    ) -> Option<f64> {
+        let amount = self.get(token)?.to_whole(amount)?;
+        Some(amount * prices.get(token)?)
+    }
+
+    /// Returns the amount of `token` worth one whole `native` token, the wrapped
+    /// native token gas is paid in, to convert gas costs to `token`.
+    pub fn native_price(
+        &self,
+        token: &Address,
+        native: &Address,
+        prices: &NumerairePrices,
+    ) -> Option<U256> {
+        let token = self.get(token)?;
+        let native_per_token = prices.get(native)? / prices.get(&token.address)?;
+        let amount = native_per_token * 10f64.powi(token.decimals.into());
+        if !amount.is_finite() || amount > u128::MAX as f64 {
+            return None;
+        }
+        Some(U256::from(amount as u128))
+    }
+}
+
+/// The prices of tokens in whole numeraire tokens per whole token, read from
+/// the pools trading them against the numeraire.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NumerairePrices {
+    prices: HashMap<Address, f64>,
+}
+
+impl NumerairePrices {
+    /// Reads the price of each of `tokens` in `numeraire` from the spot prices
+    /// of the `pools` trading it against the numeraire.
+    ///
+    /// The median price across pools is taken, so that a single stale or
+    /// manipulated pool does not skew it. Tokens without such a pool have no
+    /// price.
+    pub fn from_pools<'a>(
+        numeraire: Address,
+        tokens: impl IntoIterator<Item = Address>,
+        pools: impl IntoIterator<Item = &'a AMM>,
+    ) -> Self {
+        let mut quotes: HashMap<Address, Vec<f64>> = tokens
+            .into_iter()
+            .filter(|token| *token != numeraire)
+            .map(|token| (token, vec![]))
+            .collect();
+        for pool in pools {
+            let tokens = pool.tokens();
+            let [token_a, token_b] = tokens.as_slice() else {
+                continue;
+            };
+            let token = match (*token_a == numeraire, *token_b == numeraire) {
+                (true, false) => token_b,
+                (false, true) => token_a,
+                _ => continue,
+            };
+            let Some(token_quotes) = quotes.get_mut(token) else {
+                continue;
+            };
+            match pool.calculate_price(*token) {
+                Ok(price) if price.is_finite() && price > 0.0 => token_quotes.push(price),
+                _ => {}
+            }
+        }
+
+        let mut prices = HashMap::from([(numeraire, 1.0)]);
+        for (token, mut token_quotes) in quotes {
+            if token_quotes.is_empty() {
+                continue;
+            }
+            token_quotes.sort_by(f64::total_cmp);
+            prices.insert(token, token_quotes[token_quotes.len() / 2]);
+        }
+        Self { prices }
+    }
+
+    /// Returns the price of `token` in whole numeraire tokens per whole token.
+    pub fn get(&self, token: &Address) -> Option<f64> {
+        self.prices.get(token).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amms::amm::uniswap_v2::UniswapV2Pool;
+
+    const WETH: Address = Address::repeat_byte(1);
+    const USDC: Address = Address::repeat_byte(2);
+
+    fn pool(address: u8, reserve_weth: u128, reserve_usdc: u128) -> AMM {
+        AMM::UniswapV2Pool(UniswapV2Pool {
+            address: Address::repeat_byte(address),
+            token_a: WETH,
+            token_a_decimals: 18,
+            token_b: USDC,
+            token_b_decimals: 6,
+            reserve_0: reserve_weth,
+            reserve_1: reserve_usdc,
+            fee: 300,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_amounts_are_valued_in_the_numeraire() {
+        let inventory = Inventory::new(InventoryToken::new(WETH, 18)).with_token(
+            InventoryToken::new(USDC, 6).with_max_amount_in(U256::from(1_000_000_000u64)),
+        );
+        // 1 WETH is worth 2000 USDC in the first pools, and 4000 in an outlier
+        let pools = [
+            pool(10, 10u128.pow(20), 200_000 * 10u128.pow(6)),
+            pool(11, 10u128.pow(18), 2_000 * 10u128.pow(6)),
+            pool(12, 10u128.pow(18), 4_000 * 10u128.pow(6)),
+        ];
+        let prices = inventory.prices(pools.iter());
+        let price = prices.get(&USDC).unwrap();
+        assert!((price - 1.0 / 2_000.0).abs() < 1e-9);
+
+        // 10 USDC of profit is worth 0.005 WETH
+        let profit = I256::try_from(10_000_000).unwrap();
+        let value = inventory.to_numeraire(&USDC, profit, &prices).unwrap();
+        assert!((value - 0.005).abs() < 1e-9);
+        // gas costs paid in ether convert to 2000 USDC per ether
+        let native_price = inventory.native_price(&USDC, &WETH, &prices).unwrap();
+        assert!(native_price.abs_diff(U256::from(2_000_000_000u64)) < U256::from(1_000));
+        assert_eq!(
+            inventory.native_price(&WETH, &WETH, &prices),
+            Some(U256::from(10u64.pow(18)))
+        );
+    }
+}
@@ -2,14 +2,18 @@ pub mod bigint;
 pub mod bindings;
 pub mod bridge;
 pub mod cycle;
+pub mod cycle_registry;
 pub mod evm_helpers;
+pub mod inventory;
 pub mod lend;
 pub mod pool_helpers;
+pub mod pool_history;
 pub mod proxy;
 pub mod route;
 pub mod simulator_foundry;
 pub mod simulator_revm;
 pub mod swap;
+pub mod token_graph;
 pub mod token_helpers;
 pub mod token_manager;
 pub mod utils;
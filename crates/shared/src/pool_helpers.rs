@@ -1,3 +1,4 @@
+use std::collections::HashSet;
 use std::str::FromStr;
 use std::sync::Arc;
 
@@ -232,6 +233,49 @@ pub async fn filter_amms(
     Ok(active_pools)
 }
 
+/// The USD value under which the liquidity of a tracked pool is considered
+/// too low to trade through, and the pool inactive.
+pub const MIN_ACTIVE_POOL_USD: f64 = 1_000.0;
+
+/// Returns the addresses of the AMMs whose liquidity fell under `usd_threshold`.
+///
+/// Only UniswapV2 and UniswapV3 pools can be valued by [`filter_amms`], so
+/// pools of other types are never reported inactive.
+///
+/// # Arguments
+/// * `chain` - The blockchain on which the AMMs exist
+/// * `usd_threshold` - The minimum USD value for an AMM to be active
+/// * `amms` - The AMMs to check
+///
+/// # Returns
+/// A Result containing the addresses of the inactive AMMs or an AMMError
+pub async fn inactive_amms(
+    chain: Chain,
+    usd_threshold: f64,
+    amms: &[AMM],
+) -> Result<Vec<Address>, AMMError> {
+    let candidates = amms
+        .iter()
+        .filter(|amm| matches!(amm, AMM::UniswapV2Pool(_) | AMM::UniswapV3Pool(_)))
+        .cloned()
+        .collect::<Vec<AMM>>();
+    if candidates.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let active = filter_amms(chain, usd_threshold, candidates.clone())
+        .await?
+        .iter()
+        .map(|amm| amm.address())
+        .collect::<HashSet<Address>>();
+
+    Ok(candidates
+        .iter()
+        .map(|amm| amm.address())
+        .filter(|address| !active.contains(address))
+        .collect())
+}
+
 pub async fn filter_univ2_pools<P, N>(
     amms: Vec<AMM>,
     chain: Chain,
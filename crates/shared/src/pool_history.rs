@@ -0,0 +1,113 @@
+use alloy::primitives::Address;
+use amms::amm::{AutomatedMarketMaker, AMM};
+use eyre::{eyre, Result};
+use std::collections::{BTreeMap, HashMap};
+
+/// Number of blocks of pool states kept to roll back chain reorganizations.
+pub const POOL_HISTORY_DEPTH: u64 = 64;
+
+/// A short per-block history of pool states, used to roll pools back to the
+/// fork point of a chain reorganization before replaying the canonical logs.
+///
+/// Rather than full snapshots, the history keeps the state of each pool before
+/// its first update in each block, which is all a rollback needs.
+#[derive(Debug, Clone, Default)]
+pub struct PoolHistory {
+    /// The state of the pools updated in each block, before the update
+    snapshots: BTreeMap<u64, HashMap<Address, AMM>>,
+    /// The oldest block the pools can be rolled back to
+    covered_from: Option<u64>,
+}
+
+impl PoolHistory {
+    /// Records the state of `pool` before it is updated by a log of `block_number`.
+    pub fn record(&mut self, block_number: u64, pool: &AMM) {
+        self.snapshots
+            .entry(block_number)
+            .or_default()
+            .entry(pool.address())
+            .or_insert_with(|| pool.clone());
+
+        let oldest = block_number.saturating_sub(POOL_HISTORY_DEPTH);
+        self.snapshots = self.snapshots.split_off(&oldest);
+        let covered_from = self
+            .covered_from
+            .map_or(block_number, |from| from.max(oldest));
+        self.covered_from = Some(covered_from);
+    }
+
+    /// Forgets the updates made from `from_block` on, and returns the pools they
+    /// touched in their state at the end of block `from_block - 1`.
+    ///
+    /// Fails if the reorg is deeper than the history, in which case the pools
+    /// must be fetched from the chain again.
+    pub fn rollback(&mut self, from_block: u64) -> Result<Vec<AMM>> {
+        match self.covered_from {
+            Some(covered_from) if from_block >= covered_from => {}
+            _ => {
+                return Err(eyre!(
+                    "Cannot roll back to block {}, history starts at {:?}",
+                    from_block,
+                    self.covered_from
+                ))
+            }
+        }
+
+        let mut pools = HashMap::new();
+        // the earliest snapshot of each pool is its state before the fork
+        for (_, snapshot) in self.snapshots.split_off(&from_block).into_iter().rev() {
+            pools.extend(snapshot);
+        }
+        Ok(pools.into_values().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amms::amm::uniswap_v2::UniswapV2Pool;
+
+    fn pool(reserve_0: u128) -> AMM {
+        AMM::UniswapV2Pool(UniswapV2Pool {
+            address: Address::repeat_byte(1),
+            reserve_0,
+            ..Default::default()
+        })
+    }
+
+    fn reserve_0(amm: &AMM) -> u128 {
+        match amm {
+            AMM::UniswapV2Pool(pool) => pool.reserve_0,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_rollback_restores_state_before_fork() {
+        let mut history = PoolHistory::default();
+        // the pool has reserves 1 until block 10, 2 in block 10, then 3 and 4 in block 11
+        history.record(10, &pool(1));
+        history.record(11, &pool(2));
+        history.record(11, &pool(3));
+        history.record(12, &pool(4));
+
+        let pools = history.rollback(11).unwrap();
+        assert_eq!(pools.iter().map(reserve_0).collect::<Vec<_>>(), [2]);
+        // updates of block 10 are kept
+        let pools = history.rollback(10).unwrap();
+        assert_eq!(pools.iter().map(reserve_0).collect::<Vec<_>>(), [1]);
+    }
+
+    #[test]
+    fn test_rollback_deeper_than_history_fails() {
+        let mut history = PoolHistory::default();
+        assert!(history.rollback(10).is_err());
+
+        history.record(10, &pool(1));
+        assert!(history.rollback(9).is_err());
+
+        history.record(10 + POOL_HISTORY_DEPTH + 1, &pool(2));
+        assert!(history.rollback(10).is_err());
+        assert!(history.rollback(11).is_ok());
+    }
+}
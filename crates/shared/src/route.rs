@@ -26,7 +26,7 @@ pub fn simulate_route(
             token_out = token_b;
         }
 
-        amount_out = pool.simulate_swap(token_in, amount_in, token_out).unwrap();
+        amount_out = pool.simulate_swap(token_in, amount_in, token_out)?;
 
         amount_in = amount_out
     }
@@ -0,0 +1,478 @@
+use std::collections::{hash_map::Entry, BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+
+use alloy::primitives::Address;
+use amms::amm::{AutomatedMarketMaker, AMM};
+
+use crate::cycle::{canonical_id, Cycle};
+
+/// A cycle of a [TokenGraph]: the pools it trades through, in order, starting
+/// and ending with its entry token.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CyclePath {
+    /// The canonical id of the cycle, the id of the [Cycle] it builds
+    pub id: String,
+    pub entry_token: Address,
+    pub pools: Vec<Address>,
+}
+
+impl CyclePath {
+    /// Builds the cycle with the current state of its pools, as returned by
+    /// `pool`. Returns `None` if a pool is unknown.
+    pub fn to_cycle(&self, pool: impl Fn(&Address) -> Option<AMM>) -> Option<Cycle> {
+        let amms = self.pools.iter().map(pool).collect::<Option<Vec<_>>>()?;
+        Some(Cycle::with_entry_token(self.entry_token, amms))
+    }
+}
+
+/// A graph with tokens as vertices and pools as edges, maintaining the cycles
+/// through a set of entry tokens as pools are added and removed.
+///
+/// Cycles are enumerated in both directions and up to `max_hops` pools, each
+/// pool being traded at most once. Adding a pool only searches for the cycles
+/// going through it, and removing a pool drops the cycles going through it, so
+/// that the cycles never need to be recomputed from scratch.
+///
+/// For pool sets too large to enumerate every cycle, [TokenGraph::negative_cycles]
+/// only returns the most profitable cycles at spot prices.
+#[derive(Debug, Clone)]
+pub struct TokenGraph {
+    /// The maximum number of pools of a cycle
+    max_hops: usize,
+    /// The tokens cycles start and end with
+    entry_tokens: BTreeSet<Address>,
+    /// The two tokens of each pool
+    pools: HashMap<Address, (Address, Address)>,
+    /// The pools trading each token
+    token_pools: HashMap<Address, BTreeSet<Address>>,
+    /// The cycles going through each pool
+    pool_cycles: HashMap<Address, HashSet<CyclePath>>,
+    /// Every cycle of the graph
+    cycles: HashSet<CyclePath>,
+}
+
+impl TokenGraph {
+    pub fn new(entry_tokens: impl IntoIterator<Item = Address>, max_hops: usize) -> Self {
+        Self {
+            max_hops,
+            entry_tokens: entry_tokens.into_iter().collect(),
+            pools: HashMap::new(),
+            token_pools: HashMap::new(),
+            pool_cycles: HashMap::new(),
+            cycles: HashSet::new(),
+        }
+    }
+
+    /// Returns the number of pools in the graph.
+    pub fn pool_count(&self) -> usize {
+        self.pools.len()
+    }
+
+    /// Returns every cycle of the graph.
+    pub fn cycles(&self) -> impl Iterator<Item = &CyclePath> {
+        self.cycles.iter()
+    }
+
+    /// Returns the cycles going through `pool`.
+    pub fn cycles_through(&self, pool: &Address) -> impl Iterator<Item = &CyclePath> {
+        self.pool_cycles.get(pool).into_iter().flatten()
+    }
+
+    /// Returns the pools trading `token`.
+    pub fn pools_of(&self, token: &Address) -> impl Iterator<Item = &Address> {
+        self.token_pools.get(token).into_iter().flatten()
+    }
+
+    /// Adds a pool to the graph, and returns the cycles going through it.
+    /// Adding a pool already in the graph does nothing.
+    pub fn add_pool(&mut self, amm: &AMM) -> Vec<CyclePath> {
+        let address = amm.address();
+        let tokens = amm.tokens();
+        let [token_a, token_b] = tokens.as_slice() else {
+            return vec![];
+        };
+        if self.pools.contains_key(&address) {
+            return vec![];
+        }
+
+        self.pools.insert(address, (*token_a, *token_b));
+        for token in [token_a, token_b] {
+            self.token_pools.entry(*token).or_default().insert(address);
+        }
+
+        let mut added = vec![];
+        for entry_token in self.entry_tokens.clone() {
+            for pools in self.search(entry_token, Some(address)) {
+                let cycle = self.path(entry_token, pools);
+                if self.insert_cycle(cycle.clone()) {
+                    added.push(cycle);
+                }
+            }
+        }
+        added
+    }
+
+    /// Removes a pool from the graph, and returns the cycles that went through it.
+    pub fn remove_pool(&mut self, address: &Address) -> Vec<CyclePath> {
+        let Some((token_a, token_b)) = self.pools.remove(address) else {
+            return vec![];
+        };
+        for token in [token_a, token_b] {
+            if let Some(pools) = self.token_pools.get_mut(&token) {
+                pools.remove(address);
+                if pools.is_empty() {
+                    self.token_pools.remove(&token);
+                }
+            }
+        }
+
+        let removed: Vec<_> = self
+            .pool_cycles
+            .remove(address)
+            .unwrap_or_default()
+            .into_iter()
+            .collect();
+        for cycle in removed.iter() {
+            self.cycles.remove(cycle);
+            for pool in cycle.pools.iter().filter(|pool| *pool != address) {
+                if let Some(cycles) = self.pool_cycles.get_mut(pool) {
+                    cycles.remove(cycle);
+                }
+            }
+        }
+        removed
+    }
+
+    /// Enumerates every cycle of the graph from scratch, as a reference for
+    /// the cycles maintained incrementally.
+    pub fn find_cycles(&self) -> Vec<CyclePath> {
+        self.entry_tokens
+            .iter()
+            .flat_map(|entry_token| {
+                self.search(*entry_token, None)
+                    .into_iter()
+                    .map(|pools| self.path(*entry_token, pools))
+            })
+            .collect()
+    }
+
+    /// Returns the cycle going through `pools` from `entry_token`.
+    fn path(&self, entry_token: Address, pools: Vec<Address>) -> CyclePath {
+        let mut hops = Vec::with_capacity(pools.len());
+        let mut token_in = entry_token;
+        for pool in pools.iter() {
+            hops.push((*pool, token_in));
+            token_in = self.other_token(pool, token_in).unwrap_or_default();
+        }
+        CyclePath {
+            id: canonical_id(hops),
+            entry_token,
+            pools,
+        }
+    }
+
+    fn insert_cycle(&mut self, cycle: CyclePath) -> bool {
+        if !self.cycles.insert(cycle.clone()) {
+            return false;
+        }
+        for pool in cycle.pools.iter() {
+            self.pool_cycles
+                .entry(*pool)
+                .or_default()
+                .insert(cycle.clone());
+        }
+        true
+    }
+
+    /// Returns the token received for `token_in` from `pool`.
+    fn other_token(&self, pool: &Address, token_in: Address) -> Option<Address> {
+        let (token_a, token_b) = self.pools.get(pool)?;
+        if token_in == *token_a {
+            Some(*token_b)
+        } else if token_in == *token_b {
+            Some(*token_a)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the number of hops from each token to the nearest token of `pool`.
+    fn distances_to(&self, pool: &Address) -> HashMap<Address, usize> {
+        let mut distances = HashMap::new();
+        let mut queue = VecDeque::new();
+        if let Some((token_a, token_b)) = self.pools.get(pool) {
+            for token in [*token_a, *token_b] {
+                distances.insert(token, 0);
+                queue.push_back(token);
+            }
+        }
+        while let Some(token) = queue.pop_front() {
+            let distance = distances[&token];
+            for pool in self.token_pools.get(&token).into_iter().flatten() {
+                let Some(next) = self.other_token(pool, token) else {
+                    continue;
+                };
+                if let Entry::Vacant(entry) = distances.entry(next) {
+                    entry.insert(distance + 1);
+                    queue.push_back(next);
+                }
+            }
+        }
+        distances
+    }
+
+    /// Returns the cycles from `entry_token`, as the pools they go through.
+    /// When `through` is set, only the cycles going through that pool are returned.
+    fn search(&self, entry_token: Address, through: Option<Address>) -> Vec<Vec<Address>> {
+        let distances = through.map(|pool| self.distances_to(&pool));
+        let mut search = Search {
+            graph: self,
+            entry_token,
+            through,
+            distances,
+            path: vec![],
+            cycles: vec![],
+        };
+        search.visit(entry_token);
+        search.cycles
+    }
+
+    /// Searches for the most profitable cycles from each entry token at spot
+    /// prices, with a Bellman-Ford search on the negative log of the exchange
+    /// rates: a cycle is profitable when the sum of its weights is negative.
+    ///
+    /// `rate` returns the amount of the other token received per unit of
+    /// `token_in` from `pool`, fees included. Unlike [TokenGraph::find_cycles],
+    /// at most one cycle is returned per entry token, closing pool and length,
+    /// which keeps the search polynomial in the number of pools.
+    pub fn negative_cycles(
+        &self,
+        rate: impl Fn(&Address, Address) -> Option<f64>,
+    ) -> Vec<CyclePath> {
+        // the weight of trading through each pool, in each direction
+        let mut edges = vec![];
+        for (pool, (token_a, token_b)) in self.pools.iter() {
+            for (token_in, token_out) in [(*token_a, *token_b), (*token_b, *token_a)] {
+                if let Some(rate) = rate(pool, token_in).filter(|rate| *rate > 0.0) {
+                    edges.push((*pool, token_in, token_out, -rate.ln()));
+                }
+            }
+        }
+
+        let mut found = BTreeSet::new();
+        for entry_token in self.entry_tokens.iter() {
+            // the lightest path to each token, and how it was reached, after each round
+            let mut distances = HashMap::from([(*entry_token, 0.0)]);
+            let mut rounds: Vec<BTreeMap<Address, (Address, Address)>> = vec![];
+            for hops in 1..=self.max_hops {
+                let mut next = HashMap::new();
+                let mut previous = BTreeMap::new();
+                for (pool, token_in, token_out, weight) in edges.iter() {
+                    let Some(distance) = distances.get(token_in) else {
+                        continue;
+                    };
+                    let distance = distance + weight;
+                    if token_out == entry_token {
+                        if distance < 0.0 {
+                            let mut pools = self.trace(&rounds, *token_in, hops - 1);
+                            pools.push(*pool);
+                            if is_simple(&pools) {
+                                found.insert(self.path(*entry_token, pools));
+                            }
+                        }
+                        continue;
+                    }
+                    if next
+                        .get(token_out)
+                        .is_none_or(|current| distance < *current)
+                    {
+                        next.insert(*token_out, distance);
+                        previous.insert(*token_out, (*pool, *token_in));
+                    }
+                }
+                distances = next;
+                rounds.push(previous);
+            }
+        }
+        found.into_iter().collect()
+    }
+
+    /// Returns the pools of the path reaching `token` after `hops` rounds.
+    fn trace(
+        &self,
+        rounds: &[BTreeMap<Address, (Address, Address)>],
+        mut token: Address,
+        hops: usize,
+    ) -> Vec<Address> {
+        let mut pools = vec![];
+        for round in rounds[..hops].iter().rev() {
+            let Some((pool, token_in)) = round.get(&token) else {
+                break;
+            };
+            pools.push(*pool);
+            token = *token_in;
+        }
+        pools.reverse();
+        pools
+    }
+}
+
+/// Returns true if no pool is traded twice.
+fn is_simple(pools: &[Address]) -> bool {
+    let unique: HashSet<_> = pools.iter().collect();
+    unique.len() == pools.len()
+}
+
+/// A depth-first search for the cycles from an entry token.
+struct Search<'a> {
+    graph: &'a TokenGraph,
+    entry_token: Address,
+    /// The pool every cycle must go through, if any
+    through: Option<Address>,
+    /// The number of hops from each token to the pool cycles must go through
+    distances: Option<HashMap<Address, usize>>,
+    /// The pools of the current path
+    path: Vec<Address>,
+    cycles: Vec<Vec<Address>>,
+}
+
+impl Search<'_> {
+    fn visit(&mut self, token: Address) {
+        let remaining = self.graph.max_hops - self.path.len();
+        if remaining == 0 {
+            return;
+        }
+        let graph = self.graph;
+        for pool in graph.token_pools.get(&token).into_iter().flatten() {
+            if self.path.contains(pool) {
+                continue;
+            }
+            let Some(next) = graph.other_token(pool, token) else {
+                continue;
+            };
+
+            let uses_through = self
+                .through
+                .is_none_or(|through| through == *pool || self.path.contains(&through));
+            if !uses_through && !self.can_reach_through(next, remaining - 1) {
+                continue;
+            }
+
+            self.path.push(*pool);
+            if next == self.entry_token {
+                if uses_through {
+                    self.cycles.push(self.path.clone());
+                }
+            } else if remaining > 1 {
+                self.visit(next);
+            }
+            self.path.pop();
+        }
+    }
+
+    /// Returns true if the pool cycles must go through can still be traded
+    /// from `token`, with `remaining` hops left.
+    fn can_reach_through(&self, token: Address, remaining: usize) -> bool {
+        match &self.distances {
+            // the pool itself takes a hop
+            Some(distances) => distances.get(&token).is_some_and(|d| *d < remaining),
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amms::amm::uniswap_v2::UniswapV2Pool;
+
+    fn token(byte: u8) -> Address {
+        Address::repeat_byte(byte)
+    }
+
+    fn pool(address: u8, token_a: u8, token_b: u8) -> AMM {
+        AMM::UniswapV2Pool(UniswapV2Pool {
+            address: Address::repeat_byte(address),
+            token_a: token(token_a),
+            token_b: token(token_b),
+            ..Default::default()
+        })
+    }
+
+    fn sorted(cycles: impl IntoIterator<Item = CyclePath>) -> Vec<CyclePath> {
+        let mut cycles: Vec<_> = cycles.into_iter().collect();
+        cycles.sort();
+        cycles
+    }
+
+    #[test]
+    fn test_cycles_are_maintained_incrementally() {
+        // tokens 1 (entry), 2 and 3, with two pools between 1 and 2
+        let pools = [
+            pool(10, 1, 2),
+            pool(11, 2, 3),
+            pool(12, 3, 1),
+            pool(13, 1, 2),
+        ];
+        let mut graph = TokenGraph::new([token(1)], 3);
+        for pool in pools.iter() {
+            graph.add_pool(pool);
+        }
+
+        // the triangle in both directions, with either pool between 1 and 2,
+        // and the two pools between 1 and 2 in both directions
+        assert_eq!(graph.cycles().count(), 6);
+        assert_eq!(sorted(graph.cycles().cloned()), sorted(graph.find_cycles()));
+        assert_eq!(graph.cycles_through(&Address::repeat_byte(11)).count(), 4);
+
+        let removed = graph.remove_pool(&Address::repeat_byte(13));
+        assert_eq!(removed.len(), 4);
+        assert_eq!(graph.cycles().count(), 2);
+        assert_eq!(sorted(graph.cycles().cloned()), sorted(graph.find_cycles()));
+        assert_eq!(graph.cycles_through(&Address::repeat_byte(11)).count(), 2);
+    }
+
+    #[test]
+    fn test_max_hops_limits_cycle_length() {
+        // a square 1 -> 2 -> 3 -> 4 -> 1
+        let mut graph = TokenGraph::new([token(1)], 3);
+        graph.add_pool(&pool(10, 1, 2));
+        graph.add_pool(&pool(11, 2, 3));
+        graph.add_pool(&pool(12, 3, 4));
+        assert!(graph.add_pool(&pool(13, 4, 1)).is_empty());
+
+        let mut graph = TokenGraph::new([token(1)], 4);
+        graph.add_pool(&pool(10, 1, 2));
+        graph.add_pool(&pool(11, 2, 3));
+        graph.add_pool(&pool(12, 3, 4));
+        assert_eq!(graph.add_pool(&pool(13, 4, 1)).len(), 2);
+    }
+
+    #[test]
+    fn test_negative_cycles_are_profitable_at_spot_prices() {
+        let mut graph = TokenGraph::new([token(1)], 3);
+        graph.add_pool(&pool(10, 1, 2));
+        graph.add_pool(&pool(11, 2, 3));
+        graph.add_pool(&pool(12, 3, 1));
+
+        // 1 -> 2 -> 3 -> 1 returns 1.1 units per unit, the reverse direction 0.9
+        let rate = |pool: &Address, token_in: Address| {
+            let forward = match *pool {
+                p if p == Address::repeat_byte(10) => token_in == token(1),
+                p if p == Address::repeat_byte(11) => token_in == token(2),
+                _ => token_in == token(3),
+            };
+            let rate = if forward { 1.1f64 } else { 0.9f64 };
+            Some(rate.powf(1.0 / 3.0))
+        };
+        let cycles = graph.negative_cycles(rate);
+        assert_eq!(cycles.len(), 1);
+        assert_eq!(
+            cycles[0].pools,
+            [
+                Address::repeat_byte(10),
+                Address::repeat_byte(11),
+                Address::repeat_byte(12)
+            ]
+        );
+    }
+}
@@ -11,4 +11,5 @@ pub mod types;
 
 pub mod state;
 
-pub mod profit;
+/// This module tracks the outcome of the transactions submitted for each cycle.
+pub mod outcomes;
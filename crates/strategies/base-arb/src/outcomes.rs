@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+
+use alloy::primitives::{Address, U256};
+use engine::outcome::ActionStatus;
+
+/// Identifies a transaction submitted for a cycle, to correlate its outcome
+/// with the submission.
+pub type SubmissionId = u64;
+
+/// An arbitrage transaction that was submitted and has no final outcome yet.
+#[derive(Debug, Clone)]
+struct InFlight {
+    submission: SubmissionId,
+    token: Address,
+    amount_in: U256,
+    block: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+struct CycleRecord {
+    consecutive_reverts: u32,
+    backed_off_until: Option<u64>,
+    in_flight: Vec<InFlight>,
+}
+
+/// Tracks the outcome of the arbitrage transactions submitted for each cycle.
+///
+/// Cycles that keep reverting are backed off for a number of blocks that
+/// doubles with every further revert, and the inventory committed to
+/// transactions that are still in flight is accounted for, per entry token,
+/// until they settle.
+#[derive(Debug, Clone)]
+pub struct CycleOutcomes {
+    /// Consecutive reverts after which a cycle is backed off
+    max_reverts: u32,
+    /// Blocks a cycle is backed off for after `max_reverts` reverts
+    cooldown_blocks: u64,
+    /// Blocks after which an in-flight transaction without outcome is forgotten
+    in_flight_timeout_blocks: u64,
+    cycles: HashMap<String, CycleRecord>,
+    gas_spent: U256,
+    next_submission: SubmissionId,
+}
+
+impl Default for CycleOutcomes {
+    fn default() -> Self {
+        Self {
+            max_reverts: 3,
+            cooldown_blocks: 10,
+            in_flight_timeout_blocks: 20,
+            cycles: HashMap::new(),
+            gas_spent: U256::ZERO,
+            next_submission: 0,
+        }
+    }
+}
+
+impl CycleOutcomes {
+    /// Sets the number of consecutive reverts after which a cycle is backed off.
+    pub fn with_max_reverts(mut self, max_reverts: u32) -> Self {
+        self.max_reverts = max_reverts.max(1);
+        self
+    }
+
+    /// Sets the number of blocks a cycle is backed off for.
+    pub fn with_cooldown_blocks(mut self, blocks: u64) -> Self {
+        self.cooldown_blocks = blocks;
+        self
+    }
+
+    /// Records a transaction submitted for a cycle at `block`, trading
+    /// `amount_in` of `token`. Returns the id to report its outcome with,
+    /// carried by the submitted action.
+    pub fn record_submitted(
+        &mut self,
+        cycle_id: &str,
+        token: Address,
+        amount_in: U256,
+        block: u64,
+    ) -> SubmissionId {
+        let submission = self.next_submission;
+        self.next_submission += 1;
+        let record = self.cycles.entry(cycle_id.to_string()).or_default();
+        record.in_flight.push(InFlight {
+            submission,
+            token,
+            amount_in,
+            block,
+        });
+        submission
+    }
+
+    /// Records a status reported for the transaction `submission` of a cycle.
+    pub fn record_outcome(
+        &mut self,
+        cycle_id: &str,
+        submission: SubmissionId,
+        status: &ActionStatus,
+        block: u64,
+    ) {
+        if !status.is_final() {
+            return;
+        }
+
+        if let Some(receipt) = status.receipt() {
+            let fee = U256::from(receipt.gas_used) * U256::from(receipt.effective_gas_price);
+            self.gas_spent += fee;
+        }
+
+        let Some(record) = self.cycles.get_mut(cycle_id) else {
+            return;
+        };
+        record.in_flight.retain(|tx| tx.submission != submission);
+
+        match status {
+            ActionStatus::Mined { .. } => {
+                record.consecutive_reverts = 0;
+                record.backed_off_until = None;
+            }
+            ActionStatus::Reverted { .. } => {
+                record.consecutive_reverts += 1;
+                if record.consecutive_reverts >= self.max_reverts {
+                    let exponent = (record.consecutive_reverts - self.max_reverts).min(16);
+                    let cooldown = self.cooldown_blocks.saturating_mul(1 << exponent);
+                    record.backed_off_until = Some(block.saturating_add(cooldown));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Forgets in-flight transactions submitted too long before `block`,
+    /// whose outcome will likely never be reported.
+    pub fn expire(&mut self, block: u64) {
+        let timeout = self.in_flight_timeout_blocks;
+        for record in self.cycles.values_mut() {
+            record
+                .in_flight
+                .retain(|tx| tx.block.saturating_add(timeout) > block);
+        }
+        self.cycles.retain(|_, record| {
+            !record.in_flight.is_empty()
+                || record.consecutive_reverts > 0
+                || record.backed_off_until.is_some_and(|until| until > block)
+        });
+    }
+
+    /// Returns true if the cycle reverted too many times and should not be
+    /// submitted at `block`.
+    pub fn is_backed_off(&self, cycle_id: &str, block: u64) -> bool {
+        self.cycles
+            .get(cycle_id)
+            .and_then(|record| record.backed_off_until)
+            .is_some_and(|until| block < until)
+    }
+
+    /// Returns true if a transaction submitted for the cycle has no final outcome yet.
+    pub fn is_in_flight(&self, cycle_id: &str) -> bool {
+        self.cycles
+            .get(cycle_id)
+            .is_some_and(|record| !record.in_flight.is_empty())
+    }
+
+    /// Returns the amount of `token` committed to transactions that are still
+    /// in flight.
+    pub fn committed(&self, token: &Address) -> U256 {
+        self.cycles
+            .values()
+            .flat_map(|record| record.in_flight.iter())
+            .filter(|tx| tx.token == *token)
+            .map(|tx| tx.amount_in)
+            .sum()
+    }
+
+    /// Returns the gas fees paid by every mined transaction, in wei.
+    pub fn gas_spent(&self) -> U256 {
+        self.gas_spent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outcomes_settle_their_own_submission() {
+        let mut outcomes = CycleOutcomes::default();
+        let token = Address::repeat_byte(1);
+        let amount_in = U256::from(100);
+
+        let expired = outcomes.record_submitted("cycle", token, amount_in, 10);
+        outcomes.expire(30);
+        assert!(!outcomes.is_in_flight("cycle"));
+
+        // the same trade is submitted again, and the outcome of the expired
+        // submission only arrives afterwards
+        let submission = outcomes.record_submitted("cycle", token, amount_in, 30);
+        assert_ne!(submission, expired);
+        let dropped = ActionStatus::Dropped {
+            reason: "late".to_string(),
+        };
+        outcomes.record_outcome("cycle", expired, &dropped, 31);
+        assert!(outcomes.is_in_flight("cycle"));
+        assert_eq!(outcomes.committed(&token), amount_in);
+
+        outcomes.record_outcome("cycle", submission, &dropped, 32);
+        assert!(!outcomes.is_in_flight("cycle"));
+        assert_eq!(outcomes.committed(&token), U256::ZERO);
+    }
+}
@@ -3,47 +3,60 @@ use alloy::providers::Provider;
 use amms::amm::{AutomatedMarketMaker, AMM};
 use amms::sync;
 use dashmap::DashMap;
+use engine::collectors::pool_set::PoolSet;
 use eyre::{eyre, Result};
 use shared::cycle::Cycle;
-use std::collections::{HashMap, HashSet};
+use shared::cycle_registry::CycleRegistry;
+use shared::inventory::{Inventory, InventoryToken, NumerairePrices};
+use shared::pool_history::PoolHistory;
+use shared::token_graph::{CyclePath, TokenGraph};
+use std::collections::BTreeSet;
 use std::sync::Arc;
 use tracing::info;
 
+/// Maximum number of pools in a trading cycle
+const MAX_CYCLE_HOPS: usize = 3;
+
 /// State represents the core state management for arbitrage opportunities
 ///
 /// Structure visualization:
 /// ```text
 /// State
 /// ├── Provider (handles blockchain interaction)
-/// ├── Pools (active trading pools)
+/// ├── Pools (active trading pools, whose addresses scope the log subscriptions)
 /// │   ├── Pool 1
 /// │   ├── Pool 2
 /// │   └── Pool N
-/// ├── Cycles (trading cycles)
+/// ├── Pool history (recent pool states, to roll back reorgs)
+/// ├── Cycles (trading cycles, and their stats in the registry)
 /// │   ├── Cycle 1: Token A -> Token B -> Token C -> Token A
 /// │   └── Cycle 2: Token X -> Token Y -> Token X
-/// └── Inventory (tokens that are allowed to be traded)
+/// └── Inventory (tokens that are allowed to be traded, and their trade sizes)
 /// ```
 #[derive(Debug, Clone)]
 pub struct State<P: Provider> {
     provider: Arc<P>,
     pub block_number: u64,
     pub pools: DashMap<Address, AMM>,
-    pub pools_cycles_map: DashMap<Address, HashSet<String>>, // Maps pool addresses to cycles they're part of
-    pub cycles: HashMap<String, Cycle>,                      // All valid trading cycles identified
-    pub inventory: Vec<Address>,
+    pub pool_set: PoolSet, // addresses of the pools, shared with the log collectors
+    pub pool_history: PoolHistory,
+    pub graph: TokenGraph, // Trading cycles through the inventory, maintained as pools are added
+    pub registry: CycleRegistry, // Stats of the cycles of the graph
+    pub inventory: Inventory,
 }
 
 impl<P: Provider> State<P> {
     /// Creates a new State instance with the given provider and inventory of tradeable tokens
-    pub fn new(provider: Arc<P>, inventory: Vec<Address>) -> Self {
+    pub fn new(provider: Arc<P>, inventory: Inventory) -> Self {
         Self {
             provider,
+            graph: TokenGraph::new(inventory.addresses(), MAX_CYCLE_HOPS),
+            registry: CycleRegistry::default(),
             inventory,
             block_number: 0,
             pools: DashMap::new(),
-            pools_cycles_map: DashMap::new(),
-            cycles: HashMap::new(),
+            pool_set: PoolSet::new(),
+            pool_history: PoolHistory::default(),
         }
     }
 
@@ -53,169 +66,129 @@ impl<P: Provider> State<P> {
         }
     }
 
-    /// Recursively finds all possible trading cycles between token_in and token_out
-    /// Parameters:
-    /// - pairs: Available trading pairs
-    /// - token_in: Starting token
-    /// - token_out: Target token
-    /// - max_hops: Maximum number of trades allowed
-    /// - current_pairs: Accumulated trading pairs in current path
-    /// - circles: Accumulated valid cycles found
-    /// - seen: Set of visited pool addresses to prevent loops
-    pub fn get_cycles(
-        pairs: &[AMM],
-        token_in: Address,
-        token_out: Address,
-        max_hops: i32,
-        current_pairs: &Vec<AMM>,
-        circles: &mut Vec<Cycle>,
-        seen: &mut HashSet<Address>,
-    ) -> Result<Vec<Cycle>> {
-        let mut circles_copy: Vec<Cycle> = circles.clone();
-
-        for pair in pairs {
-            let address = pair.address();
-            let tokens = pair.tokens();
-            let [token_a, token_b] = tokens.as_slice() else {
-                todo!()
-            };
-            if seen.contains(&address) {
-                continue;
+    /// Updates the active pools list, and the cycles going through the new pools
+    pub fn set_pools(&mut self, amms: Vec<AMM>) {
+        self.pool_set.insert(amms.iter().map(|amm| amm.address()));
+        for amm in amms {
+            for cycle in self.graph.add_pool(&amm) {
+                self.registry.register(&cycle.id);
             }
+            self.pools.insert(amm.address(), amm);
+        }
+    }
 
-            // Determine which token would be received from this pair
-            let temp_out: Address;
-            if token_in == *token_a {
-                temp_out = *token_b;
-            } else if token_in == *token_b {
-                temp_out = *token_a;
-            } else {
-                continue;
+    /// Removes pools that went inactive, along with the cycles going through
+    /// them and their stats
+    pub fn remove_pools(&mut self, addresses: &[Address]) {
+        self.pool_set.remove(addresses.iter().copied());
+        for address in addresses {
+            self.pools.remove(address);
+            for cycle in self.graph.remove_pool(address) {
+                self.registry.remove(&cycle.id);
             }
+        }
+    }
 
-            let mut new_seen = seen.clone();
-            new_seen.insert(address);
+    /// Builds a cycle of the graph with the current state of its pools
+    fn build_cycle(&self, path: &CyclePath) -> Option<Cycle> {
+        path.to_cycle(|address| self.pools.get(address).map(|pool| pool.value().clone()))
+    }
 
-            // If we've found a path back to our target token, we've found a cycle
-            if temp_out == token_out {
-                let mut pools = current_pairs.clone();
-                pools.push(pair.clone());
-                let cycle = Cycle::new(pools);
-                circles_copy.push(cycle);
-            } else if max_hops > 1 {
-                // Continue searching for longer paths if we haven't hit max_hops
-                let mut new_pairs: Vec<AMM> = current_pairs.clone();
-                new_pairs.push(pair.clone());
-                circles_copy = Self::get_cycles(
-                    pairs,
-                    temp_out,
-                    token_out,
-                    max_hops - 1,
-                    &new_pairs,
-                    &mut circles_copy,
-                    &mut new_seen,
-                )?;
+    /// Adds a token to the inventory, or updates its trade sizes. The cycles
+    /// through the new entry token are searched among the tracked pools.
+    pub fn set_inventory_token(&mut self, token: InventoryToken) {
+        let is_new = !self.inventory.contains(&token.address);
+        self.inventory.insert(token);
+        if !is_new {
+            return;
+        }
+        // the entry tokens of the graph are fixed, so it is rebuilt with its pools
+        self.graph = TokenGraph::new(self.inventory.addresses(), MAX_CYCLE_HOPS);
+        for pool in self.pools.iter() {
+            for cycle in self.graph.add_pool(pool.value()) {
+                self.registry.register(&cycle.id);
             }
         }
+    }
 
-        Ok(circles_copy)
+    /// Returns the profit of trading one whole entry token through `cycle`, as
+    /// a fraction of the input, whatever the decimals of the token.
+    fn profit_perc(&self, cycle: &Cycle) -> f64 {
+        self.inventory
+            .get(&cycle.entry_token)
+            .map_or(f64::NEG_INFINITY, |token| cycle.profit_perc(token.unit()))
     }
 
-    /// Updates the active pools list
-    pub fn set_pools(&self, amms: Vec<AMM>) {
-        for amm in amms {
-            self.pools.insert(amm.address(), amm);
+    /// Returns the prices of the inventory tokens in the numeraire, read from
+    /// the tracked pools trading them against it.
+    pub fn prices(&self) -> NumerairePrices {
+        let numeraire = self.inventory.numeraire().address;
+        let pools: Vec<AMM> = self
+            .graph
+            .pools_of(&numeraire)
+            .filter_map(|address| self.pools.get(address).map(|pool| pool.value().clone()))
+            .collect();
+        self.inventory.prices(pools.iter())
+    }
+
+    /// Records the state of a pool before it is updated by a log of `block_number`,
+    /// so that the update can be rolled back if the block is reorged out.
+    pub fn record_pool_update(&mut self, block_number: u64, pool_address: Address) {
+        if let Some(pool) = self.pools.get(&pool_address) {
+            self.pool_history.record(block_number, pool.value());
         }
     }
 
+    /// Rolls the pools back to their state at the end of block `from_block - 1`,
+    /// the fork point of a reorg, and returns the restored pools. The logs of
+    /// the canonical branch are then applied as usual.
+    pub fn rollback(&mut self, from_block: u64) -> Result<Vec<AMM>> {
+        let pools = self.pool_history.rollback(from_block)?;
+        for pool in pools.iter() {
+            self.pools.insert(pool.address(), pool.clone());
+        }
+        self.block_number = from_block.saturating_sub(1);
+        Ok(pools)
+    }
+
     /// Returns cycles that contain any of the provided AMMs
     pub fn get_updated_cycles(&self, amms: Vec<AMM>) -> Result<Vec<Cycle>> {
-        let mut cycles = vec![];
+        let mut paths = BTreeSet::new();
         for amm in amms {
-            let pool_address = amm.address();
-            let pool_cycles = self.pools_cycles_map.get(&pool_address);
-            if let Some(pool_cycles) = pool_cycles {
-                for cycle_id in pool_cycles.iter() {
-                    let cycle = self
-                        .cycles
-                        .get(cycle_id)
-                        .ok_or_else(|| eyre!("Cycle not found with id: {}", cycle_id))?
-                        .clone();
-                    cycles.push(cycle);
-                }
-            }
+            paths.extend(self.graph.cycles_through(&amm.address()));
         }
 
-        Ok(cycles)
+        paths
+            .into_iter()
+            .map(|path| {
+                self.build_cycle(path)
+                    .ok_or_else(|| eyre!("Cycle through unknown pools: {:?}", path.pools))
+            })
+            .collect()
     }
 
-    /// Updates all possible trading cycles in the system
-    /// Returns cycles that meet the profit threshold (-0.50%)
+    /// Returns the trading cycles of the tracked pools that meet the profit
+    /// threshold (-0.50%)
     pub fn update_cycles(&mut self) -> Result<Vec<Cycle>> {
-        let mut all_cycles = vec![];
-
-        // Find cycles starting from each token in inventory
-        for token in self.inventory.iter() {
-            let pools = self
-                .pools
-                .iter()
-                .map(|entry| entry.value().clone())
-                .collect::<Vec<_>>();
-
-            let cycles = Self::get_cycles(
-                &pools,
-                token.clone(),
-                token.clone(),
-                3, // Maximum of 3 hops in a cycle
-                &vec![],
-                &mut vec![],
-                &mut HashSet::new(),
-            )?;
-
-            all_cycles.extend(cycles);
-        }
-
-        tracing::info!("Found {} potential cycles", all_cycles.len());
+        let cycles: Vec<Cycle> = self
+            .graph
+            .cycles()
+            .filter_map(|path| self.build_cycle(path))
+            .collect();
+        tracing::info!("Found {} potential cycles", cycles.len());
 
         // Filter cycles by profit threshold
         let profit_threshold = -0.50;
-
-        // Update the pools_cycles_map with new cycles
-        for cycle in all_cycles.clone() {
-            let profit_perc = cycle.get_profit_perc();
-            if profit_perc > profit_threshold {
-                let id = cycle.id.clone();
-                self.cycles.insert(id.clone(), cycle.clone());
-
-                for pool in &cycle.amms {
-                    let pool_address = pool.address();
-                    let pool_cycles = self.pools_cycles_map.get_mut(&pool_address);
-                    if let Some(mut c) = pool_cycles {
-                        c.insert(id.clone());
-                    } else {
-                        self.pools_cycles_map
-                            .insert(pool_address, HashSet::from([id.clone()]));
-                    }
-                }
-            } else {
-                tracing::info!("Cycle {} has no profit", cycle);
-                let amms = cycle.amms.clone();
-                for amm in amms {
-                    let pool_address = amm.address();
-                    let pool_cycles = self.pools_cycles_map.get_mut(&pool_address);
-                    if let Some(mut c) = pool_cycles {
-                        c.remove(&cycle.id);
-                    }
+        let profitable_cycles = cycles
+            .into_iter()
+            .filter(|cycle| {
+                let profitable = self.profit_perc(cycle) > profit_threshold;
+                if !profitable {
+                    tracing::info!("Cycle {} has no profit", cycle);
                 }
-                self.cycles.remove(&cycle.id);
-            }
-        }
-
-        let profitable_cycles: Vec<Cycle> =
-            self.cycles.values().map(|cycle| cycle.clone()).collect();
-
-        // info!("Found {} cycles", self.cycles.len());
-        // info!("Nb cycles: {}", nb_cycles);
+                profitable
+            })
+            .collect();
         Ok(profitable_cycles)
     }
 
@@ -278,3 +251,52 @@ impl<P: Provider> State<P> {
             .collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::providers::ProviderBuilder;
+    use amms::amm::uniswap_v2::UniswapV2Pool;
+
+    fn pool(address: u8, token_a: u8, token_b: u8) -> AMM {
+        AMM::UniswapV2Pool(UniswapV2Pool {
+            address: Address::repeat_byte(address),
+            token_a: Address::repeat_byte(token_a),
+            token_b: Address::repeat_byte(token_b),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_removed_pools_drop_their_cycles() {
+        let provider = ProviderBuilder::new().on_http("http://localhost:8545".parse().unwrap());
+        let inventory = Inventory::new(InventoryToken::new(Address::repeat_byte(1), 18));
+        let mut state = State::new(Arc::new(provider), inventory);
+        // two pools between the entry token 1 and token 2, and a triangle through token 3
+        state.set_pools(vec![
+            pool(10, 1, 2),
+            pool(11, 1, 2),
+            pool(12, 2, 3),
+            pool(13, 3, 1),
+        ]);
+        let removed = Address::repeat_byte(10);
+        let cycles: Vec<CyclePath> = state.graph.cycles_through(&removed).cloned().collect();
+        assert!(!cycles.is_empty());
+        assert!(cycles
+            .iter()
+            .all(|cycle| state.registry.contains(&cycle.id)));
+
+        state.remove_pools(&[removed]);
+
+        assert!(!state.pools.contains_key(&removed));
+        assert!(!state.pool_set.addresses().contains(&removed));
+        assert_eq!(state.pool_set.len(), 3);
+        assert_eq!(state.graph.cycles_through(&removed).count(), 0);
+        assert!(cycles
+            .iter()
+            .all(|cycle| !state.registry.contains(&cycle.id)));
+        // the cycles through the remaining pools are kept
+        assert!(state.graph.cycles().count() > 0);
+        assert_eq!(state.graph.cycles().count(), state.registry.len());
+    }
+}
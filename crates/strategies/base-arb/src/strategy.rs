@@ -1,9 +1,10 @@
-use super::types::{Action, Event};
+use super::types::{Action, CycleTx, Event};
+use crate::outcomes::CycleOutcomes;
 use crate::state::State;
 use addressbook::Addressbook;
 use alloy::primitives::aliases::U24;
 use alloy::primitives::utils::parse_units;
-use alloy::primitives::{Bytes, I256, U256};
+use alloy::primitives::{Bytes, U256};
 use alloy::providers::Provider;
 use alloy::{primitives::Address, rpc::types::Log};
 use alloy_chains::{Chain, NamedChain};
@@ -29,15 +30,23 @@ use db::{
     },
 };
 use diesel::PgConnection;
+use engine::collectors::pool_set::PoolSet;
 use engine::executors::encoded_tx_executor::SubmitEncodedTx;
+use engine::executors::gas_bidder::GasBidder;
+use engine::outcome::{ActionOutcome, ActionStatus};
 use engine::types::Strategy;
 use eyre::Result;
-use shared::cycle::{get_most_profitable_cycles, Cycle};
-use shared::pool_helpers::db_pools_to_amms;
+use provider::get_default_signer;
+use shared::cycle::{CostModel, Cycle, ProfitBreakdown};
+use shared::inventory::{BalanceSource, Inventory, InventoryToken};
+use shared::pool_helpers::{db_pools_to_amms, inactive_amms, MIN_ACTIVE_POOL_USD};
+use shared::token_helpers::get_token_balance;
+use std::collections::HashMap;
 use std::env;
 use std::str::FromStr;
 use std::sync::Arc;
 use tracing::{debug, info, warn};
+use tx_executor::bindings::batchexecutor::BatchExecutor::BatchExecutorInstance;
 use tx_executor::encoder::BatchExecutorClient;
 // use tx_executor::{get_default_encoder, BasicEncoder};
 use tx_simulator::simulator::TxSimulatorClient;
@@ -50,8 +59,22 @@ pub struct BaseArb<P: Provider> {
     pub state: State<P>,
     pub db_url: String,
     pub simulator: Option<TxSimulatorClient<P>>,
+    pub outcomes: CycleOutcomes,
+    /// The cycles updated by the logs of the current block
+    pub block_cycles: HashMap<String, Cycle>,
+    /// The wrapped native token, which gas costs are converted from
+    pub weth: Address,
+    /// The USD value under which the liquidity of a pool is too low to trade
+    /// through, and the pool is removed along with its cycles
+    pub min_pool_usd: f64,
+    /// The balances of the inventory tokens held by their source, as of the
+    /// last sync. Tokens without a known balance are not traded.
+    pub balances: HashMap<Address, U256>,
 }
 
+/// Maximum number of cycles simulated on-chain, and executed, per block
+const MAX_CYCLES_PER_BLOCK: usize = 3;
+
 impl<P: Provider> BaseArb<P> {
     pub fn new(chain: Chain, client: Arc<P>, db_url: String) -> Self {
         let addressbook = Addressbook::load().expect("Failed to load addressbook");
@@ -59,6 +82,13 @@ impl<P: Provider> BaseArb<P> {
         let weth = addressbook
             .get_weth(&chain_name)
             .expect("Failed to get WETH address");
+        let weth_inventory = InventoryToken::new(weth, 18)
+            .with_source(BalanceSource::Wallet)
+            .with_max_amount_in(
+                parse_units("0.001", 18)
+                    .expect("Failed to parse max amount in")
+                    .into(),
+            );
 
         Self {
             chain,
@@ -66,11 +96,50 @@ impl<P: Provider> BaseArb<P> {
             client: client.clone(),
             encoder: None,
             simulator: None,
-            state: State::new(client.clone(), vec![weth]),
+            // cycles entering with WETH are funded by wrapping ether from the
+            // wallet, and profits of every entry token are compared in WETH
+            state: State::new(client.clone(), Inventory::new(weth_inventory)),
             db_url,
+            outcomes: CycleOutcomes::default(),
+            block_cycles: HashMap::new(),
+            weth,
+            min_pool_usd: MIN_ACTIVE_POOL_USD,
+            balances: HashMap::new(),
         }
     }
 
+    /// Sets the largest amount of WETH traded through a single cycle. Cycles are
+    /// traded at the size maximizing their profit, up to this amount and to the
+    /// balance of the wallet.
+    pub fn with_max_amount_in(mut self, max_amount_in: U256) -> Self {
+        let weth = self.state.inventory.numeraire().clone();
+        self.state
+            .set_inventory_token(weth.with_max_amount_in(max_amount_in));
+        self
+    }
+
+    /// Adds a token to the inventory, so that the cycles starting and ending
+    /// with it are traded, within its trade sizes and the balance held by its
+    /// source. Tokens held by the wallet
+    /// can only be traded when they are WETH, wrapped from ether.
+    pub fn with_inventory_token(mut self, token: InventoryToken) -> Self {
+        self.state.set_inventory_token(token);
+        self
+    }
+
+    /// Sets the USD value under which the liquidity of a pool is too low to
+    /// trade through. Pools under it are removed when the state is synced.
+    pub fn with_min_pool_usd(mut self, min_pool_usd: f64) -> Self {
+        self.min_pool_usd = min_pool_usd;
+        self
+    }
+
+    /// Returns the set of pools tracked by the strategy, to scope the log
+    /// collectors to. It is filled once the strategy loads its pools.
+    pub fn pool_set(&self) -> PoolSet {
+        self.state.pool_set.clone()
+    }
+
     async fn load_encoder(&mut self) -> Result<()> {
         let named_chain = NamedChain::try_from(self.chain).unwrap();
         let executor_address = Address::from_str(&env::var("EXECUTOR_ADDRESS").unwrap()).unwrap();
@@ -153,6 +222,66 @@ impl<P: Provider> BaseArb<P> {
         Ok(())
     }
 
+    /// Removes the pools whose liquidity fell under the minimum value, along
+    /// with the cycles going through them.
+    async fn remove_inactive_pools(&mut self) {
+        let pools = self.state.get_all_pools().await;
+        match inactive_amms(self.chain, self.min_pool_usd, &pools).await {
+            Ok(inactive) if !inactive.is_empty() => {
+                info!("Removing {} inactive pools", inactive.len());
+                self.state.remove_pools(&inactive);
+                self.block_cycles
+                    .retain(|id, _| self.state.registry.contains(id));
+            }
+            Ok(_) => {}
+            Err(e) => warn!("Failed to check the liquidity of the pools: {}", e),
+        }
+    }
+
+    /// Queries the balance of each inventory token from where it is held, so
+    /// that cycles are never sized above it. Tokens whose balance cannot be
+    /// queried keep their last known balance.
+    async fn update_balances(&mut self) {
+        let executor_address = Address::from_str(&env::var("EXECUTOR_ADDRESS").unwrap())
+            .expect("Failed to parse executor address");
+        let wallet = get_default_signer().address();
+        for token in self.state.inventory.tokens() {
+            let balance: Result<U256> = match token.source {
+                // WETH held by the wallet is wrapped from its ether
+                BalanceSource::Wallet if token.address == self.weth => {
+                    self.client.get_balance(wallet).await.map_err(Into::into)
+                }
+                BalanceSource::Wallet => {
+                    get_token_balance(self.client.clone(), token.address, wallet).await
+                }
+                BalanceSource::Executor => {
+                    get_token_balance(self.client.clone(), token.address, executor_address).await
+                }
+            };
+            match balance {
+                Ok(balance) => {
+                    self.balances.insert(token.address, balance);
+                }
+                Err(e) => warn!("Failed to get balance of {}: {}", token.address, e),
+            }
+        }
+    }
+
+    /// Returns the amount of `token` a cycle can still enter with: its largest
+    /// trade size capped by its balance, less the amounts committed to the
+    /// transactions in flight.
+    fn available(&self, token: &InventoryToken) -> U256 {
+        let balance = self
+            .balances
+            .get(&token.address)
+            .copied()
+            .unwrap_or_default();
+        token
+            .max_amount_in
+            .min(balance)
+            .saturating_sub(self.outcomes.committed(&token.address))
+    }
+
     fn log_arbitrage_cycles(&self, cycles: &[impl std::fmt::Display]) {
         for cycle in cycles {
             info!("{}", cycle);
@@ -203,23 +332,37 @@ impl<P: Provider> BaseArb<P> {
 
         let executor_address = Address::from_str(&env::var("EXECUTOR_ADDRESS").unwrap())
             .expect("Failed to parse executor address");
-        let weth = self
-            .addressbook
-            .get_weth(&self.chain.named().unwrap())
-            .expect("Failed to get WETH address");
-
-        encoder
-            .add_wrap_eth(weth, amount_in)
-            .add_transfer_erc20(weth, executor_address, amount_in)
-            .add_swap(
-                first_amm.exchange_name(),
-                token_in,
-                token_out,
-                amount_in,
-                None,
-                stable,
-                fee,
-            );
+        let source = self
+            .state
+            .inventory
+            .get(&token_first)
+            .map(|token| token.source)
+            .ok_or_else(|| eyre::eyre!("Token {} is not in the inventory", token_first))?;
+
+        // balances held by the executor are traded in place
+        match source {
+            BalanceSource::Wallet if token_first == self.weth => {
+                encoder
+                    .add_wrap_eth(self.weth, amount_in)
+                    .add_transfer_erc20(self.weth, executor_address, amount_in);
+            }
+            BalanceSource::Wallet => {
+                return Err(eyre::eyre!(
+                    "Wallet balances of {} cannot be sent to the executor",
+                    token_first
+                ))
+            }
+            BalanceSource::Executor => {}
+        }
+        encoder.add_swap(
+            first_amm.exchange_name(),
+            token_in,
+            token_out,
+            amount_in,
+            None,
+            stable,
+            fee,
+        );
 
         let mut last_token = token_out;
 
@@ -254,7 +397,7 @@ impl<P: Provider> BaseArb<P> {
         }
 
         encoder.require_profitable(
-            weth,
+            token_first,
             U256::from(amount_in) * U256::from(50) / U256::from(100),
         );
 
@@ -279,6 +422,7 @@ impl<P: Provider + Clone> Strategy<Event, Action> for BaseArb<P> {
         info!("Loaded encoder 📦");
         self.load_simulator().await?;
         info!("Loaded simulator 📡");
+        self.update_balances().await;
 
         let arb_cycles = self.state.update_cycles()?;
         self.log_arbitrage_cycles(&arb_cycles);
@@ -289,6 +433,8 @@ impl<P: Provider + Clone> Strategy<Event, Action> for BaseArb<P> {
     async fn sync_state(&mut self) -> Result<()> {
         info!("Syncing state... 🔄");
         self.state.update_pools().await?;
+        self.remove_inactive_pools().await;
+        self.update_balances().await;
         Ok(())
     }
 
@@ -304,73 +450,280 @@ impl<P: Provider + Clone> Strategy<Event, Action> for BaseArb<P> {
                 {
                     warn!("Failed to update block number: {}", e);
                 }
+                self.outcomes.expire(self.state.block_number);
             }
             Event::Log(log) => {
-                updated_cycles = self.handle_log_event(log).await;
+                // cycles are evaluated once per block, after every log of the block was applied
+                for cycle in self.handle_log_event(log).await {
+                    self.block_cycles.insert(cycle.id.clone(), cycle);
+                }
+            }
+            Event::BlockEnd(_) => {
+                updated_cycles = std::mem::take(&mut self.block_cycles)
+                    .into_values()
+                    .collect();
+                info!("Updated cycles: {:?}", updated_cycles.len());
+                self.log_arbitrage_cycles(&updated_cycles);
+                info!("--------------------------------");
+            }
+            Event::CollectorRestarted(restarted) => {
+                warn!(
+                    "{} restarted (last block: {:?}) 🔌",
+                    restarted.collector, restarted.last_block
+                );
+            }
+            Event::Reorg(reorg) => {
+                warn!(
+                    "Reorg of blocks {}..={} detected by {} ⛓️",
+                    reorg.from_block, reorg.to_block, reorg.collector
+                );
+                // the cycles were updated with logs of the abandoned branch
+                self.block_cycles.clear();
+                self.handle_reorg(reorg.from_block).await;
             }
             _ => {}
         }
 
-        info!("Updated cycles: {:?}", updated_cycles.len());
-        self.log_arbitrage_cycles(&updated_cycles);
-        info!("--------------------------------");
+        if updated_cycles.is_empty() {
+            return actions;
+        }
+        let gas_price = match self.client.get_gas_price().await {
+            Ok(gas_price) => gas_price,
+            Err(e) => {
+                warn!("Failed to get gas price: {}", e);
+                return actions;
+            }
+        };
+
+        let block_number = self.state.block_number;
+        let prices = self.state.prices();
+        let mut candidates = vec![];
+        for cycle in updated_cycles {
+            if self.outcomes.is_backed_off(&cycle.id, block_number) {
+                debug!("Skipping backed off cycle: {}", cycle);
+                continue;
+            }
+            if self.outcomes.is_in_flight(&cycle.id) {
+                debug!("Skipping cycle with a transaction in flight: {}", cycle);
+                continue;
+            }
+
+            let Some(token) = self.state.inventory.get(&cycle.entry_token) else {
+                continue;
+            };
+            // in-flight transactions entering with the same token leave less of
+            // it to trade
+            let available = self.available(token);
+            if available < token.min_amount_in || available.is_zero() {
+                debug!("Skipping cycle without inventory available: {}", cycle);
+                continue;
+            }
+            let Some(trade) = cycle.optimal_trade(available) else {
+                debug!("Skipping cycle that cannot be sized: {}", cycle);
+                continue;
+            };
+            if trade.amount_in < token.min_amount_in {
+                debug!("Skipping cycle sized below the minimum trade: {}", cycle);
+                continue;
+            }
+            debug!(
+                "Sized cycle {} at {} (expected out: {}, hops: {:?})",
+                cycle.id, trade.amount_in, trade.amount_out, trade.hop_amounts
+            );
 
-        let most_profitable_cycles = get_most_profitable_cycles(updated_cycles, 3);
+            // gas is paid in ether, and converted to the entry token at pool prices.
+            // The L1 data fee is only known once the transaction is built, and is
+            // deducted before the cycle is submitted
+            let Some(native_price) =
+                self.state
+                    .inventory
+                    .native_price(&token.address, &self.weth, &prices)
+            else {
+                debug!("Skipping cycle entering with an unpriced token: {}", cycle);
+                continue;
+            };
+            let costs =
+                CostModel::new(cycle.gas_estimate(), gas_price).with_native_price(native_price);
+            // cycles entering with different tokens are ranked by their expected
+            // net profit in the numeraire
+            let expected_profit = ProfitBreakdown::new(trade.amount_in, trade.amount_out, &costs)
+                .ok()
+                .and_then(|expected| {
+                    self.state
+                        .inventory
+                        .to_numeraire(&token.address, expected.net_profit, &prices)
+                });
+            let Some(expected_profit) = expected_profit else {
+                debug!("Skipping cycle that cannot be evaluated: {}", cycle);
+                continue;
+            };
+            candidates.push((expected_profit, cycle, trade.amount_in, costs));
+        }
+        candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
 
-        for cycle in most_profitable_cycles {
+        let executor_address = Address::from_str(&env::var("EXECUTOR_ADDRESS").unwrap())
+            .expect("Failed to parse executor address");
+        let gas_bidder =
+            GasBidder::new(self.client.clone()).with_l1_data_fee(self.chain.is_optimism());
+        for (expected_profit, cycle, amount_in, costs) in
+            candidates.into_iter().take(MAX_CYCLES_PER_BLOCK)
+        {
             let token_first = cycle.get_entry_token();
-            let amount_in = parse_units("0.001", 18)
-                .expect("Failed to parse amount in")
-                .into();
-            let amount_out = self
+            // earlier cycles of this block may have committed the same token
+            let available = self
+                .state
+                .inventory
+                .get(&token_first)
+                .map(|token| self.available(token))
+                .unwrap_or_default();
+            if amount_in > available {
+                debug!("Skipping cycle without inventory available: {}", cycle);
+                continue;
+            }
+            let amount_out = match self
                 .simulator
                 .as_ref()
                 .expect("Simulator must be loaded")
                 .simulate_route(token_first, amount_in, &cycle.amms)
                 .await
-                .unwrap_or_else(|e| {
+            {
+                Ok(amount_out) => amount_out,
+                Err(e) => {
                     warn!("Failed to simulate route: {}", e);
-                    U256::from(0)
-                });
+                    continue;
+                }
+            };
 
-            let scale_decimals = 5;
-            let scale_multiplier: U256 = parse_units("1", scale_decimals).unwrap().into();
-            let percentage_profit = (amount_out - amount_in) * scale_multiplier / amount_in;
-            let scaled_percentage: I256 = parse_units("-0.03", scale_decimals).unwrap().into();
-            let profitable = I256::try_from(percentage_profit).unwrap() >= scaled_percentage;
+            let breakdown = match ProfitBreakdown::new(amount_in, amount_out, &costs) {
+                Ok(breakdown) => breakdown,
+                Err(e) => {
+                    warn!("Failed to evaluate cycle {}: {}", cycle.id, e);
+                    continue;
+                }
+            };
+            if !breakdown.is_profitable() {
+                self.state.registry.record_evaluation(
+                    &cycle.id,
+                    block_number,
+                    breakdown.net_profit,
+                );
+                info!(
+                    "Negative cycle: {} - Profit: {} (gas: {}) 📉",
+                    cycle, breakdown.net_profit, breakdown.gas_cost
+                );
+                continue;
+            }
 
-            if profitable {
+            let (calldata, total_value) = match self
+                .get_cycle_calldata(token_first, amount_in, &cycle)
+                .await
+            {
+                Ok(calldata) => calldata,
+                Err(e) => {
+                    warn!("Failed to get cycle calldata: {}", e);
+                    continue;
+                }
+            };
+
+            // the L1 data fee is paid on top of the gas of the built transaction
+            let executor: BatchExecutorInstance<(), Arc<P>> =
+                BatchExecutorInstance::new(executor_address, self.client.clone());
+            let tx = executor
+                .batchCall(calldata.clone())
+                .value(total_value)
+                .into_transaction_request();
+            let l1_fee = match gas_bidder.l1_fee(&tx).await {
+                Ok(l1_fee) => l1_fee,
+                Err(e) => {
+                    warn!("Failed to get L1 data fee of cycle {}: {}", cycle.id, e);
+                    continue;
+                }
+            };
+            let costs = costs.with_l1_fee(l1_fee);
+            let breakdown = match ProfitBreakdown::new(amount_in, amount_out, &costs) {
+                Ok(breakdown) => breakdown,
+                Err(e) => {
+                    warn!("Failed to evaluate cycle {}: {}", cycle.id, e);
+                    continue;
+                }
+            };
+            self.state
+                .registry
+                .record_evaluation(&cycle.id, block_number, breakdown.net_profit);
+
+            if breakdown.is_profitable() {
                 info!(
-                    "Profitable cycle: {} - Profit: {:?} 💰",
-                    cycle,
-                    amount_out - amount_in
+                    "Profitable cycle: {} - Profit: {} (gas: {}, L1 fee: {}, expected: {} WETH) 💰",
+                    cycle, breakdown.net_profit, breakdown.gas_cost, l1_fee, expected_profit
                 );
-                let (calldata, total_value) = self
-                    .get_cycle_calldata(token_first, amount_in, &cycle)
-                    .await
-                    .unwrap_or_else(|e| {
-                        warn!("Failed to get cycle calldata: {}", e);
-                        (vec![], U256::from(0))
-                    });
-
-                let action = Action::SubmitEncodedTx(SubmitEncodedTx {
-                    calldata,
-                    total_value,
-                    gas_bid_info: None,
+                let submission =
+                    self.outcomes
+                        .record_submitted(&cycle.id, token_first, amount_in, block_number);
+                let action = Action::ExecuteCycle(CycleTx {
+                    cycle_id: cycle.id.clone(),
+                    submission,
+                    token: token_first,
+                    amount_in,
+                    tx: SubmitEncodedTx {
+                        calldata,
+                        total_value,
+                        expected_profit: amount_out
+                            .checked_sub(amount_in)
+                            .and_then(|profit| costs.to_native(profit)),
+                        gas_bid_info: None,
+                    },
                 });
                 info!("Submitting encoded tx... 📨");
                 actions.push(action);
             } else {
                 info!(
-                    "Negative cycle: {} - Loss: {:?} 📉",
-                    cycle,
-                    amount_in - amount_out
+                    "Negative cycle after L1 data fee: {} - Profit: {} (L1 fee: {}) 📉",
+                    cycle, breakdown.net_profit, l1_fee
                 );
             }
         }
 
         actions
     }
+
+    fn on_action_outcome(&mut self, outcome: ActionOutcome<Action>) {
+        let Action::ExecuteCycle(cycle_tx) = &outcome.action;
+        match &outcome.status {
+            ActionStatus::Submitted { tx_hash } => {
+                info!("Cycle {} submitted: {} 📬", cycle_tx.cycle_id, tx_hash)
+            }
+            ActionStatus::Mined { receipt } => info!(
+                "Cycle {} mined: {} (gas used: {}) ✅",
+                cycle_tx.cycle_id, receipt.transaction_hash, receipt.gas_used
+            ),
+            ActionStatus::Reverted { receipt, reason } => {
+                warn!(
+                    "Cycle {} reverted: {} (reason: {:?}) ❌",
+                    cycle_tx.cycle_id, receipt.transaction_hash, reason
+                );
+                self.state.registry.record_revert(&cycle_tx.cycle_id);
+            }
+            ActionStatus::Dropped { reason } | ActionStatus::Failed { reason } => {
+                warn!("Cycle {} not executed: {}", cycle_tx.cycle_id, reason)
+            }
+            ActionStatus::Executed => {}
+        }
+
+        self.outcomes.record_outcome(
+            &cycle_tx.cycle_id,
+            cycle_tx.submission,
+            &outcome.status,
+            self.state.block_number,
+        );
+        if outcome.status.is_final() {
+            info!(
+                "Inventory of {} committed to in-flight cycles: {}, gas spent: {}",
+                cycle_tx.token,
+                self.outcomes.committed(&cycle_tx.token),
+                self.outcomes.gas_spent()
+            );
+        }
+    }
 }
 
 // Private implementation details
@@ -384,6 +737,9 @@ impl<P: Provider + Clone> BaseArb<P> {
             return vec![];
         }
 
+        // keep the state before the update, in case the block is reorged out
+        self.state.record_pool_update(block_number, pool_address);
+
         match log.topics()[0] {
             topic if topic == IUniswapV2Pool::Swap::SIGNATURE_HASH => {
                 debug!("New uniswap v2 swap on pool {:?}", pool_address);
@@ -411,6 +767,24 @@ impl<P: Provider + Clone> BaseArb<P> {
         vec![]
     }
 
+    /// Rolls the pools back to the fork point of a reorg. The logs of the
+    /// canonical branch follow the reorg event, and are applied as usual.
+    async fn handle_reorg(&mut self, from_block: u64) {
+        match self.state.rollback(from_block) {
+            Ok(pools) => info!(
+                "Rolled back {} pools to block {} ⏪",
+                pools.len(),
+                self.state.block_number
+            ),
+            Err(e) => {
+                warn!("Failed to roll back pools: {}, resyncing", e);
+                if let Err(e) = self.sync_state().await {
+                    warn!("Failed to resync pools: {}", e);
+                }
+            }
+        }
+    }
+
     // handles sync for both uniswap v2 and ve33 pools
     async fn handle_v2_sync(&mut self, pool_address: Address, log: Log) -> Result<Vec<Cycle>> {
         if let Some(mut pool) = self.state.pools.get_mut(&pool_address) {
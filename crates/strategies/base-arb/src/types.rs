@@ -1,7 +1,13 @@
-use alloy::rpc::types::Log;
+use crate::outcomes::SubmissionId;
+use alloy::{
+    primitives::{Address, U256},
+    rpc::types::Log,
+};
 use amms::amm::{uniswap_v2::IUniswapV2Pair, uniswap_v3::IUniswapV3Pool};
 use engine::{
-    collectors::block_collector::NewBlock, executors::encoded_tx_executor::SubmitEncodedTx,
+    collectors::block_collector::NewBlock,
+    executors::encoded_tx_executor::SubmitEncodedTx,
+    types::{CollectorRestarted, EngineEvent, Reorg},
 };
 
 /// Core Event enum for the current strategy.
@@ -12,10 +18,59 @@ pub enum Event {
     UniswapV2Sync(IUniswapV2Pair::Sync),
     UniswapV3Swap(IUniswapV3Pool::Swap),
     Log(Log),
+    CollectorRestarted(CollectorRestarted),
+    Reorg(Reorg),
+    /// Every log of this block was received
+    BlockEnd(u64),
+}
+
+impl EngineEvent for Event {
+    fn block_number(&self) -> Option<u64> {
+        match self {
+            Event::NewBlock(block) => Some(block.number.to::<u64>()),
+            Event::Log(log) => log.block_number,
+            Event::CollectorRestarted(restarted) => restarted.resumed_block,
+            Event::BlockEnd(block) => Some(*block),
+            _ => None,
+        }
+    }
+
+    fn is_collector_restart(&self) -> bool {
+        matches!(self, Event::CollectorRestarted(_))
+    }
+
+    fn coalesce_key(&self) -> Option<Address> {
+        match self {
+            Event::Log(log) => Some(log.address()),
+            _ => None,
+        }
+    }
+
+    fn is_control(&self) -> bool {
+        matches!(
+            self,
+            Event::CollectorRestarted(_) | Event::Reorg(_) | Event::BlockEnd(_)
+        )
+    }
 }
 
 /// Core Action enum for the current strategy.
 #[derive(Debug, Clone)]
 pub enum Action {
-    SubmitEncodedTx(SubmitEncodedTx),
+    ExecuteCycle(CycleTx),
+}
+
+/// An encoded transaction executing an arbitrage cycle.
+#[derive(Debug, Clone)]
+pub struct CycleTx {
+    /// The id of the executed cycle
+    pub cycle_id: String,
+    /// The id the outcome of the transaction is recorded with
+    pub submission: SubmissionId,
+    /// The token the cycle starts and ends with
+    pub token: Address,
+    /// The amount of the entry token swapped into the cycle
+    pub amount_in: U256,
+    /// The transaction to submit
+    pub tx: SubmitEncodedTx,
 }
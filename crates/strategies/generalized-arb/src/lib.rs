@@ -10,5 +10,3 @@ pub mod strategy;
 pub mod types;
 
 pub mod state;
-
-pub mod profit;
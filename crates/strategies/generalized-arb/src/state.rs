@@ -4,32 +4,44 @@ use amms::amm::{AutomatedMarketMaker, AMM};
 use amms::errors::AMMError;
 use amms::sync;
 use dashmap::DashMap;
+use engine::collectors::pool_set::PoolSet;
 use shared::cycle::Cycle;
-use std::collections::{HashMap, HashSet};
+use shared::cycle_registry::CycleRegistry;
+use shared::inventory::{Inventory, InventoryToken, NumerairePrices};
+use shared::pool_history::PoolHistory;
+use shared::token_graph::{CyclePath, TokenGraph};
+use std::collections::BTreeSet;
 use std::sync::Arc;
 use tracing::info;
 
+/// Maximum number of pools in a trading cycle
+const MAX_CYCLE_HOPS: usize = 3;
+
 #[derive(Debug, Clone)]
 pub struct State<P: Provider> {
     provider: Arc<P>,
     pub block_number: u64,
     pub inactive_pools: DashMap<Address, AMM>,
     pub pools: DashMap<Address, AMM>,
-    pub pools_cycles_map: DashMap<Address, HashSet<String>>, // map of pool address to all cycles that include the pool
-    pub cycles: HashMap<String, Cycle>,                      // map of cycle id to cycle
-    pub inventory: Vec<Address>,                             // list of tokens that can be traded
+    pub pool_set: PoolSet, // addresses of the pools, shared with the log collectors
+    pub pool_history: PoolHistory, // recent pool states, to roll back reorgs
+    pub graph: TokenGraph, // cycles through the inventory, maintained as pools are added
+    pub registry: CycleRegistry, // stats of the cycles of the graph
+    pub inventory: Inventory, // tokens that can be traded, and their trade sizes
 }
 
 impl<P: Provider> State<P> {
-    pub fn new(provider: Arc<P>, inventory: Vec<Address>) -> Self {
+    pub fn new(provider: Arc<P>, inventory: Inventory) -> Self {
         Self {
             provider,
+            graph: TokenGraph::new(inventory.addresses(), MAX_CYCLE_HOPS),
+            registry: CycleRegistry::default(),
             inventory,
             inactive_pools: DashMap::new(),
             block_number: 0,
             pools: DashMap::new(),
-            pools_cycles_map: DashMap::new(),
-            cycles: HashMap::new(),
+            pool_set: PoolSet::new(),
+            pool_history: PoolHistory::default(),
         }
     }
 
@@ -39,147 +51,122 @@ impl<P: Provider> State<P> {
         }
     }
 
-    pub fn get_cycles(
-        pairs: &[AMM],
-        token_in: Address,
-        token_out: Address,
-        max_hops: i32,
-        current_pairs: &Vec<AMM>,
-        circles: &mut Vec<Cycle>,
-        seen: &mut HashSet<Address>,
-    ) -> Vec<Cycle> {
-        let mut circles_copy: Vec<Cycle> = circles.clone();
-
-        for pair in pairs {
-            let address = pair.address();
-            let tokens = pair.tokens();
-            let [token_a, token_b] = tokens.as_slice() else {
-                todo!();
-            };
-            if seen.contains(&address) {
-                continue;
+    pub fn set_pools(&mut self, amms: Vec<AMM>) {
+        self.pool_set.insert(amms.iter().map(|amm| amm.address()));
+        for amm in amms {
+            for cycle in self.graph.add_pool(&amm) {
+                self.registry.register(&cycle.id);
             }
+            self.pools.insert(amm.address(), amm);
+        }
+    }
 
-            let temp_out: Address;
-            if token_in == *token_a {
-                temp_out = *token_b;
-            } else if token_in == *token_b {
-                temp_out = *token_a;
-            } else {
-                continue;
-            }
+    /// Builds a cycle of the graph with the current state of its pools
+    fn build_cycle(&self, path: &CyclePath) -> Option<Cycle> {
+        path.to_cycle(|address| self.pools.get(address).map(|pool| pool.value().clone()))
+    }
 
-            let mut new_seen = seen.clone();
-            new_seen.insert(address);
-
-            if temp_out == token_out {
-                let mut pools = current_pairs.clone();
-                pools.push(pair.clone());
-                let cycle = Cycle::new(pools);
-                circles_copy.push(cycle);
-            } else if max_hops > 1 {
-                let mut new_pairs: Vec<AMM> = current_pairs.clone();
-                new_pairs.push(pair.clone());
-                circles_copy = Self::get_cycles(
-                    pairs,
-                    temp_out,
-                    token_out,
-                    max_hops - 1,
-                    &new_pairs,
-                    &mut circles_copy,
-                    &mut new_seen,
-                );
+    /// Adds a token to the inventory, or updates its trade sizes. The cycles
+    /// through the new entry token are searched among the tracked pools.
+    pub fn set_inventory_token(&mut self, token: InventoryToken) {
+        let is_new = !self.inventory.contains(&token.address);
+        self.inventory.insert(token);
+        if !is_new {
+            return;
+        }
+        // the entry tokens of the graph are fixed, so it is rebuilt with its pools
+        self.graph = TokenGraph::new(self.inventory.addresses(), MAX_CYCLE_HOPS);
+        for pool in self.pools.iter() {
+            for cycle in self.graph.add_pool(pool.value()) {
+                self.registry.register(&cycle.id);
             }
         }
+    }
+
+    /// Returns the profit of trading one whole entry token through `cycle`, as
+    /// a fraction of the input, whatever the decimals of the token.
+    fn profit_perc(&self, cycle: &Cycle) -> f64 {
+        self.inventory
+            .get(&cycle.entry_token)
+            .map_or(f64::NEG_INFINITY, |token| cycle.profit_perc(token.unit()))
+    }
 
-        circles_copy
+    /// Returns the prices of the inventory tokens in the numeraire, read from
+    /// the tracked pools trading them against it.
+    pub fn prices(&self) -> NumerairePrices {
+        let numeraire = self.inventory.numeraire().address;
+        let pools: Vec<AMM> = self
+            .graph
+            .pools_of(&numeraire)
+            .filter_map(|address| self.pools.get(address).map(|pool| pool.value().clone()))
+            .collect();
+        self.inventory.prices(pools.iter())
     }
 
-    pub fn set_pools(&self, amms: Vec<AMM>) {
+    /// Marks pools as inactive: their logs are no longer collected, and the
+    /// cycles going through them are dropped along with their stats.
+    pub fn set_inactive_pools(&mut self, amms: Vec<AMM>) {
+        self.pool_set.remove(amms.iter().map(|amm| amm.address()));
         for amm in amms {
-            self.pools.insert(amm.address(), amm);
+            let address = amm.address();
+            self.pools.remove(&address);
+            for cycle in self.graph.remove_pool(&address) {
+                self.registry.remove(&cycle.id);
+            }
+            self.inactive_pools.insert(address, amm);
         }
     }
 
-    pub fn set_inactive_pools(&self, amms: Vec<AMM>) {
-        for amm in amms {
-            self.inactive_pools.insert(amm.address(), amm);
+    /// Records the state of a pool before it is updated by a log of `block_number`,
+    /// so that the update can be rolled back if the block is reorged out.
+    pub fn record_pool_update(&mut self, block_number: u64, pool_address: Address) {
+        if let Some(pool) = self.pools.get(&pool_address) {
+            self.pool_history.record(block_number, pool.value());
+        }
+    }
+
+    /// Rolls the pools back to their state at the end of block `from_block - 1`,
+    /// the fork point of a reorg, and returns the restored pools. The logs of
+    /// the canonical branch are then applied as usual.
+    pub fn rollback(&mut self, from_block: u64) -> eyre::Result<Vec<AMM>> {
+        let pools = self.pool_history.rollback(from_block)?;
+        for pool in pools.iter() {
+            self.pools.insert(pool.address(), pool.clone());
         }
+        self.block_number = from_block.saturating_sub(1);
+        Ok(pools)
     }
 
     pub fn get_updated_cycles(&self, amms: Vec<AMM>) -> Vec<Cycle> {
         // get the cycles that include the amms
-        let mut cycles = vec![];
+        let mut paths = BTreeSet::new();
         for amm in amms {
-            tracing::info!("Getting pool cycles for: {:?}", amm.name());
-            let pool_address = amm.address();
-            tracing::info!("Pool address: {:?}", pool_address);
-            let pool_cycles = self.pools_cycles_map.get(&pool_address);
-            tracing::info!("Updated pool cycles: {:?}", pool_cycles);
-            if let Some(c) = pool_cycles {
-                // flat map of the cycle ids
-                let cycle_ids = c.iter().collect::<Vec<_>>();
-                for cycle_id in cycle_ids {
-                    let cycle = self.cycles.get(cycle_id).unwrap().clone();
-                    cycles.push(cycle);
-                }
-            }
+            paths.extend(self.graph.cycles_through(&amm.address()));
         }
+        tracing::info!("Updated pool cycles: {:?}", paths.len());
 
-        return cycles;
+        paths
+            .into_iter()
+            .filter_map(|path| self.build_cycle(path))
+            .collect()
     }
 
     pub fn update_cycles(&mut self) -> Vec<Cycle> {
-        let mut nb_cycles = 0;
-        let mut all_cycles = vec![];
-        for token in self.inventory.iter() {
-            let pools = self
-                .pools
-                .iter()
-                .map(|entry| entry.value().clone())
-                .collect::<Vec<_>>();
-
-            let cycles = Self::get_cycles(
-                &pools,
-                token.clone(),
-                token.clone(),
-                3,
-                &vec![],
-                &mut vec![],
-                &mut HashSet::new(),
-            );
-
-            all_cycles.extend(cycles);
-        }
-
+        let all_cycles: Vec<Cycle> = self
+            .graph
+            .cycles()
+            .filter_map(|path| self.build_cycle(path))
+            .collect();
         tracing::info!("Found {} potential cycles", all_cycles.len());
 
         let profit_threshold = -0.50;
         let potential_cycles: Vec<Cycle> = all_cycles
             .into_iter()
-            .filter(|cycle| cycle.get_profit_perc() > profit_threshold)
+            .filter(|cycle| self.profit_perc(cycle) > profit_threshold)
             .collect();
 
-        for cycle in potential_cycles.clone() {
-            nb_cycles += 1;
-            let id = cycle.id.clone();
-            self.cycles.insert(id.clone(), cycle.clone());
-
-            for pool in &cycle.amms {
-                let pool_address = pool.address();
-                let pool_cycles = self.pools_cycles_map.get_mut(&pool_address);
-                if let Some(mut c) = pool_cycles {
-                    c.insert(id.clone());
-                } else {
-                    self.pools_cycles_map
-                        .insert(pool_address, HashSet::from([id.clone()]));
-                }
-            }
-        }
-
-        info!("Found {} cycles", self.cycles.len());
-        info!("Nb cycles: {}", nb_cycles);
-        return potential_cycles;
+        info!("Nb cycles: {}", potential_cycles.len());
+        potential_cycles
     }
 
     pub async fn update_pools(&self) -> Result<(), AMMError> {
@@ -213,3 +200,49 @@ impl<P: Provider> State<P> {
             .collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::providers::ProviderBuilder;
+    use amms::amm::uniswap_v2::UniswapV2Pool;
+
+    fn pool(address: u8, token_a: u8, token_b: u8) -> AMM {
+        AMM::UniswapV2Pool(UniswapV2Pool {
+            address: Address::repeat_byte(address),
+            token_a: Address::repeat_byte(token_a),
+            token_b: Address::repeat_byte(token_b),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_inactive_pools_drop_their_cycles() {
+        let provider = ProviderBuilder::new().on_http("http://localhost:8545".parse().unwrap());
+        let inventory = Inventory::new(InventoryToken::new(Address::repeat_byte(1), 18));
+        let mut state = State::new(Arc::new(provider), inventory);
+        // a triangle through the entry token 1, and a second pool between 1 and 2
+        state.set_pools(vec![
+            pool(10, 1, 2),
+            pool(11, 2, 3),
+            pool(12, 3, 1),
+            pool(13, 1, 2),
+        ]);
+        let inactive = Address::repeat_byte(11);
+        let cycles: Vec<CyclePath> = state.graph.cycles_through(&inactive).cloned().collect();
+        assert!(!cycles.is_empty());
+
+        state.set_inactive_pools(vec![pool(11, 2, 3)]);
+
+        assert!(!state.pools.contains_key(&inactive));
+        assert!(state.inactive_pools.contains_key(&inactive));
+        assert!(!state.pool_set.addresses().contains(&inactive));
+        assert_eq!(state.graph.cycles_through(&inactive).count(), 0);
+        assert!(cycles
+            .iter()
+            .all(|cycle| !state.registry.contains(&cycle.id)));
+        // only the cycles between the two pools of 1 and 2 are left
+        assert_eq!(state.graph.cycles().count(), 2);
+        assert_eq!(state.registry.len(), 2);
+    }
+}
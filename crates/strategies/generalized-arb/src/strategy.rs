@@ -2,7 +2,12 @@ use crate::state::State;
 
 use super::types::{Action, Event};
 use addressbook::Addressbook;
-use alloy::{primitives::Address, providers::Provider, rpc::types::Log, sol_types::SolEvent};
+use alloy::{
+    primitives::{Address, U256},
+    providers::Provider,
+    rpc::types::Log,
+    sol_types::SolEvent,
+};
 use alloy_chains::Chain;
 use amms::{
     amm::{
@@ -33,9 +38,12 @@ use db::{
 };
 use db::{models::NewDbUniV3Pool, queries::exchange::get_exchanges_by_chain};
 use diesel::PgConnection;
-use engine::types::Strategy;
+use engine::{collectors::pool_set::PoolSet, executors::gas_bidder::GasBidder, types::Strategy};
 use eyre::Result;
-use shared::pool_helpers::db_pools_to_amms;
+use shared::cycle::{CostModel, Cycle};
+use shared::inventory::{Inventory, InventoryToken};
+use shared::pool_helpers::{db_pools_to_amms, inactive_amms, MIN_ACTIVE_POOL_USD};
+use std::collections::HashMap;
 use std::sync::Arc;
 use tracing::{debug, info, warn};
 
@@ -45,6 +53,11 @@ pub struct GeneralizedArb<P: Provider + Clone> {
     pub client: Arc<P>,
     pub state: State<P>,
     pub db_url: String,
+    /// The wrapped native token, which gas costs are converted from
+    pub weth: Address,
+    /// The USD value under which the liquidity of a pool is too low to trade
+    /// through, and the pool is set inactive
+    pub min_pool_usd: f64,
 }
 
 impl<P: Provider + Clone> GeneralizedArb<P> {
@@ -54,10 +67,45 @@ impl<P: Provider + Clone> GeneralizedArb<P> {
         Self {
             chain,
             client: client.clone(),
-            state: State::new(client.clone(), vec![weth]),
+            // profits are compared in WETH, and only WETH-rooted cycles are
+            // searched until other tokens are added to the inventory
+            state: State::new(
+                client.clone(),
+                Inventory::new(InventoryToken::new(weth, 18)),
+            ),
             db_url,
+            weth,
+            min_pool_usd: MIN_ACTIVE_POOL_USD,
         }
     }
+
+    /// Sets the largest amount of WETH traded through a single cycle.
+    pub fn with_max_amount_in(mut self, max_amount_in: U256) -> Self {
+        let weth = self.state.inventory.numeraire().clone();
+        self.state
+            .set_inventory_token(weth.with_max_amount_in(max_amount_in));
+        self
+    }
+
+    /// Adds a token to the inventory, so that the cycles starting and ending
+    /// with it are traded, within its trade sizes.
+    pub fn with_inventory_token(mut self, token: InventoryToken) -> Self {
+        self.state.set_inventory_token(token);
+        self
+    }
+
+    /// Sets the USD value under which the liquidity of a pool is too low to
+    /// trade through. Pools under it are set inactive when the state is synced.
+    pub fn with_min_pool_usd(mut self, min_pool_usd: f64) -> Self {
+        self.min_pool_usd = min_pool_usd;
+        self
+    }
+
+    /// Returns the set of pools tracked by the strategy, to scope the log
+    /// collectors to. It is filled once the strategy loads its pools.
+    pub fn pool_set(&self) -> PoolSet {
+        self.state.pool_set.clone()
+    }
 }
 
 #[async_trait]
@@ -147,6 +195,7 @@ impl<P: Provider + Clone> Strategy<Event, Action> for GeneralizedArb<P> {
             .update_pools()
             .await
             .map_err(|e| eyre::eyre!("Failed to sync pools: {}", e))?;
+        self.set_inactive_pools().await;
 
         Ok(())
     }
@@ -173,7 +222,35 @@ impl<P: Provider + Clone> Strategy<Event, Action> for GeneralizedArb<P> {
                 );
                 return vec![];
             }
-            Event::UniswapV2Sync(_) => {
+            Event::UniswapV2Sync(_) | Event::BlockEnd(_) => {
+                return vec![];
+            }
+            Event::CollectorRestarted(restarted) => {
+                warn!(
+                    "{} restarted (last block: {:?})",
+                    restarted.collector, restarted.last_block
+                );
+                return vec![];
+            }
+            Event::Reorg(reorg) => {
+                warn!(
+                    "Reorg of blocks {}..={} detected by {}",
+                    reorg.from_block, reorg.to_block, reorg.collector
+                );
+                // the logs of the canonical branch follow, and are applied as usual
+                match self.state.rollback(reorg.from_block) {
+                    Ok(pools) => info!(
+                        "Rolled back {} pools to block {}",
+                        pools.len(),
+                        self.state.block_number
+                    ),
+                    Err(e) => {
+                        warn!("Failed to roll back pools: {}, resyncing", e);
+                        if let Err(e) = self.sync_state().await {
+                            warn!("Failed to resync pools: {}", e);
+                        }
+                    }
+                }
                 return vec![];
             }
             Event::Log(log) => {
@@ -181,6 +258,7 @@ impl<P: Provider + Clone> Strategy<Event, Action> for GeneralizedArb<P> {
                 let block_number = log.block_number.unwrap();
                 let mut conn = establish_connection(&self.db_url);
                 self.state.update_block_number(block_number).await.unwrap();
+                self.state.record_pool_update(block_number, pool_address);
 
                 if log.topics()[0] == IUniswapV2Pair::Swap::SIGNATURE_HASH {
                     // self.handle_uniswap_v2_swap(&mut conn, pool_address, log.clone())
@@ -213,6 +291,107 @@ impl<P: Provider + Clone> Strategy<Event, Action> for GeneralizedArb<P> {
 }
 
 impl<P: Provider + Clone> GeneralizedArb<P> {
+    /// Sets the pools whose liquidity fell under the minimum value inactive,
+    /// dropping the cycles going through them.
+    async fn set_inactive_pools(&mut self) {
+        let pools = self.state.get_all_pools().await;
+        let inactive = match inactive_amms(self.chain, self.min_pool_usd, &pools).await {
+            Ok(inactive) => inactive,
+            Err(e) => {
+                warn!("Failed to check the liquidity of the pools: {}", e);
+                return;
+            }
+        };
+        if inactive.is_empty() {
+            return;
+        }
+        info!("Setting {} pools inactive", inactive.len());
+        let amms = pools
+            .into_iter()
+            .filter(|amm| inactive.contains(&amm.address()))
+            .collect();
+        self.state.set_inactive_pools(amms);
+    }
+
+    /// Evaluates the cycles at their optimal size, net of gas costs, and
+    /// reports the ones worth executing.
+    async fn evaluate_cycles(&self, cycles: &[Cycle]) {
+        let gas_price = match self.client.get_gas_price().await {
+            Ok(gas_price) => gas_price,
+            Err(e) => {
+                warn!("Failed to get gas price: {}", e);
+                return;
+            }
+        };
+
+        // the L1 data fee mostly depends on the calldata size, so it is
+        // estimated once per size
+        let gas_bidder =
+            GasBidder::new(self.client.clone()).with_l1_data_fee(self.chain.is_optimism());
+        let mut l1_fees: HashMap<usize, U256> = HashMap::new();
+
+        let prices = self.state.prices();
+        let inventory = &self.state.inventory;
+        for cycle in cycles {
+            let Some(token) = inventory.get(&cycle.entry_token) else {
+                continue;
+            };
+            let Some(trade) = cycle.optimal_trade(token.max_amount_in) else {
+                debug!("{}: cannot be sized", cycle);
+                continue;
+            };
+            if trade.amount_in < token.min_amount_in {
+                debug!("{}: sized below the minimum trade", cycle);
+                continue;
+            }
+            // gas is paid in ether, and converted to the entry token at pool prices
+            let Some(native_price) = inventory.native_price(&token.address, &self.weth, &prices)
+            else {
+                debug!("{}: no price for the entry token", cycle);
+                continue;
+            };
+            let calldata_size = cycle.calldata_size_estimate();
+            let l1_fee = match l1_fees.get(&calldata_size) {
+                Some(l1_fee) => *l1_fee,
+                None => match gas_bidder.estimate_l1_fee(calldata_size).await {
+                    Ok(l1_fee) => {
+                        l1_fees.insert(calldata_size, l1_fee);
+                        l1_fee
+                    }
+                    Err(e) => {
+                        warn!("Failed to estimate L1 data fee: {}", e);
+                        continue;
+                    }
+                },
+            };
+            let costs = CostModel::new(cycle.gas_estimate(), gas_price)
+                .with_l1_fee(l1_fee)
+                .with_native_price(native_price);
+            let breakdown = cycle.evaluate(trade.amount_in, &costs);
+            if let Ok(breakdown) = &breakdown {
+                self.state.registry.record_evaluation(
+                    &cycle.id,
+                    self.state.block_number,
+                    breakdown.net_profit,
+                );
+            }
+            match breakdown {
+                Ok(breakdown) if breakdown.is_profitable() => info!(
+                    "{}: Net profit: {} for {} in ({:?} WETH) 💰",
+                    cycle,
+                    breakdown.net_profit,
+                    breakdown.amount_in,
+                    inventory.to_numeraire(&token.address, breakdown.net_profit, &prices)
+                ),
+                Ok(breakdown) => debug!(
+                    "{}: Net profit: {} for {} in",
+                    cycle, breakdown.net_profit, breakdown.amount_in
+                ),
+                Err(e) => warn!("Failed to evaluate cycle {}: {}", cycle.id, e),
+            }
+        }
+    }
+
     async fn handle_uniswap_v2_sync(
         &self,
         mut conn: &mut PgConnection,
@@ -238,9 +417,7 @@ impl<P: Provider + Clone> GeneralizedArb<P> {
             let amm_slice: &mut [AMM] = std::slice::from_mut(pool);
             let updated_cycles = self.state.get_updated_cycles(amm_slice.to_vec());
             info!("Found {} updated cycles", updated_cycles.len());
-            for cycle in updated_cycles {
-                info!("{}: Profit: {}", cycle, cycle.get_profit_perc());
-            }
+            self.evaluate_cycles(&updated_cycles).await;
 
             return Ok(());
         }
@@ -287,9 +464,7 @@ impl<P: Provider + Clone> GeneralizedArb<P> {
             let amm_slice: &mut [AMM] = std::slice::from_mut(pool);
             let updated_cycles = self.state.get_updated_cycles(amm_slice.to_vec());
             info!("Found {} updated cycles", updated_cycles.len());
-            for cycle in updated_cycles {
-                info!("{}: Profit: {}", cycle, cycle.get_profit_perc());
-            }
+            self.evaluate_cycles(&updated_cycles).await;
 
             return Ok(());
         }
@@ -1,7 +1,9 @@
-use alloy::rpc::types::Log;
+use alloy::{primitives::Address, rpc::types::Log};
 use amms::amm::{uniswap_v2::IUniswapV2Pair, uniswap_v3::IUniswapV3Pool};
 use engine::{
-    collectors::block_collector::NewBlock, executors::mempool_executor::SubmitTxToMempool,
+    collectors::block_collector::NewBlock,
+    executors::mempool_executor::SubmitTxToMempool,
+    types::{CollectorRestarted, EngineEvent, Reorg},
 };
 
 /// Core Event enum for the current strategy.
@@ -12,6 +14,40 @@ pub enum Event {
     UniswapV2Sync(IUniswapV2Pair::Sync),
     UniswapV3Swap(IUniswapV3Pool::Swap),
     Log(Log),
+    CollectorRestarted(CollectorRestarted),
+    Reorg(Reorg),
+    /// Every log of this block was received
+    BlockEnd(u64),
+}
+
+impl EngineEvent for Event {
+    fn block_number(&self) -> Option<u64> {
+        match self {
+            Event::NewBlock(block) => Some(block.number.to::<u64>()),
+            Event::Log(log) => log.block_number,
+            Event::CollectorRestarted(restarted) => restarted.resumed_block,
+            Event::BlockEnd(block) => Some(*block),
+            _ => None,
+        }
+    }
+
+    fn is_collector_restart(&self) -> bool {
+        matches!(self, Event::CollectorRestarted(_))
+    }
+
+    fn coalesce_key(&self) -> Option<Address> {
+        match self {
+            Event::Log(log) => Some(log.address()),
+            _ => None,
+        }
+    }
+
+    fn is_control(&self) -> bool {
+        matches!(
+            self,
+            Event::CollectorRestarted(_) | Event::Reorg(_) | Event::BlockEnd(_)
+        )
+    }
 }
 
 /// Core Action enum for the current strategy.